@@ -2053,690 +2053,695 @@ pub enum XKeySym {
 }
 
 impl XKeySym {
+    /// The raw X11 keysym value for this keysym
+    pub fn as_u32(&self) -> u32 {
+        match self {
+            XKeySym::XK_BackSpace => 0xff08,
+            XKeySym::XK_Tab => 0xff09,
+            XKeySym::XK_Linefeed => 0xff0a,
+            XKeySym::XK_Clear => 0xff0b,
+            XKeySym::XK_Return => 0xff0d,
+            XKeySym::XK_Pause => 0xff13,
+            XKeySym::XK_Scroll_Lock => 0xff14,
+            XKeySym::XK_Sys_Req => 0xff15,
+            XKeySym::XK_Escape => 0xff1b,
+            XKeySym::XK_Delete => 0xffff,
+            XKeySym::XK_Home => 0xff50,
+            XKeySym::XK_Left => 0xff51,
+            XKeySym::XK_Up => 0xff52,
+            XKeySym::XK_Right => 0xff53,
+            XKeySym::XK_Down => 0xff54,
+            XKeySym::XK_Prior => 0xff55,
+            XKeySym::XK_Page_Up => 0xff55,
+            XKeySym::XK_Next => 0xff56,
+            XKeySym::XK_Page_Down => 0xff56,
+            XKeySym::XK_End => 0xff57,
+            XKeySym::XK_Begin => 0xff58,
+            XKeySym::XK_Select => 0xff60,
+            XKeySym::XK_Print => 0xff61,
+            XKeySym::XK_Execute => 0xff62,
+            XKeySym::XK_Insert => 0xff63,
+            XKeySym::XK_Undo => 0xff65,
+            XKeySym::XK_Redo => 0xff66,
+            XKeySym::XK_Menu => 0xff67,
+            XKeySym::XK_Find => 0xff68,
+            XKeySym::XK_Cancel => 0xff69,
+            XKeySym::XK_Help => 0xff6a,
+            XKeySym::XK_Break => 0xff6b,
+            XKeySym::XK_Mode_switch => 0xff7e,
+            XKeySym::XK_script_switch => 0xff7e,
+            XKeySym::XK_Num_Lock => 0xff7f,
+            XKeySym::XK_KP_Space => 0xff80,
+            XKeySym::XK_KP_Tab => 0xff89,
+            XKeySym::XK_KP_Enter => 0xff8d,
+            XKeySym::XK_KP_F1 => 0xff91,
+            XKeySym::XK_KP_F2 => 0xff92,
+            XKeySym::XK_KP_F3 => 0xff93,
+            XKeySym::XK_KP_F4 => 0xff94,
+            XKeySym::XK_KP_Home => 0xff95,
+            XKeySym::XK_KP_Left => 0xff96,
+            XKeySym::XK_KP_Up => 0xff97,
+            XKeySym::XK_KP_Right => 0xff98,
+            XKeySym::XK_KP_Down => 0xff99,
+            XKeySym::XK_KP_Prior => 0xff9a,
+            XKeySym::XK_KP_Page_Up => 0xff9a,
+            XKeySym::XK_KP_Next => 0xff9b,
+            XKeySym::XK_KP_Page_Down => 0xff9b,
+            XKeySym::XK_KP_End => 0xff9c,
+            XKeySym::XK_KP_Begin => 0xff9d,
+            XKeySym::XK_KP_Insert => 0xff9e,
+            XKeySym::XK_KP_Delete => 0xff9f,
+            XKeySym::XK_KP_Equal => 0xffbd,
+            XKeySym::XK_KP_Multiply => 0xffaa,
+            XKeySym::XK_KP_Add => 0xffab,
+            XKeySym::XK_KP_Separator => 0xffac,
+            XKeySym::XK_KP_Subtract => 0xffad,
+            XKeySym::XK_KP_Decimal => 0xffae,
+            XKeySym::XK_KP_Divide => 0xffaf,
+            XKeySym::XK_KP_0 => 0xffb0,
+            XKeySym::XK_KP_1 => 0xffb1,
+            XKeySym::XK_KP_2 => 0xffb2,
+            XKeySym::XK_KP_3 => 0xffb3,
+            XKeySym::XK_KP_4 => 0xffb4,
+            XKeySym::XK_KP_5 => 0xffb5,
+            XKeySym::XK_KP_6 => 0xffb6,
+            XKeySym::XK_KP_7 => 0xffb7,
+            XKeySym::XK_KP_8 => 0xffb8,
+            XKeySym::XK_KP_9 => 0xffb9,
+            XKeySym::XK_F1 => 0xffbe,
+            XKeySym::XK_F2 => 0xffbf,
+            XKeySym::XK_F3 => 0xffc0,
+            XKeySym::XK_F4 => 0xffc1,
+            XKeySym::XK_F5 => 0xffc2,
+            XKeySym::XK_F6 => 0xffc3,
+            XKeySym::XK_F7 => 0xffc4,
+            XKeySym::XK_F8 => 0xffc5,
+            XKeySym::XK_F9 => 0xffc6,
+            XKeySym::XK_F10 => 0xffc7,
+            XKeySym::XK_F11 => 0xffc8,
+            XKeySym::XK_L1 => 0xffc8,
+            XKeySym::XK_F12 => 0xffc9,
+            XKeySym::XK_L2 => 0xffc9,
+            XKeySym::XK_F13 => 0xffca,
+            XKeySym::XK_L3 => 0xffca,
+            XKeySym::XK_F14 => 0xffcb,
+            XKeySym::XK_L4 => 0xffcb,
+            XKeySym::XK_F15 => 0xffcc,
+            XKeySym::XK_L5 => 0xffcc,
+            XKeySym::XK_F16 => 0xffcd,
+            XKeySym::XK_L6 => 0xffcd,
+            XKeySym::XK_F17 => 0xffce,
+            XKeySym::XK_L7 => 0xffce,
+            XKeySym::XK_F18 => 0xffcf,
+            XKeySym::XK_L8 => 0xffcf,
+            XKeySym::XK_F19 => 0xffd0,
+            XKeySym::XK_L9 => 0xffd0,
+            XKeySym::XK_F20 => 0xffd1,
+            XKeySym::XK_L10 => 0xffd1,
+            XKeySym::XK_F21 => 0xffd2,
+            XKeySym::XK_R1 => 0xffd2,
+            XKeySym::XK_F22 => 0xffd3,
+            XKeySym::XK_R2 => 0xffd3,
+            XKeySym::XK_F23 => 0xffd4,
+            XKeySym::XK_R3 => 0xffd4,
+            XKeySym::XK_F24 => 0xffd5,
+            XKeySym::XK_R4 => 0xffd5,
+            XKeySym::XK_F25 => 0xffd6,
+            XKeySym::XK_R5 => 0xffd6,
+            XKeySym::XK_F26 => 0xffd7,
+            XKeySym::XK_R6 => 0xffd7,
+            XKeySym::XK_F27 => 0xffd8,
+            XKeySym::XK_R7 => 0xffd8,
+            XKeySym::XK_F28 => 0xffd9,
+            XKeySym::XK_R8 => 0xffd9,
+            XKeySym::XK_F29 => 0xffda,
+            XKeySym::XK_R9 => 0xffda,
+            XKeySym::XK_F30 => 0xffdb,
+            XKeySym::XK_R10 => 0xffdb,
+            XKeySym::XK_F31 => 0xffdc,
+            XKeySym::XK_R11 => 0xffdc,
+            XKeySym::XK_F32 => 0xffdd,
+            XKeySym::XK_R12 => 0xffdd,
+            XKeySym::XK_F33 => 0xffde,
+            XKeySym::XK_R13 => 0xffde,
+            XKeySym::XK_F34 => 0xffdf,
+            XKeySym::XK_R14 => 0xffdf,
+            XKeySym::XK_F35 => 0xffe0,
+            XKeySym::XK_R15 => 0xffe0,
+            XKeySym::XK_Shift_L => 0xffe1,
+            XKeySym::XK_Shift_R => 0xffe2,
+            XKeySym::XK_Control_L => 0xffe3,
+            XKeySym::XK_Control_R => 0xffe4,
+            XKeySym::XK_Caps_Lock => 0xffe5,
+            XKeySym::XK_Shift_Lock => 0xffe6,
+            XKeySym::XK_Meta_L => 0xffe7,
+            XKeySym::XK_Meta_R => 0xffe8,
+            XKeySym::XK_Alt_L => 0xffe9,
+            XKeySym::XK_Alt_R => 0xffea,
+            XKeySym::XK_Super_L => 0xffeb,
+            XKeySym::XK_Super_R => 0xffec,
+            XKeySym::XK_Hyper_L => 0xffed,
+            XKeySym::XK_Hyper_R => 0xffee,
+            XKeySym::XK_ISO_Lock => 0xfe01,
+            XKeySym::XK_ISO_Level2_Latch => 0xfe02,
+            XKeySym::XK_ISO_Level3_Shift => 0xfe03,
+            XKeySym::XK_ISO_Level3_Latch => 0xfe04,
+            XKeySym::XK_ISO_Level3_Lock => 0xfe05,
+            XKeySym::XK_ISO_Level5_Shift => 0xfe11,
+            XKeySym::XK_ISO_Level5_Latch => 0xfe12,
+            XKeySym::XK_ISO_Level5_Lock => 0xfe13,
+            XKeySym::XK_ISO_Left_Tab => 0xfe20,
+            XKeySym::XK_ISO_Partial_Space_Left => 0xfe25,
+            XKeySym::XK_ISO_Partial_Space_Right => 0xfe26,
+            XKeySym::XK_ISO_Set_Margin_Left => 0xfe27,
+            XKeySym::XK_ISO_Set_Margin_Right => 0xfe28,
+            XKeySym::XK_ISO_Continuous_Underline => 0xfe30,
+            XKeySym::XK_ISO_Discontinuous_Underline => 0xfe31,
+            XKeySym::XK_ISO_Emphasize => 0xfe32,
+            XKeySym::XK_ISO_Center_Object => 0xfe33,
+            XKeySym::XK_ISO_Enter => 0xfe34,
+            XKeySym::XK_Terminate_Server => 0xfed5,
+            XKeySym::XK_ch => 0xfea0,
+            XKeySym::XK_Ch => 0xfea1,
+            XKeySym::XK_CH => 0xfea2,
+            XKeySym::XK_c_h => 0xfea3,
+            XKeySym::XK_C_h => 0xfea4,
+            XKeySym::XK_C_H => 0xfea5,
+            XKeySym::XK_3270_Duplicate => 0xfd01,
+            XKeySym::XK_3270_FieldMark => 0xfd02,
+            XKeySym::XK_3270_Right2 => 0xfd03,
+            XKeySym::XK_3270_Left2 => 0xfd04,
+            XKeySym::XK_3270_BackTab => 0xfd05,
+            XKeySym::XK_3270_EraseEOF => 0xfd06,
+            XKeySym::XK_3270_EraseInput => 0xfd07,
+            XKeySym::XK_3270_Reset => 0xfd08,
+            XKeySym::XK_3270_Quit => 0xfd09,
+            XKeySym::XK_3270_PA1 => 0xfd0a,
+            XKeySym::XK_3270_PA2 => 0xfd0b,
+            XKeySym::XK_3270_PA3 => 0xfd0c,
+            XKeySym::XK_3270_Test => 0xfd0d,
+            XKeySym::XK_3270_Attn => 0xfd0e,
+            XKeySym::XK_3270_CursorBlink => 0xfd0f,
+            XKeySym::XK_3270_AltCursor => 0xfd10,
+            XKeySym::XK_3270_KeyClick => 0xfd11,
+            XKeySym::XK_3270_Jump => 0xfd12,
+            XKeySym::XK_3270_Ident => 0xfd13,
+            XKeySym::XK_3270_Rule => 0xfd14,
+            XKeySym::XK_3270_Copy => 0xfd15,
+            XKeySym::XK_3270_Play => 0xfd16,
+            XKeySym::XK_3270_Setup => 0xfd17,
+            XKeySym::XK_3270_Record => 0xfd18,
+            XKeySym::XK_3270_DeleteWord => 0xfd1a,
+            XKeySym::XK_3270_ExSelect => 0xfd1b,
+            XKeySym::XK_3270_CursorSelect => 0xfd1c,
+            XKeySym::XK_3270_Enter => 0xfd1e,
+            XKeySym::XK_space => 0x0020,
+            XKeySym::XK_exclam => 0x0021,
+            XKeySym::XK_quotedbl => 0x0022,
+            XKeySym::XK_numbersign => 0x0023,
+            XKeySym::XK_dollar => 0x0024,
+            XKeySym::XK_percent => 0x0025,
+            XKeySym::XK_ampersand => 0x0026,
+            XKeySym::XK_apostrophe => 0x0027,
+            XKeySym::XK_quoteright => 0x0027,
+            XKeySym::XK_parenleft => 0x0028,
+            XKeySym::XK_parenright => 0x0029,
+            XKeySym::XK_asterisk => 0x002a,
+            XKeySym::XK_plus => 0x002b,
+            XKeySym::XK_comma => 0x002c,
+            XKeySym::XK_minus => 0x002d,
+            XKeySym::XK_period => 0x002e,
+            XKeySym::XK_slash => 0x002f,
+            XKeySym::XK_0 => 0x0030,
+            XKeySym::XK_1 => 0x0031,
+            XKeySym::XK_2 => 0x0032,
+            XKeySym::XK_3 => 0x0033,
+            XKeySym::XK_4 => 0x0034,
+            XKeySym::XK_5 => 0x0035,
+            XKeySym::XK_6 => 0x0036,
+            XKeySym::XK_7 => 0x0037,
+            XKeySym::XK_8 => 0x0038,
+            XKeySym::XK_9 => 0x0039,
+            XKeySym::XK_colon => 0x003a,
+            XKeySym::XK_semicolon => 0x003b,
+            XKeySym::XK_less => 0x003c,
+            XKeySym::XK_equal => 0x003d,
+            XKeySym::XK_greater => 0x003e,
+            XKeySym::XK_question => 0x003f,
+            XKeySym::XK_at => 0x0040,
+            XKeySym::XK_A => 0x0041,
+            XKeySym::XK_B => 0x0042,
+            XKeySym::XK_C => 0x0043,
+            XKeySym::XK_D => 0x0044,
+            XKeySym::XK_E => 0x0045,
+            XKeySym::XK_F => 0x0046,
+            XKeySym::XK_G => 0x0047,
+            XKeySym::XK_H => 0x0048,
+            XKeySym::XK_I => 0x0049,
+            XKeySym::XK_J => 0x004a,
+            XKeySym::XK_K => 0x004b,
+            XKeySym::XK_L => 0x004c,
+            XKeySym::XK_M => 0x004d,
+            XKeySym::XK_N => 0x004e,
+            XKeySym::XK_O => 0x004f,
+            XKeySym::XK_P => 0x0050,
+            XKeySym::XK_Q => 0x0051,
+            XKeySym::XK_R => 0x0052,
+            XKeySym::XK_S => 0x0053,
+            XKeySym::XK_T => 0x0054,
+            XKeySym::XK_U => 0x0055,
+            XKeySym::XK_V => 0x0056,
+            XKeySym::XK_W => 0x0057,
+            XKeySym::XK_X => 0x0058,
+            XKeySym::XK_Y => 0x0059,
+            XKeySym::XK_Z => 0x005a,
+            XKeySym::XK_bracketleft => 0x005b,
+            XKeySym::XK_backslash => 0x005c,
+            XKeySym::XK_bracketright => 0x005d,
+            XKeySym::XK_asciicircum => 0x005e,
+            XKeySym::XK_underscore => 0x005f,
+            XKeySym::XK_grave => 0x0060,
+            XKeySym::XK_quoteleft => 0x0060,
+            XKeySym::XK_a => 0x0061,
+            XKeySym::XK_b => 0x0062,
+            XKeySym::XK_c => 0x0063,
+            XKeySym::XK_d => 0x0064,
+            XKeySym::XK_e => 0x0065,
+            XKeySym::XK_f => 0x0066,
+            XKeySym::XK_g => 0x0067,
+            XKeySym::XK_h => 0x0068,
+            XKeySym::XK_i => 0x0069,
+            XKeySym::XK_j => 0x006a,
+            XKeySym::XK_k => 0x006b,
+            XKeySym::XK_l => 0x006c,
+            XKeySym::XK_m => 0x006d,
+            XKeySym::XK_n => 0x006e,
+            XKeySym::XK_o => 0x006f,
+            XKeySym::XK_p => 0x0070,
+            XKeySym::XK_q => 0x0071,
+            XKeySym::XK_r => 0x0072,
+            XKeySym::XK_s => 0x0073,
+            XKeySym::XK_t => 0x0074,
+            XKeySym::XK_u => 0x0075,
+            XKeySym::XK_v => 0x0076,
+            XKeySym::XK_w => 0x0077,
+            XKeySym::XK_x => 0x0078,
+            XKeySym::XK_y => 0x0079,
+            XKeySym::XK_z => 0x007a,
+            XKeySym::XK_braceleft => 0x007b,
+            XKeySym::XK_bar => 0x007c,
+            XKeySym::XK_braceright => 0x007d,
+            XKeySym::XK_asciitilde => 0x007e,
+            XKeySym::XK_nobreakspace => 0x00a0,
+            XKeySym::XK_exclamdown => 0x00a1,
+            XKeySym::XK_cent => 0x00a2,
+            XKeySym::XK_sterling => 0x00a3,
+            XKeySym::XK_currency => 0x00a4,
+            XKeySym::XK_yen => 0x00a5,
+            XKeySym::XK_brokenbar => 0x00a6,
+            XKeySym::XK_section => 0x00a7,
+            XKeySym::XK_diaeresis => 0x00a8,
+            XKeySym::XK_copyright => 0x00a9,
+            XKeySym::XK_ordfeminine => 0x00aa,
+            XKeySym::XK_guillemotleft => 0x00ab,
+            XKeySym::XK_notsign => 0x00ac,
+            XKeySym::XK_hyphen => 0x00ad,
+            XKeySym::XK_registered => 0x00ae,
+            XKeySym::XK_macron => 0x00af,
+            XKeySym::XK_degree => 0x00b0,
+            XKeySym::XK_plusminus => 0x00b1,
+            XKeySym::XK_acute => 0x00b4,
+            XKeySym::XK_mu => 0x00b5,
+            XKeySym::XK_paragraph => 0x00b6,
+            XKeySym::XK_periodcentered => 0x00b7,
+            XKeySym::XK_cedilla => 0x00b8,
+            XKeySym::XK_masculine => 0x00ba,
+            XKeySym::XK_guillemotright => 0x00bb,
+            XKeySym::XK_onequarter => 0x00bc,
+            XKeySym::XK_onehalf => 0x00bd,
+            XKeySym::XK_threequarters => 0x00be,
+            XKeySym::XK_questiondown => 0x00bf,
+            XKeySym::XK_Aacute => 0x00c1,
+            XKeySym::XK_Atilde => 0x00c3,
+            XKeySym::XK_Adiaeresis => 0x00c4,
+            XKeySym::XK_Aring => 0x00c5,
+            XKeySym::XK_AE => 0x00c6,
+            XKeySym::XK_Ccedilla => 0x00c7,
+            XKeySym::XK_Eacute => 0x00c9,
+            XKeySym::XK_Ediaeresis => 0x00cb,
+            XKeySym::XK_Iacute => 0x00cd,
+            XKeySym::XK_Idiaeresis => 0x00cf,
+            XKeySym::XK_ETH => 0x00d0,
+            XKeySym::XK_Eth => 0x00d0,
+            XKeySym::XK_Ntilde => 0x00d1,
+            XKeySym::XK_Oacute => 0x00d3,
+            XKeySym::XK_Otilde => 0x00d5,
+            XKeySym::XK_Odiaeresis => 0x00d6,
+            XKeySym::XK_multiply => 0x00d7,
+            XKeySym::XK_Oslash => 0x00d8,
+            XKeySym::XK_Ooblique => 0x00d8,
+            XKeySym::XK_Uacute => 0x00da,
+            XKeySym::XK_Udiaeresis => 0x00dc,
+            XKeySym::XK_Yacute => 0x00dd,
+            XKeySym::XK_ssharp => 0x00df,
+            XKeySym::XK_aacute => 0x00e1,
+            XKeySym::XK_atilde => 0x00e3,
+            XKeySym::XK_adiaeresis => 0x00e4,
+            XKeySym::XK_aring => 0x00e5,
+            XKeySym::XK_ae => 0x00e6,
+            XKeySym::XK_ccedilla => 0x00e7,
+            XKeySym::XK_eacute => 0x00e9,
+            XKeySym::XK_ediaeresis => 0x00eb,
+            XKeySym::XK_iacute => 0x00ed,
+            XKeySym::XK_idiaeresis => 0x00ef,
+            XKeySym::XK_eth => 0x00f0,
+            XKeySym::XK_ntilde => 0x00f1,
+            XKeySym::XK_oacute => 0x00f3,
+            XKeySym::XK_otilde => 0x00f5,
+            XKeySym::XK_odiaeresis => 0x00f6,
+            XKeySym::XK_division => 0x00f7,
+            XKeySym::XK_oslash => 0x00f8,
+            XKeySym::XK_ooblique => 0x00f8,
+            XKeySym::XK_uacute => 0x00fa,
+            XKeySym::XK_udiaeresis => 0x00fc,
+            XKeySym::XK_yacute => 0x00fd,
+            XKeySym::XK_ydiaeresis => 0x00ff,
+            XKeySym::XK_Aogonek => 0x01a1,
+            XKeySym::XK_breve => 0x01a2,
+            XKeySym::XK_Lstroke => 0x01a3,
+            XKeySym::XK_Lcaron => 0x01a5,
+            XKeySym::XK_Sacute => 0x01a6,
+            XKeySym::XK_Scaron => 0x01a9,
+            XKeySym::XK_Scedilla => 0x01aa,
+            XKeySym::XK_Tcaron => 0x01ab,
+            XKeySym::XK_Zacute => 0x01ac,
+            XKeySym::XK_Zcaron => 0x01ae,
+            XKeySym::XK_aogonek => 0x01b1,
+            XKeySym::XK_ogonek => 0x01b2,
+            XKeySym::XK_lstroke => 0x01b3,
+            XKeySym::XK_lcaron => 0x01b5,
+            XKeySym::XK_sacute => 0x01b6,
+            XKeySym::XK_caron => 0x01b7,
+            XKeySym::XK_scaron => 0x01b9,
+            XKeySym::XK_scedilla => 0x01ba,
+            XKeySym::XK_tcaron => 0x01bb,
+            XKeySym::XK_zacute => 0x01bc,
+            XKeySym::XK_doubleacute => 0x01bd,
+            XKeySym::XK_zcaron => 0x01be,
+            XKeySym::XK_Racute => 0x01c0,
+            XKeySym::XK_Abreve => 0x01c3,
+            XKeySym::XK_Lacute => 0x01c5,
+            XKeySym::XK_Cacute => 0x01c6,
+            XKeySym::XK_Ccaron => 0x01c8,
+            XKeySym::XK_Eogonek => 0x01ca,
+            XKeySym::XK_Ecaron => 0x01cc,
+            XKeySym::XK_Dcaron => 0x01cf,
+            XKeySym::XK_Dstroke => 0x01d0,
+            XKeySym::XK_Nacute => 0x01d1,
+            XKeySym::XK_Ncaron => 0x01d2,
+            XKeySym::XK_Odoubleacute => 0x01d5,
+            XKeySym::XK_Rcaron => 0x01d8,
+            XKeySym::XK_Uring => 0x01d9,
+            XKeySym::XK_Udoubleacute => 0x01db,
+            XKeySym::XK_Tcedilla => 0x01de,
+            XKeySym::XK_racute => 0x01e0,
+            XKeySym::XK_abreve => 0x01e3,
+            XKeySym::XK_lacute => 0x01e5,
+            XKeySym::XK_cacute => 0x01e6,
+            XKeySym::XK_ccaron => 0x01e8,
+            XKeySym::XK_eogonek => 0x01ea,
+            XKeySym::XK_ecaron => 0x01ec,
+            XKeySym::XK_dcaron => 0x01ef,
+            XKeySym::XK_dstroke => 0x01f0,
+            XKeySym::XK_nacute => 0x01f1,
+            XKeySym::XK_ncaron => 0x01f2,
+            XKeySym::XK_odoubleacute => 0x01f5,
+            XKeySym::XK_rcaron => 0x01f8,
+            XKeySym::XK_uring => 0x01f9,
+            XKeySym::XK_udoubleacute => 0x01fb,
+            XKeySym::XK_tcedilla => 0x01fe,
+            XKeySym::XK_Hstroke => 0x02a1,
+            XKeySym::XK_Gbreve => 0x02ab,
+            XKeySym::XK_hstroke => 0x02b1,
+            XKeySym::XK_idotless => 0x02b9,
+            XKeySym::XK_gbreve => 0x02bb,
+            XKeySym::XK_Ubreve => 0x02dd,
+            XKeySym::XK_ubreve => 0x02fd,
+            XKeySym::XK_kra => 0x03a2,
+            XKeySym::XK_kappa => 0x03a2,
+            XKeySym::XK_Rcedilla => 0x03a3,
+            XKeySym::XK_Itilde => 0x03a5,
+            XKeySym::XK_Lcedilla => 0x03a6,
+            XKeySym::XK_Emacron => 0x03aa,
+            XKeySym::XK_Gcedilla => 0x03ab,
+            XKeySym::XK_Tslash => 0x03ac,
+            XKeySym::XK_rcedilla => 0x03b3,
+            XKeySym::XK_itilde => 0x03b5,
+            XKeySym::XK_lcedilla => 0x03b6,
+            XKeySym::XK_emacron => 0x03ba,
+            XKeySym::XK_gcedilla => 0x03bb,
+            XKeySym::XK_tslash => 0x03bc,
+            XKeySym::XK_ENG => 0x03bd,
+            XKeySym::XK_eng => 0x03bf,
+            XKeySym::XK_Amacron => 0x03c0,
+            XKeySym::XK_Iogonek => 0x03c7,
+            XKeySym::XK_Imacron => 0x03cf,
+            XKeySym::XK_Ncedilla => 0x03d1,
+            XKeySym::XK_Omacron => 0x03d2,
+            XKeySym::XK_Kcedilla => 0x03d3,
+            XKeySym::XK_Uogonek => 0x03d9,
+            XKeySym::XK_Utilde => 0x03dd,
+            XKeySym::XK_Umacron => 0x03de,
+            XKeySym::XK_amacron => 0x03e0,
+            XKeySym::XK_iogonek => 0x03e7,
+            XKeySym::XK_imacron => 0x03ef,
+            XKeySym::XK_ncedilla => 0x03f1,
+            XKeySym::XK_omacron => 0x03f2,
+            XKeySym::XK_kcedilla => 0x03f3,
+            XKeySym::XK_uogonek => 0x03f9,
+            XKeySym::XK_utilde => 0x03fd,
+            XKeySym::XK_umacron => 0x03fe,
+            XKeySym::XK_Wacute => 0x1001e82,
+            XKeySym::XK_wacute => 0x1001e83,
+            XKeySym::XK_Wdiaeresis => 0x1001e84,
+            XKeySym::XK_wdiaeresis => 0x1001e85,
+            XKeySym::XK_OE => 0x13bc,
+            XKeySym::XK_oe => 0x13bd,
+            XKeySym::XK_Ydiaeresis => 0x13be,
+            XKeySym::XK_overline => 0x047e,
+            XKeySym::XK_prolongedsound => 0x04b0,
+            XKeySym::XK_voicedsound => 0x04de,
+            XKeySym::XK_semivoicedsound => 0x04df,
+            XKeySym::XK_numerosign => 0x06b0,
+            XKeySym::XK_leftradical => 0x08a1,
+            XKeySym::XK_topleftradical => 0x08a2,
+            XKeySym::XK_horizconnector => 0x08a3,
+            XKeySym::XK_topintegral => 0x08a4,
+            XKeySym::XK_botintegral => 0x08a5,
+            XKeySym::XK_vertconnector => 0x08a6,
+            XKeySym::XK_topleftsqbracket => 0x08a7,
+            XKeySym::XK_botleftsqbracket => 0x08a8,
+            XKeySym::XK_toprightsqbracket => 0x08a9,
+            XKeySym::XK_botrightsqbracket => 0x08aa,
+            XKeySym::XK_topleftparens => 0x08ab,
+            XKeySym::XK_botleftparens => 0x08ac,
+            XKeySym::XK_toprightparens => 0x08ad,
+            XKeySym::XK_botrightparens => 0x08ae,
+            XKeySym::XK_leftmiddlecurlybrace => 0x08af,
+            XKeySym::XK_rightmiddlecurlybrace => 0x08b0,
+            XKeySym::XK_lessthanequal => 0x08bc,
+            XKeySym::XK_notequal => 0x08bd,
+            XKeySym::XK_greaterthanequal => 0x08be,
+            XKeySym::XK_integral => 0x08bf,
+            XKeySym::XK_therefore => 0x08c0,
+            XKeySym::XK_variation => 0x08c1,
+            XKeySym::XK_infinity => 0x08c2,
+            XKeySym::XK_nabla => 0x08c5,
+            XKeySym::XK_approximate => 0x08c8,
+            XKeySym::XK_similarequal => 0x08c9,
+            XKeySym::XK_ifonlyif => 0x08cd,
+            XKeySym::XK_implies => 0x08ce,
+            XKeySym::XK_identical => 0x08cf,
+            XKeySym::XK_radical => 0x08d6,
+            XKeySym::XK_includedin => 0x08da,
+            XKeySym::XK_includes => 0x08db,
+            XKeySym::XK_intersection => 0x08dc,
+            XKeySym::XK_union => 0x08dd,
+            XKeySym::XK_logicaland => 0x08de,
+            XKeySym::XK_logicalor => 0x08df,
+            XKeySym::XK_partialderivative => 0x08ef,
+            XKeySym::XK_function => 0x08f6,
+            XKeySym::XK_leftarrow => 0x08fb,
+            XKeySym::XK_uparrow => 0x08fc,
+            XKeySym::XK_rightarrow => 0x08fd,
+            XKeySym::XK_downarrow => 0x08fe,
+            XKeySym::XK_blank => 0x09df,
+            XKeySym::XK_soliddiamond => 0x09e0,
+            XKeySym::XK_checkerboard => 0x09e1,
+            XKeySym::XK_ht => 0x09e2,
+            XKeySym::XK_ff => 0x09e3,
+            XKeySym::XK_cr => 0x09e4,
+            XKeySym::XK_lf => 0x09e5,
+            XKeySym::XK_nl => 0x09e8,
+            XKeySym::XK_vt => 0x09e9,
+            XKeySym::XK_lowrightcorner => 0x09ea,
+            XKeySym::XK_uprightcorner => 0x09eb,
+            XKeySym::XK_upleftcorner => 0x09ec,
+            XKeySym::XK_lowleftcorner => 0x09ed,
+            XKeySym::XK_crossinglines => 0x09ee,
+            XKeySym::XK_leftt => 0x09f4,
+            XKeySym::XK_rightt => 0x09f5,
+            XKeySym::XK_bott => 0x09f6,
+            XKeySym::XK_topt => 0x09f7,
+            XKeySym::XK_vertbar => 0x09f8,
+            XKeySym::XK_emspace => 0x0aa1,
+            XKeySym::XK_enspace => 0x0aa2,
+            XKeySym::XK_em3space => 0x0aa3,
+            XKeySym::XK_em4space => 0x0aa4,
+            XKeySym::XK_digitspace => 0x0aa5,
+            XKeySym::XK_punctspace => 0x0aa6,
+            XKeySym::XK_thinspace => 0x0aa7,
+            XKeySym::XK_hairspace => 0x0aa8,
+            XKeySym::XK_emdash => 0x0aa9,
+            XKeySym::XK_endash => 0x0aaa,
+            XKeySym::XK_signifblank => 0x0aac,
+            XKeySym::XK_ellipsis => 0x0aae,
+            XKeySym::XK_doubbaselinedot => 0x0aaf,
+            XKeySym::XK_onethird => 0x0ab0,
+            XKeySym::XK_twothirds => 0x0ab1,
+            XKeySym::XK_onefifth => 0x0ab2,
+            XKeySym::XK_twofifths => 0x0ab3,
+            XKeySym::XK_threefifths => 0x0ab4,
+            XKeySym::XK_fourfifths => 0x0ab5,
+            XKeySym::XK_onesixth => 0x0ab6,
+            XKeySym::XK_fivesixths => 0x0ab7,
+            XKeySym::XK_careof => 0x0ab8,
+            XKeySym::XK_figdash => 0x0abb,
+            XKeySym::XK_leftanglebracket => 0x0abc,
+            XKeySym::XK_decimalpoint => 0x0abd,
+            XKeySym::XK_rightanglebracket => 0x0abe,
+            XKeySym::XK_marker => 0x0abf,
+            XKeySym::XK_oneeighth => 0x0ac3,
+            XKeySym::XK_threeeighths => 0x0ac4,
+            XKeySym::XK_fiveeighths => 0x0ac5,
+            XKeySym::XK_seveneighths => 0x0ac6,
+            XKeySym::XK_trademark => 0x0ac9,
+            XKeySym::XK_signaturemark => 0x0aca,
+            XKeySym::XK_leftopentriangle => 0x0acc,
+            XKeySym::XK_rightopentriangle => 0x0acd,
+            XKeySym::XK_emopenrectangle => 0x0acf,
+            XKeySym::XK_leftsinglequotemark => 0x0ad0,
+            XKeySym::XK_rightsinglequotemark => 0x0ad1,
+            XKeySym::XK_leftdoublequotemark => 0x0ad2,
+            XKeySym::XK_rightdoublequotemark => 0x0ad3,
+            XKeySym::XK_prescription => 0x0ad4,
+            XKeySym::XK_permille => 0x0ad5,
+            XKeySym::XK_minutes => 0x0ad6,
+            XKeySym::XK_seconds => 0x0ad7,
+            XKeySym::XK_latincross => 0x0ad9,
+            XKeySym::XK_hexagram => 0x0ada,
+            XKeySym::XK_emfilledrect => 0x0adf,
+            XKeySym::XK_openstar => 0x0ae5,
+            XKeySym::XK_leftpointer => 0x0aea,
+            XKeySym::XK_rightpointer => 0x0aeb,
+            XKeySym::XK_club => 0x0aec,
+            XKeySym::XK_diamond => 0x0aed,
+            XKeySym::XK_heart => 0x0aee,
+            XKeySym::XK_maltesecross => 0x0af0,
+            XKeySym::XK_dagger => 0x0af1,
+            XKeySym::XK_doubledagger => 0x0af2,
+            XKeySym::XK_checkmark => 0x0af3,
+            XKeySym::XK_ballotcross => 0x0af4,
+            XKeySym::XK_musicalsharp => 0x0af5,
+            XKeySym::XK_musicalflat => 0x0af6,
+            XKeySym::XK_malesymbol => 0x0af7,
+            XKeySym::XK_femalesymbol => 0x0af8,
+            XKeySym::XK_telephone => 0x0af9,
+            XKeySym::XK_telephonerecorder => 0x0afa,
+            XKeySym::XK_phonographcopyright => 0x0afb,
+            XKeySym::XK_caret => 0x0afc,
+            XKeySym::XK_singlelowquotemark => 0x0afd,
+            XKeySym::XK_doublelowquotemark => 0x0afe,
+            XKeySym::XK_cursor => 0x0aff,
+            XKeySym::XK_leftcaret => 0x0ba3,
+            XKeySym::XK_rightcaret => 0x0ba6,
+            XKeySym::XK_downcaret => 0x0ba8,
+            XKeySym::XK_upcaret => 0x0ba9,
+            XKeySym::XK_overbar => 0x0bc0,
+            XKeySym::XK_downtack => 0x0bc2,
+            XKeySym::XK_upshoe => 0x0bc3,
+            XKeySym::XK_downstile => 0x0bc4,
+            XKeySym::XK_underbar => 0x0bc6,
+            XKeySym::XK_jot => 0x0bca,
+            XKeySym::XK_quad => 0x0bcc,
+            XKeySym::XK_uptack => 0x0bce,
+            XKeySym::XK_upstile => 0x0bd3,
+            XKeySym::XK_downshoe => 0x0bd6,
+            XKeySym::XK_rightshoe => 0x0bd8,
+            XKeySym::XK_leftshoe => 0x0bda,
+            XKeySym::XK_lefttack => 0x0bdc,
+            XKeySym::XK_righttack => 0x0bfc,
+            XKeySym::XK_Korean_Won => 0x0eff,
+            XKeySym::XK_Ibreve => 0x100012c,
+            XKeySym::XK_Zstroke => 0x10001b5,
+            XKeySym::XK_Gcaron => 0x10001e6,
+            XKeySym::XK_Ocaron => 0x10001d1,
+            XKeySym::XK_Obarred => 0x100019f,
+            XKeySym::XK_ibreve => 0x100012d,
+            XKeySym::XK_zstroke => 0x10001b6,
+            XKeySym::XK_gcaron => 0x10001e7,
+            XKeySym::XK_ocaron => 0x10001d2,
+            XKeySym::XK_obarred => 0x1000275,
+            XKeySym::XK_SCHWA => 0x100018f,
+            XKeySym::XK_schwa => 0x1000259,
+            XKeySym::XK_EZH => 0x10001b7,
+            XKeySym::XK_ezh => 0x1000292,
+            XKeySym::XK_Abreveacute => 0x1001eae,
+            XKeySym::XK_abreveacute => 0x1001eaf,
+            XKeySym::XK_Abrevetilde => 0x1001eb4,
+            XKeySym::XK_abrevetilde => 0x1001eb5,
+            XKeySym::XK_Etilde => 0x1001ebc,
+            XKeySym::XK_etilde => 0x1001ebd,
+            XKeySym::XK_Ytilde => 0x1001ef8,
+            XKeySym::XK_ytilde => 0x1001ef9,
+            XKeySym::XK_EcuSign => 0x10020a0,
+            XKeySym::XK_ColonSign => 0x10020a1,
+            XKeySym::XK_CruzeiroSign => 0x10020a2,
+            XKeySym::XK_FFrancSign => 0x10020a3,
+            XKeySym::XK_LiraSign => 0x10020a4,
+            XKeySym::XK_MillSign => 0x10020a5,
+            XKeySym::XK_NairaSign => 0x10020a6,
+            XKeySym::XK_PesetaSign => 0x10020a7,
+            XKeySym::XK_RupeeSign => 0x10020a8,
+            XKeySym::XK_WonSign => 0x10020a9,
+            XKeySym::XK_NewSheqelSign => 0x10020aa,
+            XKeySym::XK_DongSign => 0x10020ab,
+            XKeySym::XK_EuroSign => 0x20ac,
+            XKeySym::XF86XK_MonBrightnessUp => 0x1008FF02,
+            XKeySym::XF86XK_MonBrightnessDown => 0x1008FF03,
+            XKeySym::XF86XK_KbdLightOnOff => 0x1008FF04,
+            XKeySym::XF86XK_KbdBrightnessUp => 0x1008FF05,
+            XKeySym::XF86XK_KbdBrightnessDown => 0x1008FF06,
+            XKeySym::XF86XK_MonBrightnessCycle => 0x1008FF07,
+            XKeySym::XF86XK_Standby => 0x1008FF10,
+            XKeySym::XF86XK_AudioLowerVolume => 0x1008FF11,
+            XKeySym::XF86XK_AudioMute => 0x1008FF12,
+            XKeySym::XF86XK_AudioRaiseVolume => 0x1008FF13,
+            XKeySym::XF86XK_AudioPlay => 0x1008FF14,
+            XKeySym::XF86XK_AudioStop => 0x1008FF15,
+            XKeySym::XF86XK_AudioPrev => 0x1008FF16,
+            XKeySym::XF86XK_AudioNext => 0x1008FF17,
+            XKeySym::XF86XK_AudioMicMute => 0x1008FF18,
+            XKeySym::XF86XK_DisplayOff => 0x1008FF19,
+            XKeySym::XF86XK_TouchpadToggle => 0x1008FF1A,
+            XKeySym::XF86XK_Calculator => 0x1008FF1D,
+        }
+    }
+
     /// Convert this keysym to its utf8 representation if possible
     pub fn as_utf8_string(&self) -> Result<String, std::string::FromUtf8Error> {
         String::from_utf8(
-            (match self {
-                XKeySym::XK_BackSpace => 0xff08,
-                XKeySym::XK_Tab => 0xff09,
-                XKeySym::XK_Linefeed => 0xff0a,
-                XKeySym::XK_Clear => 0xff0b,
-                XKeySym::XK_Return => 0xff0d,
-                XKeySym::XK_Pause => 0xff13,
-                XKeySym::XK_Scroll_Lock => 0xff14,
-                XKeySym::XK_Sys_Req => 0xff15,
-                XKeySym::XK_Escape => 0xff1b,
-                XKeySym::XK_Delete => 0xffff,
-                XKeySym::XK_Home => 0xff50,
-                XKeySym::XK_Left => 0xff51,
-                XKeySym::XK_Up => 0xff52,
-                XKeySym::XK_Right => 0xff53,
-                XKeySym::XK_Down => 0xff54,
-                XKeySym::XK_Prior => 0xff55,
-                XKeySym::XK_Page_Up => 0xff55,
-                XKeySym::XK_Next => 0xff56,
-                XKeySym::XK_Page_Down => 0xff56,
-                XKeySym::XK_End => 0xff57,
-                XKeySym::XK_Begin => 0xff58,
-                XKeySym::XK_Select => 0xff60,
-                XKeySym::XK_Print => 0xff61,
-                XKeySym::XK_Execute => 0xff62,
-                XKeySym::XK_Insert => 0xff63,
-                XKeySym::XK_Undo => 0xff65,
-                XKeySym::XK_Redo => 0xff66,
-                XKeySym::XK_Menu => 0xff67,
-                XKeySym::XK_Find => 0xff68,
-                XKeySym::XK_Cancel => 0xff69,
-                XKeySym::XK_Help => 0xff6a,
-                XKeySym::XK_Break => 0xff6b,
-                XKeySym::XK_Mode_switch => 0xff7e,
-                XKeySym::XK_script_switch => 0xff7e,
-                XKeySym::XK_Num_Lock => 0xff7f,
-                XKeySym::XK_KP_Space => 0xff80,
-                XKeySym::XK_KP_Tab => 0xff89,
-                XKeySym::XK_KP_Enter => 0xff8d,
-                XKeySym::XK_KP_F1 => 0xff91,
-                XKeySym::XK_KP_F2 => 0xff92,
-                XKeySym::XK_KP_F3 => 0xff93,
-                XKeySym::XK_KP_F4 => 0xff94,
-                XKeySym::XK_KP_Home => 0xff95,
-                XKeySym::XK_KP_Left => 0xff96,
-                XKeySym::XK_KP_Up => 0xff97,
-                XKeySym::XK_KP_Right => 0xff98,
-                XKeySym::XK_KP_Down => 0xff99,
-                XKeySym::XK_KP_Prior => 0xff9a,
-                XKeySym::XK_KP_Page_Up => 0xff9a,
-                XKeySym::XK_KP_Next => 0xff9b,
-                XKeySym::XK_KP_Page_Down => 0xff9b,
-                XKeySym::XK_KP_End => 0xff9c,
-                XKeySym::XK_KP_Begin => 0xff9d,
-                XKeySym::XK_KP_Insert => 0xff9e,
-                XKeySym::XK_KP_Delete => 0xff9f,
-                XKeySym::XK_KP_Equal => 0xffbd,
-                XKeySym::XK_KP_Multiply => 0xffaa,
-                XKeySym::XK_KP_Add => 0xffab,
-                XKeySym::XK_KP_Separator => 0xffac,
-                XKeySym::XK_KP_Subtract => 0xffad,
-                XKeySym::XK_KP_Decimal => 0xffae,
-                XKeySym::XK_KP_Divide => 0xffaf,
-                XKeySym::XK_KP_0 => 0xffb0,
-                XKeySym::XK_KP_1 => 0xffb1,
-                XKeySym::XK_KP_2 => 0xffb2,
-                XKeySym::XK_KP_3 => 0xffb3,
-                XKeySym::XK_KP_4 => 0xffb4,
-                XKeySym::XK_KP_5 => 0xffb5,
-                XKeySym::XK_KP_6 => 0xffb6,
-                XKeySym::XK_KP_7 => 0xffb7,
-                XKeySym::XK_KP_8 => 0xffb8,
-                XKeySym::XK_KP_9 => 0xffb9,
-                XKeySym::XK_F1 => 0xffbe,
-                XKeySym::XK_F2 => 0xffbf,
-                XKeySym::XK_F3 => 0xffc0,
-                XKeySym::XK_F4 => 0xffc1,
-                XKeySym::XK_F5 => 0xffc2,
-                XKeySym::XK_F6 => 0xffc3,
-                XKeySym::XK_F7 => 0xffc4,
-                XKeySym::XK_F8 => 0xffc5,
-                XKeySym::XK_F9 => 0xffc6,
-                XKeySym::XK_F10 => 0xffc7,
-                XKeySym::XK_F11 => 0xffc8,
-                XKeySym::XK_L1 => 0xffc8,
-                XKeySym::XK_F12 => 0xffc9,
-                XKeySym::XK_L2 => 0xffc9,
-                XKeySym::XK_F13 => 0xffca,
-                XKeySym::XK_L3 => 0xffca,
-                XKeySym::XK_F14 => 0xffcb,
-                XKeySym::XK_L4 => 0xffcb,
-                XKeySym::XK_F15 => 0xffcc,
-                XKeySym::XK_L5 => 0xffcc,
-                XKeySym::XK_F16 => 0xffcd,
-                XKeySym::XK_L6 => 0xffcd,
-                XKeySym::XK_F17 => 0xffce,
-                XKeySym::XK_L7 => 0xffce,
-                XKeySym::XK_F18 => 0xffcf,
-                XKeySym::XK_L8 => 0xffcf,
-                XKeySym::XK_F19 => 0xffd0,
-                XKeySym::XK_L9 => 0xffd0,
-                XKeySym::XK_F20 => 0xffd1,
-                XKeySym::XK_L10 => 0xffd1,
-                XKeySym::XK_F21 => 0xffd2,
-                XKeySym::XK_R1 => 0xffd2,
-                XKeySym::XK_F22 => 0xffd3,
-                XKeySym::XK_R2 => 0xffd3,
-                XKeySym::XK_F23 => 0xffd4,
-                XKeySym::XK_R3 => 0xffd4,
-                XKeySym::XK_F24 => 0xffd5,
-                XKeySym::XK_R4 => 0xffd5,
-                XKeySym::XK_F25 => 0xffd6,
-                XKeySym::XK_R5 => 0xffd6,
-                XKeySym::XK_F26 => 0xffd7,
-                XKeySym::XK_R6 => 0xffd7,
-                XKeySym::XK_F27 => 0xffd8,
-                XKeySym::XK_R7 => 0xffd8,
-                XKeySym::XK_F28 => 0xffd9,
-                XKeySym::XK_R8 => 0xffd9,
-                XKeySym::XK_F29 => 0xffda,
-                XKeySym::XK_R9 => 0xffda,
-                XKeySym::XK_F30 => 0xffdb,
-                XKeySym::XK_R10 => 0xffdb,
-                XKeySym::XK_F31 => 0xffdc,
-                XKeySym::XK_R11 => 0xffdc,
-                XKeySym::XK_F32 => 0xffdd,
-                XKeySym::XK_R12 => 0xffdd,
-                XKeySym::XK_F33 => 0xffde,
-                XKeySym::XK_R13 => 0xffde,
-                XKeySym::XK_F34 => 0xffdf,
-                XKeySym::XK_R14 => 0xffdf,
-                XKeySym::XK_F35 => 0xffe0,
-                XKeySym::XK_R15 => 0xffe0,
-                XKeySym::XK_Shift_L => 0xffe1,
-                XKeySym::XK_Shift_R => 0xffe2,
-                XKeySym::XK_Control_L => 0xffe3,
-                XKeySym::XK_Control_R => 0xffe4,
-                XKeySym::XK_Caps_Lock => 0xffe5,
-                XKeySym::XK_Shift_Lock => 0xffe6,
-                XKeySym::XK_Meta_L => 0xffe7,
-                XKeySym::XK_Meta_R => 0xffe8,
-                XKeySym::XK_Alt_L => 0xffe9,
-                XKeySym::XK_Alt_R => 0xffea,
-                XKeySym::XK_Super_L => 0xffeb,
-                XKeySym::XK_Super_R => 0xffec,
-                XKeySym::XK_Hyper_L => 0xffed,
-                XKeySym::XK_Hyper_R => 0xffee,
-                XKeySym::XK_ISO_Lock => 0xfe01,
-                XKeySym::XK_ISO_Level2_Latch => 0xfe02,
-                XKeySym::XK_ISO_Level3_Shift => 0xfe03,
-                XKeySym::XK_ISO_Level3_Latch => 0xfe04,
-                XKeySym::XK_ISO_Level3_Lock => 0xfe05,
-                XKeySym::XK_ISO_Level5_Shift => 0xfe11,
-                XKeySym::XK_ISO_Level5_Latch => 0xfe12,
-                XKeySym::XK_ISO_Level5_Lock => 0xfe13,
-                XKeySym::XK_ISO_Left_Tab => 0xfe20,
-                XKeySym::XK_ISO_Partial_Space_Left => 0xfe25,
-                XKeySym::XK_ISO_Partial_Space_Right => 0xfe26,
-                XKeySym::XK_ISO_Set_Margin_Left => 0xfe27,
-                XKeySym::XK_ISO_Set_Margin_Right => 0xfe28,
-                XKeySym::XK_ISO_Continuous_Underline => 0xfe30,
-                XKeySym::XK_ISO_Discontinuous_Underline => 0xfe31,
-                XKeySym::XK_ISO_Emphasize => 0xfe32,
-                XKeySym::XK_ISO_Center_Object => 0xfe33,
-                XKeySym::XK_ISO_Enter => 0xfe34,
-                XKeySym::XK_Terminate_Server => 0xfed5,
-                XKeySym::XK_ch => 0xfea0,
-                XKeySym::XK_Ch => 0xfea1,
-                XKeySym::XK_CH => 0xfea2,
-                XKeySym::XK_c_h => 0xfea3,
-                XKeySym::XK_C_h => 0xfea4,
-                XKeySym::XK_C_H => 0xfea5,
-                XKeySym::XK_3270_Duplicate => 0xfd01,
-                XKeySym::XK_3270_FieldMark => 0xfd02,
-                XKeySym::XK_3270_Right2 => 0xfd03,
-                XKeySym::XK_3270_Left2 => 0xfd04,
-                XKeySym::XK_3270_BackTab => 0xfd05,
-                XKeySym::XK_3270_EraseEOF => 0xfd06,
-                XKeySym::XK_3270_EraseInput => 0xfd07,
-                XKeySym::XK_3270_Reset => 0xfd08,
-                XKeySym::XK_3270_Quit => 0xfd09,
-                XKeySym::XK_3270_PA1 => 0xfd0a,
-                XKeySym::XK_3270_PA2 => 0xfd0b,
-                XKeySym::XK_3270_PA3 => 0xfd0c,
-                XKeySym::XK_3270_Test => 0xfd0d,
-                XKeySym::XK_3270_Attn => 0xfd0e,
-                XKeySym::XK_3270_CursorBlink => 0xfd0f,
-                XKeySym::XK_3270_AltCursor => 0xfd10,
-                XKeySym::XK_3270_KeyClick => 0xfd11,
-                XKeySym::XK_3270_Jump => 0xfd12,
-                XKeySym::XK_3270_Ident => 0xfd13,
-                XKeySym::XK_3270_Rule => 0xfd14,
-                XKeySym::XK_3270_Copy => 0xfd15,
-                XKeySym::XK_3270_Play => 0xfd16,
-                XKeySym::XK_3270_Setup => 0xfd17,
-                XKeySym::XK_3270_Record => 0xfd18,
-                XKeySym::XK_3270_DeleteWord => 0xfd1a,
-                XKeySym::XK_3270_ExSelect => 0xfd1b,
-                XKeySym::XK_3270_CursorSelect => 0xfd1c,
-                XKeySym::XK_3270_Enter => 0xfd1e,
-                XKeySym::XK_space => 0x0020,
-                XKeySym::XK_exclam => 0x0021,
-                XKeySym::XK_quotedbl => 0x0022,
-                XKeySym::XK_numbersign => 0x0023,
-                XKeySym::XK_dollar => 0x0024,
-                XKeySym::XK_percent => 0x0025,
-                XKeySym::XK_ampersand => 0x0026,
-                XKeySym::XK_apostrophe => 0x0027,
-                XKeySym::XK_quoteright => 0x0027,
-                XKeySym::XK_parenleft => 0x0028,
-                XKeySym::XK_parenright => 0x0029,
-                XKeySym::XK_asterisk => 0x002a,
-                XKeySym::XK_plus => 0x002b,
-                XKeySym::XK_comma => 0x002c,
-                XKeySym::XK_minus => 0x002d,
-                XKeySym::XK_period => 0x002e,
-                XKeySym::XK_slash => 0x002f,
-                XKeySym::XK_0 => 0x0030,
-                XKeySym::XK_1 => 0x0031,
-                XKeySym::XK_2 => 0x0032,
-                XKeySym::XK_3 => 0x0033,
-                XKeySym::XK_4 => 0x0034,
-                XKeySym::XK_5 => 0x0035,
-                XKeySym::XK_6 => 0x0036,
-                XKeySym::XK_7 => 0x0037,
-                XKeySym::XK_8 => 0x0038,
-                XKeySym::XK_9 => 0x0039,
-                XKeySym::XK_colon => 0x003a,
-                XKeySym::XK_semicolon => 0x003b,
-                XKeySym::XK_less => 0x003c,
-                XKeySym::XK_equal => 0x003d,
-                XKeySym::XK_greater => 0x003e,
-                XKeySym::XK_question => 0x003f,
-                XKeySym::XK_at => 0x0040,
-                XKeySym::XK_A => 0x0041,
-                XKeySym::XK_B => 0x0042,
-                XKeySym::XK_C => 0x0043,
-                XKeySym::XK_D => 0x0044,
-                XKeySym::XK_E => 0x0045,
-                XKeySym::XK_F => 0x0046,
-                XKeySym::XK_G => 0x0047,
-                XKeySym::XK_H => 0x0048,
-                XKeySym::XK_I => 0x0049,
-                XKeySym::XK_J => 0x004a,
-                XKeySym::XK_K => 0x004b,
-                XKeySym::XK_L => 0x004c,
-                XKeySym::XK_M => 0x004d,
-                XKeySym::XK_N => 0x004e,
-                XKeySym::XK_O => 0x004f,
-                XKeySym::XK_P => 0x0050,
-                XKeySym::XK_Q => 0x0051,
-                XKeySym::XK_R => 0x0052,
-                XKeySym::XK_S => 0x0053,
-                XKeySym::XK_T => 0x0054,
-                XKeySym::XK_U => 0x0055,
-                XKeySym::XK_V => 0x0056,
-                XKeySym::XK_W => 0x0057,
-                XKeySym::XK_X => 0x0058,
-                XKeySym::XK_Y => 0x0059,
-                XKeySym::XK_Z => 0x005a,
-                XKeySym::XK_bracketleft => 0x005b,
-                XKeySym::XK_backslash => 0x005c,
-                XKeySym::XK_bracketright => 0x005d,
-                XKeySym::XK_asciicircum => 0x005e,
-                XKeySym::XK_underscore => 0x005f,
-                XKeySym::XK_grave => 0x0060,
-                XKeySym::XK_quoteleft => 0x0060,
-                XKeySym::XK_a => 0x0061,
-                XKeySym::XK_b => 0x0062,
-                XKeySym::XK_c => 0x0063,
-                XKeySym::XK_d => 0x0064,
-                XKeySym::XK_e => 0x0065,
-                XKeySym::XK_f => 0x0066,
-                XKeySym::XK_g => 0x0067,
-                XKeySym::XK_h => 0x0068,
-                XKeySym::XK_i => 0x0069,
-                XKeySym::XK_j => 0x006a,
-                XKeySym::XK_k => 0x006b,
-                XKeySym::XK_l => 0x006c,
-                XKeySym::XK_m => 0x006d,
-                XKeySym::XK_n => 0x006e,
-                XKeySym::XK_o => 0x006f,
-                XKeySym::XK_p => 0x0070,
-                XKeySym::XK_q => 0x0071,
-                XKeySym::XK_r => 0x0072,
-                XKeySym::XK_s => 0x0073,
-                XKeySym::XK_t => 0x0074,
-                XKeySym::XK_u => 0x0075,
-                XKeySym::XK_v => 0x0076,
-                XKeySym::XK_w => 0x0077,
-                XKeySym::XK_x => 0x0078,
-                XKeySym::XK_y => 0x0079,
-                XKeySym::XK_z => 0x007a,
-                XKeySym::XK_braceleft => 0x007b,
-                XKeySym::XK_bar => 0x007c,
-                XKeySym::XK_braceright => 0x007d,
-                XKeySym::XK_asciitilde => 0x007e,
-                XKeySym::XK_nobreakspace => 0x00a0,
-                XKeySym::XK_exclamdown => 0x00a1,
-                XKeySym::XK_cent => 0x00a2,
-                XKeySym::XK_sterling => 0x00a3,
-                XKeySym::XK_currency => 0x00a4,
-                XKeySym::XK_yen => 0x00a5,
-                XKeySym::XK_brokenbar => 0x00a6,
-                XKeySym::XK_section => 0x00a7,
-                XKeySym::XK_diaeresis => 0x00a8,
-                XKeySym::XK_copyright => 0x00a9,
-                XKeySym::XK_ordfeminine => 0x00aa,
-                XKeySym::XK_guillemotleft => 0x00ab,
-                XKeySym::XK_notsign => 0x00ac,
-                XKeySym::XK_hyphen => 0x00ad,
-                XKeySym::XK_registered => 0x00ae,
-                XKeySym::XK_macron => 0x00af,
-                XKeySym::XK_degree => 0x00b0,
-                XKeySym::XK_plusminus => 0x00b1,
-                XKeySym::XK_acute => 0x00b4,
-                XKeySym::XK_mu => 0x00b5,
-                XKeySym::XK_paragraph => 0x00b6,
-                XKeySym::XK_periodcentered => 0x00b7,
-                XKeySym::XK_cedilla => 0x00b8,
-                XKeySym::XK_masculine => 0x00ba,
-                XKeySym::XK_guillemotright => 0x00bb,
-                XKeySym::XK_onequarter => 0x00bc,
-                XKeySym::XK_onehalf => 0x00bd,
-                XKeySym::XK_threequarters => 0x00be,
-                XKeySym::XK_questiondown => 0x00bf,
-                XKeySym::XK_Aacute => 0x00c1,
-                XKeySym::XK_Atilde => 0x00c3,
-                XKeySym::XK_Adiaeresis => 0x00c4,
-                XKeySym::XK_Aring => 0x00c5,
-                XKeySym::XK_AE => 0x00c6,
-                XKeySym::XK_Ccedilla => 0x00c7,
-                XKeySym::XK_Eacute => 0x00c9,
-                XKeySym::XK_Ediaeresis => 0x00cb,
-                XKeySym::XK_Iacute => 0x00cd,
-                XKeySym::XK_Idiaeresis => 0x00cf,
-                XKeySym::XK_ETH => 0x00d0,
-                XKeySym::XK_Eth => 0x00d0,
-                XKeySym::XK_Ntilde => 0x00d1,
-                XKeySym::XK_Oacute => 0x00d3,
-                XKeySym::XK_Otilde => 0x00d5,
-                XKeySym::XK_Odiaeresis => 0x00d6,
-                XKeySym::XK_multiply => 0x00d7,
-                XKeySym::XK_Oslash => 0x00d8,
-                XKeySym::XK_Ooblique => 0x00d8,
-                XKeySym::XK_Uacute => 0x00da,
-                XKeySym::XK_Udiaeresis => 0x00dc,
-                XKeySym::XK_Yacute => 0x00dd,
-                XKeySym::XK_ssharp => 0x00df,
-                XKeySym::XK_aacute => 0x00e1,
-                XKeySym::XK_atilde => 0x00e3,
-                XKeySym::XK_adiaeresis => 0x00e4,
-                XKeySym::XK_aring => 0x00e5,
-                XKeySym::XK_ae => 0x00e6,
-                XKeySym::XK_ccedilla => 0x00e7,
-                XKeySym::XK_eacute => 0x00e9,
-                XKeySym::XK_ediaeresis => 0x00eb,
-                XKeySym::XK_iacute => 0x00ed,
-                XKeySym::XK_idiaeresis => 0x00ef,
-                XKeySym::XK_eth => 0x00f0,
-                XKeySym::XK_ntilde => 0x00f1,
-                XKeySym::XK_oacute => 0x00f3,
-                XKeySym::XK_otilde => 0x00f5,
-                XKeySym::XK_odiaeresis => 0x00f6,
-                XKeySym::XK_division => 0x00f7,
-                XKeySym::XK_oslash => 0x00f8,
-                XKeySym::XK_ooblique => 0x00f8,
-                XKeySym::XK_uacute => 0x00fa,
-                XKeySym::XK_udiaeresis => 0x00fc,
-                XKeySym::XK_yacute => 0x00fd,
-                XKeySym::XK_ydiaeresis => 0x00ff,
-                XKeySym::XK_Aogonek => 0x01a1,
-                XKeySym::XK_breve => 0x01a2,
-                XKeySym::XK_Lstroke => 0x01a3,
-                XKeySym::XK_Lcaron => 0x01a5,
-                XKeySym::XK_Sacute => 0x01a6,
-                XKeySym::XK_Scaron => 0x01a9,
-                XKeySym::XK_Scedilla => 0x01aa,
-                XKeySym::XK_Tcaron => 0x01ab,
-                XKeySym::XK_Zacute => 0x01ac,
-                XKeySym::XK_Zcaron => 0x01ae,
-                XKeySym::XK_aogonek => 0x01b1,
-                XKeySym::XK_ogonek => 0x01b2,
-                XKeySym::XK_lstroke => 0x01b3,
-                XKeySym::XK_lcaron => 0x01b5,
-                XKeySym::XK_sacute => 0x01b6,
-                XKeySym::XK_caron => 0x01b7,
-                XKeySym::XK_scaron => 0x01b9,
-                XKeySym::XK_scedilla => 0x01ba,
-                XKeySym::XK_tcaron => 0x01bb,
-                XKeySym::XK_zacute => 0x01bc,
-                XKeySym::XK_doubleacute => 0x01bd,
-                XKeySym::XK_zcaron => 0x01be,
-                XKeySym::XK_Racute => 0x01c0,
-                XKeySym::XK_Abreve => 0x01c3,
-                XKeySym::XK_Lacute => 0x01c5,
-                XKeySym::XK_Cacute => 0x01c6,
-                XKeySym::XK_Ccaron => 0x01c8,
-                XKeySym::XK_Eogonek => 0x01ca,
-                XKeySym::XK_Ecaron => 0x01cc,
-                XKeySym::XK_Dcaron => 0x01cf,
-                XKeySym::XK_Dstroke => 0x01d0,
-                XKeySym::XK_Nacute => 0x01d1,
-                XKeySym::XK_Ncaron => 0x01d2,
-                XKeySym::XK_Odoubleacute => 0x01d5,
-                XKeySym::XK_Rcaron => 0x01d8,
-                XKeySym::XK_Uring => 0x01d9,
-                XKeySym::XK_Udoubleacute => 0x01db,
-                XKeySym::XK_Tcedilla => 0x01de,
-                XKeySym::XK_racute => 0x01e0,
-                XKeySym::XK_abreve => 0x01e3,
-                XKeySym::XK_lacute => 0x01e5,
-                XKeySym::XK_cacute => 0x01e6,
-                XKeySym::XK_ccaron => 0x01e8,
-                XKeySym::XK_eogonek => 0x01ea,
-                XKeySym::XK_ecaron => 0x01ec,
-                XKeySym::XK_dcaron => 0x01ef,
-                XKeySym::XK_dstroke => 0x01f0,
-                XKeySym::XK_nacute => 0x01f1,
-                XKeySym::XK_ncaron => 0x01f2,
-                XKeySym::XK_odoubleacute => 0x01f5,
-                XKeySym::XK_rcaron => 0x01f8,
-                XKeySym::XK_uring => 0x01f9,
-                XKeySym::XK_udoubleacute => 0x01fb,
-                XKeySym::XK_tcedilla => 0x01fe,
-                XKeySym::XK_Hstroke => 0x02a1,
-                XKeySym::XK_Gbreve => 0x02ab,
-                XKeySym::XK_hstroke => 0x02b1,
-                XKeySym::XK_idotless => 0x02b9,
-                XKeySym::XK_gbreve => 0x02bb,
-                XKeySym::XK_Ubreve => 0x02dd,
-                XKeySym::XK_ubreve => 0x02fd,
-                XKeySym::XK_kra => 0x03a2,
-                XKeySym::XK_kappa => 0x03a2,
-                XKeySym::XK_Rcedilla => 0x03a3,
-                XKeySym::XK_Itilde => 0x03a5,
-                XKeySym::XK_Lcedilla => 0x03a6,
-                XKeySym::XK_Emacron => 0x03aa,
-                XKeySym::XK_Gcedilla => 0x03ab,
-                XKeySym::XK_Tslash => 0x03ac,
-                XKeySym::XK_rcedilla => 0x03b3,
-                XKeySym::XK_itilde => 0x03b5,
-                XKeySym::XK_lcedilla => 0x03b6,
-                XKeySym::XK_emacron => 0x03ba,
-                XKeySym::XK_gcedilla => 0x03bb,
-                XKeySym::XK_tslash => 0x03bc,
-                XKeySym::XK_ENG => 0x03bd,
-                XKeySym::XK_eng => 0x03bf,
-                XKeySym::XK_Amacron => 0x03c0,
-                XKeySym::XK_Iogonek => 0x03c7,
-                XKeySym::XK_Imacron => 0x03cf,
-                XKeySym::XK_Ncedilla => 0x03d1,
-                XKeySym::XK_Omacron => 0x03d2,
-                XKeySym::XK_Kcedilla => 0x03d3,
-                XKeySym::XK_Uogonek => 0x03d9,
-                XKeySym::XK_Utilde => 0x03dd,
-                XKeySym::XK_Umacron => 0x03de,
-                XKeySym::XK_amacron => 0x03e0,
-                XKeySym::XK_iogonek => 0x03e7,
-                XKeySym::XK_imacron => 0x03ef,
-                XKeySym::XK_ncedilla => 0x03f1,
-                XKeySym::XK_omacron => 0x03f2,
-                XKeySym::XK_kcedilla => 0x03f3,
-                XKeySym::XK_uogonek => 0x03f9,
-                XKeySym::XK_utilde => 0x03fd,
-                XKeySym::XK_umacron => 0x03fe,
-                XKeySym::XK_Wacute => 0x1001e82,
-                XKeySym::XK_wacute => 0x1001e83,
-                XKeySym::XK_Wdiaeresis => 0x1001e84,
-                XKeySym::XK_wdiaeresis => 0x1001e85,
-                XKeySym::XK_OE => 0x13bc,
-                XKeySym::XK_oe => 0x13bd,
-                XKeySym::XK_Ydiaeresis => 0x13be,
-                XKeySym::XK_overline => 0x047e,
-                XKeySym::XK_prolongedsound => 0x04b0,
-                XKeySym::XK_voicedsound => 0x04de,
-                XKeySym::XK_semivoicedsound => 0x04df,
-                XKeySym::XK_numerosign => 0x06b0,
-                XKeySym::XK_leftradical => 0x08a1,
-                XKeySym::XK_topleftradical => 0x08a2,
-                XKeySym::XK_horizconnector => 0x08a3,
-                XKeySym::XK_topintegral => 0x08a4,
-                XKeySym::XK_botintegral => 0x08a5,
-                XKeySym::XK_vertconnector => 0x08a6,
-                XKeySym::XK_topleftsqbracket => 0x08a7,
-                XKeySym::XK_botleftsqbracket => 0x08a8,
-                XKeySym::XK_toprightsqbracket => 0x08a9,
-                XKeySym::XK_botrightsqbracket => 0x08aa,
-                XKeySym::XK_topleftparens => 0x08ab,
-                XKeySym::XK_botleftparens => 0x08ac,
-                XKeySym::XK_toprightparens => 0x08ad,
-                XKeySym::XK_botrightparens => 0x08ae,
-                XKeySym::XK_leftmiddlecurlybrace => 0x08af,
-                XKeySym::XK_rightmiddlecurlybrace => 0x08b0,
-                XKeySym::XK_lessthanequal => 0x08bc,
-                XKeySym::XK_notequal => 0x08bd,
-                XKeySym::XK_greaterthanequal => 0x08be,
-                XKeySym::XK_integral => 0x08bf,
-                XKeySym::XK_therefore => 0x08c0,
-                XKeySym::XK_variation => 0x08c1,
-                XKeySym::XK_infinity => 0x08c2,
-                XKeySym::XK_nabla => 0x08c5,
-                XKeySym::XK_approximate => 0x08c8,
-                XKeySym::XK_similarequal => 0x08c9,
-                XKeySym::XK_ifonlyif => 0x08cd,
-                XKeySym::XK_implies => 0x08ce,
-                XKeySym::XK_identical => 0x08cf,
-                XKeySym::XK_radical => 0x08d6,
-                XKeySym::XK_includedin => 0x08da,
-                XKeySym::XK_includes => 0x08db,
-                XKeySym::XK_intersection => 0x08dc,
-                XKeySym::XK_union => 0x08dd,
-                XKeySym::XK_logicaland => 0x08de,
-                XKeySym::XK_logicalor => 0x08df,
-                XKeySym::XK_partialderivative => 0x08ef,
-                XKeySym::XK_function => 0x08f6,
-                XKeySym::XK_leftarrow => 0x08fb,
-                XKeySym::XK_uparrow => 0x08fc,
-                XKeySym::XK_rightarrow => 0x08fd,
-                XKeySym::XK_downarrow => 0x08fe,
-                XKeySym::XK_blank => 0x09df,
-                XKeySym::XK_soliddiamond => 0x09e0,
-                XKeySym::XK_checkerboard => 0x09e1,
-                XKeySym::XK_ht => 0x09e2,
-                XKeySym::XK_ff => 0x09e3,
-                XKeySym::XK_cr => 0x09e4,
-                XKeySym::XK_lf => 0x09e5,
-                XKeySym::XK_nl => 0x09e8,
-                XKeySym::XK_vt => 0x09e9,
-                XKeySym::XK_lowrightcorner => 0x09ea,
-                XKeySym::XK_uprightcorner => 0x09eb,
-                XKeySym::XK_upleftcorner => 0x09ec,
-                XKeySym::XK_lowleftcorner => 0x09ed,
-                XKeySym::XK_crossinglines => 0x09ee,
-                XKeySym::XK_leftt => 0x09f4,
-                XKeySym::XK_rightt => 0x09f5,
-                XKeySym::XK_bott => 0x09f6,
-                XKeySym::XK_topt => 0x09f7,
-                XKeySym::XK_vertbar => 0x09f8,
-                XKeySym::XK_emspace => 0x0aa1,
-                XKeySym::XK_enspace => 0x0aa2,
-                XKeySym::XK_em3space => 0x0aa3,
-                XKeySym::XK_em4space => 0x0aa4,
-                XKeySym::XK_digitspace => 0x0aa5,
-                XKeySym::XK_punctspace => 0x0aa6,
-                XKeySym::XK_thinspace => 0x0aa7,
-                XKeySym::XK_hairspace => 0x0aa8,
-                XKeySym::XK_emdash => 0x0aa9,
-                XKeySym::XK_endash => 0x0aaa,
-                XKeySym::XK_signifblank => 0x0aac,
-                XKeySym::XK_ellipsis => 0x0aae,
-                XKeySym::XK_doubbaselinedot => 0x0aaf,
-                XKeySym::XK_onethird => 0x0ab0,
-                XKeySym::XK_twothirds => 0x0ab1,
-                XKeySym::XK_onefifth => 0x0ab2,
-                XKeySym::XK_twofifths => 0x0ab3,
-                XKeySym::XK_threefifths => 0x0ab4,
-                XKeySym::XK_fourfifths => 0x0ab5,
-                XKeySym::XK_onesixth => 0x0ab6,
-                XKeySym::XK_fivesixths => 0x0ab7,
-                XKeySym::XK_careof => 0x0ab8,
-                XKeySym::XK_figdash => 0x0abb,
-                XKeySym::XK_leftanglebracket => 0x0abc,
-                XKeySym::XK_decimalpoint => 0x0abd,
-                XKeySym::XK_rightanglebracket => 0x0abe,
-                XKeySym::XK_marker => 0x0abf,
-                XKeySym::XK_oneeighth => 0x0ac3,
-                XKeySym::XK_threeeighths => 0x0ac4,
-                XKeySym::XK_fiveeighths => 0x0ac5,
-                XKeySym::XK_seveneighths => 0x0ac6,
-                XKeySym::XK_trademark => 0x0ac9,
-                XKeySym::XK_signaturemark => 0x0aca,
-                XKeySym::XK_leftopentriangle => 0x0acc,
-                XKeySym::XK_rightopentriangle => 0x0acd,
-                XKeySym::XK_emopenrectangle => 0x0acf,
-                XKeySym::XK_leftsinglequotemark => 0x0ad0,
-                XKeySym::XK_rightsinglequotemark => 0x0ad1,
-                XKeySym::XK_leftdoublequotemark => 0x0ad2,
-                XKeySym::XK_rightdoublequotemark => 0x0ad3,
-                XKeySym::XK_prescription => 0x0ad4,
-                XKeySym::XK_permille => 0x0ad5,
-                XKeySym::XK_minutes => 0x0ad6,
-                XKeySym::XK_seconds => 0x0ad7,
-                XKeySym::XK_latincross => 0x0ad9,
-                XKeySym::XK_hexagram => 0x0ada,
-                XKeySym::XK_emfilledrect => 0x0adf,
-                XKeySym::XK_openstar => 0x0ae5,
-                XKeySym::XK_leftpointer => 0x0aea,
-                XKeySym::XK_rightpointer => 0x0aeb,
-                XKeySym::XK_club => 0x0aec,
-                XKeySym::XK_diamond => 0x0aed,
-                XKeySym::XK_heart => 0x0aee,
-                XKeySym::XK_maltesecross => 0x0af0,
-                XKeySym::XK_dagger => 0x0af1,
-                XKeySym::XK_doubledagger => 0x0af2,
-                XKeySym::XK_checkmark => 0x0af3,
-                XKeySym::XK_ballotcross => 0x0af4,
-                XKeySym::XK_musicalsharp => 0x0af5,
-                XKeySym::XK_musicalflat => 0x0af6,
-                XKeySym::XK_malesymbol => 0x0af7,
-                XKeySym::XK_femalesymbol => 0x0af8,
-                XKeySym::XK_telephone => 0x0af9,
-                XKeySym::XK_telephonerecorder => 0x0afa,
-                XKeySym::XK_phonographcopyright => 0x0afb,
-                XKeySym::XK_caret => 0x0afc,
-                XKeySym::XK_singlelowquotemark => 0x0afd,
-                XKeySym::XK_doublelowquotemark => 0x0afe,
-                XKeySym::XK_cursor => 0x0aff,
-                XKeySym::XK_leftcaret => 0x0ba3,
-                XKeySym::XK_rightcaret => 0x0ba6,
-                XKeySym::XK_downcaret => 0x0ba8,
-                XKeySym::XK_upcaret => 0x0ba9,
-                XKeySym::XK_overbar => 0x0bc0,
-                XKeySym::XK_downtack => 0x0bc2,
-                XKeySym::XK_upshoe => 0x0bc3,
-                XKeySym::XK_downstile => 0x0bc4,
-                XKeySym::XK_underbar => 0x0bc6,
-                XKeySym::XK_jot => 0x0bca,
-                XKeySym::XK_quad => 0x0bcc,
-                XKeySym::XK_uptack => 0x0bce,
-                XKeySym::XK_upstile => 0x0bd3,
-                XKeySym::XK_downshoe => 0x0bd6,
-                XKeySym::XK_rightshoe => 0x0bd8,
-                XKeySym::XK_leftshoe => 0x0bda,
-                XKeySym::XK_lefttack => 0x0bdc,
-                XKeySym::XK_righttack => 0x0bfc,
-                XKeySym::XK_Korean_Won => 0x0eff,
-                XKeySym::XK_Ibreve => 0x100012c,
-                XKeySym::XK_Zstroke => 0x10001b5,
-                XKeySym::XK_Gcaron => 0x10001e6,
-                XKeySym::XK_Ocaron => 0x10001d1,
-                XKeySym::XK_Obarred => 0x100019f,
-                XKeySym::XK_ibreve => 0x100012d,
-                XKeySym::XK_zstroke => 0x10001b6,
-                XKeySym::XK_gcaron => 0x10001e7,
-                XKeySym::XK_ocaron => 0x10001d2,
-                XKeySym::XK_obarred => 0x1000275,
-                XKeySym::XK_SCHWA => 0x100018f,
-                XKeySym::XK_schwa => 0x1000259,
-                XKeySym::XK_EZH => 0x10001b7,
-                XKeySym::XK_ezh => 0x1000292,
-                XKeySym::XK_Abreveacute => 0x1001eae,
-                XKeySym::XK_abreveacute => 0x1001eaf,
-                XKeySym::XK_Abrevetilde => 0x1001eb4,
-                XKeySym::XK_abrevetilde => 0x1001eb5,
-                XKeySym::XK_Etilde => 0x1001ebc,
-                XKeySym::XK_etilde => 0x1001ebd,
-                XKeySym::XK_Ytilde => 0x1001ef8,
-                XKeySym::XK_ytilde => 0x1001ef9,
-                XKeySym::XK_EcuSign => 0x10020a0,
-                XKeySym::XK_ColonSign => 0x10020a1,
-                XKeySym::XK_CruzeiroSign => 0x10020a2,
-                XKeySym::XK_FFrancSign => 0x10020a3,
-                XKeySym::XK_LiraSign => 0x10020a4,
-                XKeySym::XK_MillSign => 0x10020a5,
-                XKeySym::XK_NairaSign => 0x10020a6,
-                XKeySym::XK_PesetaSign => 0x10020a7,
-                XKeySym::XK_RupeeSign => 0x10020a8,
-                XKeySym::XK_WonSign => 0x10020a9,
-                XKeySym::XK_NewSheqelSign => 0x10020aa,
-                XKeySym::XK_DongSign => 0x10020ab,
-                XKeySym::XK_EuroSign => 0x20ac,
-                XKeySym::XF86XK_MonBrightnessUp => 0x1008FF02,
-                XKeySym::XF86XK_MonBrightnessDown => 0x1008FF03,
-                XKeySym::XF86XK_KbdLightOnOff => 0x1008FF04,
-                XKeySym::XF86XK_KbdBrightnessUp => 0x1008FF05,
-                XKeySym::XF86XK_KbdBrightnessDown => 0x1008FF06,
-                XKeySym::XF86XK_MonBrightnessCycle => 0x1008FF07,
-                XKeySym::XF86XK_Standby => 0x1008FF10,
-                XKeySym::XF86XK_AudioLowerVolume => 0x1008FF11,
-                XKeySym::XF86XK_AudioMute => 0x1008FF12,
-                XKeySym::XF86XK_AudioRaiseVolume => 0x1008FF13,
-                XKeySym::XF86XK_AudioPlay => 0x1008FF14,
-                XKeySym::XF86XK_AudioStop => 0x1008FF15,
-                XKeySym::XF86XK_AudioPrev => 0x1008FF16,
-                XKeySym::XF86XK_AudioNext => 0x1008FF17,
-                XKeySym::XF86XK_AudioMicMute => 0x1008FF18,
-                XKeySym::XF86XK_DisplayOff => 0x1008FF19,
-                XKeySym::XF86XK_TouchpadToggle => 0x1008FF1A,
-                XKeySym::XF86XK_Calculator => 0x1008FF1D,
-            } as u32)
+            self.as_u32()
                 .to_le_bytes()
                 .iter()
                 .copied()
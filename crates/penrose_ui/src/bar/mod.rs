@@ -4,12 +4,16 @@ use crate::{
     Result,
 };
 use penrose::{
-    core::{State, WindowManager},
-    pure::geometry::Rect,
+    core::{
+        bindings::{MouseButton, MouseEventKind},
+        hooks::EventSource,
+        State, WindowManager,
+    },
+    pure::geometry::{Point, Rect},
     x::{event::XEvent, Atom, ClientConfig, Prop, WinType, XConn},
     Color, Xid,
 };
-use std::fmt;
+use std::{fmt, os::unix::io::RawFd, time::Duration};
 use tracing::{debug, error, info};
 use x11rb::protocol::xproto::ConnectionExt as _;
 
@@ -31,10 +35,12 @@ pub struct StatusBar<X: XConn> {
     position: Position,
     widgets: Vec<Box<dyn Widget<X>>>,
     screens: Vec<(Xid, f64)>,
+    extents: Vec<Vec<f64>>,
     hpx: u32,
     h: f64,
     bg: Color,
     active_screen: usize,
+    refresh_interval: Option<Duration>,
 }
 
 impl<X: XConn> fmt::Debug for StatusBar<X> {
@@ -46,6 +52,7 @@ impl<X: XConn> fmt::Debug for StatusBar<X> {
             .field("hpx", &self.hpx)
             .field("bg", &self.bg)
             .field("active_screen", &self.active_screen)
+            .field("refresh_interval", &self.refresh_interval)
             .finish()
     }
 }
@@ -67,10 +74,12 @@ impl<X: XConn> StatusBar<X> {
             position,
             widgets,
             screens: vec![],
+            extents: vec![],
             hpx: h,
             h: h as f64,
             bg: bg.into(),
             active_screen: 0,
+            refresh_interval: None,
         };
 
         fonts.iter().for_each(|f| bar.draw.register_font(f));
@@ -78,10 +87,31 @@ impl<X: XConn> StatusBar<X> {
         Ok(bar)
     }
 
+    /// Refresh all widgets every `interval` in addition to each time the window manager
+    /// state is refreshed.
+    ///
+    /// This allows widgets such as [widgets::RefreshText] to stay up to date even when there
+    /// is no activity from the X server.
+    pub fn with_refresh_interval(mut self, interval: Duration) -> Self {
+        self.refresh_interval = Some(interval);
+
+        self
+    }
+
     pub fn add_to(self, mut wm: WindowManager<X>) -> WindowManager<X>
     where
         X: 'static,
     {
+        if let Some(interval) = self.refresh_interval {
+            wm.add_timer(interval, refresh_hook);
+        }
+
+        for (index, w) in self.widgets.iter().enumerate() {
+            if let Some(fd) = w.event_fd() {
+                wm.add_event_source(WidgetEventSource { fd, index });
+            }
+        }
+
         wm.state.add_extension(self);
         wm.state.config.compose_or_set_event_hook(event_hook);
         wm.state.config.compose_or_set_manage_hook(manage_hook);
@@ -129,10 +159,19 @@ impl<X: XConn> StatusBar<X> {
         Ok(())
     }
 
+    fn select_button_events(&self, x: &X) -> penrose::Result<()> {
+        for &(id, _) in self.screens.iter() {
+            self.draw.select_button_events(id, x)?;
+        }
+
+        Ok(())
+    }
+
     /// Re-render all widgets in this status bar
     pub fn redraw(&mut self) -> Result<()> {
-        for (i, &(id, w)) in self.screens.clone().iter().enumerate() {
-            let screen_has_focus = self.active_screen == i;
+        let targets: Vec<_> = draw_targets(&self.screens, self.active_screen).collect();
+
+        for (i, id, w, screen_has_focus) in targets {
             let mut ctx = self.draw.context_for(id)?;
 
             ctx.clear()?;
@@ -142,13 +181,20 @@ impl<X: XConn> StatusBar<X> {
 
             let extents = self.layout(&mut ctx, w)?;
             let mut x = 0.0;
-            for (wd, (w, _)) in self.widgets.iter_mut().zip(extents) {
-                wd.draw(&mut ctx, self.active_screen, screen_has_focus, w, self.h)?;
+            for (wd, &(w, _)) in self.widgets.iter_mut().zip(extents.iter()) {
+                wd.draw(&mut ctx, i, screen_has_focus, w, self.h)?;
                 x += w;
                 ctx.flush();
                 ctx.set_x_offset(x);
             }
 
+            // Widget widths are stored for hit testing clicks on the bar
+            let widths = extents.into_iter().map(|(w, _)| w).collect();
+            match self.extents.get_mut(i) {
+                Some(e) => *e = widths,
+                None => self.extents.push(widths),
+            }
+
             self.draw.flush(id)?;
         }
 
@@ -181,6 +227,25 @@ impl<X: XConn> StatusBar<X> {
         Ok(extents)
     }
 
+    // Find the widget under the given point on a status bar window along with the position of
+    // the point relative to that widget.
+    fn widget_at(&self, id: Xid, pt: Point) -> Option<(usize, Point)> {
+        let screen = self.screens.iter().position(|&(bar_id, _)| bar_id == id)?;
+        let widths = self.extents.get(screen)?;
+        let px = pt.x as f64;
+        let mut offset = 0.0;
+
+        for (i, w) in widths.iter().enumerate() {
+            if px >= offset && px < offset + w {
+                let x = (px - offset).floor() as u32;
+                return Some((i, Point::new(x, pt.y)));
+            }
+            offset += w;
+        }
+
+        None
+    }
+
     fn redraw_if_needed(&mut self) -> Result<()> {
         if self.widgets.iter().any(|w| w.require_draw()) {
             self.redraw()?;
@@ -193,6 +258,66 @@ impl<X: XConn> StatusBar<X> {
     }
 }
 
+// Clicks are only handled if the widget says so, with errors being logged and treated as the
+// click not having been handled.
+fn click_handled<X: XConn>(
+    widget: &mut dyn Widget<X>,
+    button: MouseButton,
+    pt: Point,
+    state: &mut State<X>,
+    x: &X,
+) -> bool {
+    match widget.on_click(button, pt, state, x) {
+        Ok(handled) => handled,
+        Err(e) => {
+            error!(%e, "error running widget click handler");
+            false
+        }
+    }
+}
+
+// Each bar window is drawn with the index of the screen it is on so that widgets showing
+// per-screen state render the correct screen rather than the one that currently has focus.
+fn draw_targets(
+    screens: &[(Xid, f64)],
+    active_screen: usize,
+) -> impl Iterator<Item = (usize, Xid, f64, bool)> + '_ {
+    screens
+        .iter()
+        .enumerate()
+        .map(move |(i, &(id, w))| (i, id, w, i == active_screen))
+}
+
+// Polls the file descriptor of an individual widget that has its own source of events
+#[derive(Debug)]
+struct WidgetEventSource {
+    fd: RawFd,
+    index: usize,
+}
+
+impl<X: XConn + 'static> EventSource<X> for WidgetEventSource {
+    fn fd(&self) -> RawFd {
+        self.fd
+    }
+
+    fn on_ready(&mut self, state: &mut State<X>, x: &X) -> penrose::Result<()> {
+        let s = state.extension::<StatusBar<X>>()?;
+        let mut bar = s.borrow_mut();
+
+        if let Some(w) = bar.widgets.get_mut(self.index) {
+            if let Err(e) = w.on_fd_ready(state, x) {
+                error!(%e, "error running widget fd hook");
+            }
+        }
+
+        if let Err(e) = bar.redraw_if_needed() {
+            error!(%e, "error redrawing status bar");
+        }
+
+        Ok(())
+    }
+}
+
 /// Run any widget startup actions and then redraw
 pub fn startup_hook<X: XConn + 'static>(state: &mut State<X>, x: &X) -> penrose::Result<()> {
     let s = state.extension::<StatusBar<X>>()?;
@@ -202,6 +327,7 @@ pub fn startup_hook<X: XConn + 'static>(state: &mut State<X>, x: &X) -> penrose:
         error!(%e, "unabled to initialise for screens");
         return Err(penrose::Error::NoScreens);
     }
+    bar.select_button_events(x)?;
 
     info!("running startup widget hooks");
     for w in bar.widgets.iter_mut() {
@@ -237,13 +363,16 @@ pub fn refresh_hook<X: XConn + 'static>(state: &mut State<X>, x: &X) -> penrose:
     Ok(())
 }
 
-/// Run any widget event actions and then redraw if needed
+/// Run any widget event actions and then redraw if needed.
+///
+/// Clicks that are handled by a widget are not passed on to the window manager so that they
+/// do not also trigger any mouse bindings.
 pub fn event_hook<X: XConn + 'static>(
     event: &XEvent,
     state: &mut State<X>,
     x: &X,
 ) -> penrose::Result<bool> {
-    use XEvent::{ConfigureNotify, RandrNotify};
+    use XEvent::{ConfigureNotify, MouseEvent, RandrNotify};
 
     let s = state.extension::<StatusBar<X>>()?;
     let mut bar = s.borrow_mut();
@@ -260,10 +389,40 @@ pub fn event_hook<X: XConn + 'static>(
             error!(%e, "unabled to initialise for screens");
             return Err(penrose::Error::NoScreens);
         }
+        bar.extents.clear();
+        bar.select_button_events(x)?;
     }
 
     bar.active_screen = state.client_set.current_screen().index();
 
+    let clicked = match event {
+        MouseEvent(e) if e.kind == MouseEventKind::Press => bar
+            .widget_at(e.id, e.wpt)
+            .map(|(i, pt)| (i, pt, e.state.button)),
+        _ => None,
+    };
+
+    let mut handled_click = false;
+
+    if let Some((i, pt, button)) = clicked {
+        // Click handlers are free to modify and refresh the window manager state which will
+        // run our refresh hook, so we need to release the borrow on the bar while they run.
+        let mut widgets = std::mem::take(&mut bar.widgets);
+        drop(bar);
+
+        handled_click = click_handled(widgets[i].as_mut(), button, pt, state, x);
+
+        bar = s.borrow_mut();
+        bar.widgets = widgets;
+
+        // Catch up on any refresh that was run while the widgets were removed from the bar
+        for w in bar.widgets.iter_mut() {
+            if let Err(e) = w.on_refresh(state, x) {
+                error!(%e, "error running widget refresh hook");
+            }
+        }
+    }
+
     for w in bar.widgets.iter_mut() {
         if let Err(e) = w.on_event(event, state, x) {
             error!(%e, "error running widget event hook");
@@ -274,7 +433,7 @@ pub fn event_hook<X: XConn + 'static>(
         error!(%e, "error redrawing status bar");
     }
 
-    Ok(true)
+    Ok(!handled_click)
 }
 
 /// Run any widget on_new_client actions and then redraw if needed
@@ -298,3 +457,262 @@ pub fn manage_hook<X: XConn + 'static>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bar::widgets::{CurrentLayout, Text, Workspaces},
+        TextStyle,
+    };
+    use penrose::{
+        core::bindings::{KeyBindings, KeyCode, MouseState},
+        x::{
+            event::ClientMessage,
+            property::{MapState, WindowClass, WmState},
+            ClientAttr, WindowAttributes,
+        },
+    };
+    use std::collections::HashMap;
+
+    // An XConn with a single screen that accepts every request without doing anything so that
+    // click handlers can be run against a real window manager State.
+    struct StubXConn;
+
+    impl XConn for StubXConn {
+        fn root(&self) -> Xid {
+            Xid::from(0)
+        }
+
+        fn screen_details(&self) -> penrose::Result<Vec<Rect>> {
+            Ok(vec![Rect::new(0, 0, 800, 600)])
+        }
+
+        fn cursor_position(&self) -> penrose::Result<Point> {
+            Ok(Point::default())
+        }
+
+        fn grab(&self, _: &[KeyCode], _: &[MouseState]) -> penrose::Result<()> {
+            Ok(())
+        }
+
+        fn keyboard_mapping(&self) -> penrose::Result<HashMap<u8, Vec<u32>>> {
+            Ok(HashMap::new())
+        }
+
+        fn grab_keyboard(&self) -> penrose::Result<()> {
+            Ok(())
+        }
+
+        fn ungrab_keyboard(&self) -> penrose::Result<()> {
+            Ok(())
+        }
+
+        fn grab_pointer(&self) -> penrose::Result<()> {
+            Ok(())
+        }
+
+        fn ungrab_pointer(&self) -> penrose::Result<()> {
+            Ok(())
+        }
+
+        fn next_event(&self) -> penrose::Result<XEvent> {
+            Err(penrose::Error::Custom("no events in StubXConn".to_owned()))
+        }
+
+        fn poll_for_event(&self) -> penrose::Result<Option<XEvent>> {
+            Ok(None)
+        }
+
+        fn connection_fd(&self) -> RawFd {
+            -1
+        }
+
+        fn flush(&self) {}
+
+        fn intern_atom(&self, _: &str) -> penrose::Result<Xid> {
+            Ok(Xid::from(1))
+        }
+
+        fn atom_name(&self, _: Xid) -> penrose::Result<String> {
+            Ok(String::new())
+        }
+
+        fn client_geometry(&self, _: Xid) -> penrose::Result<Rect> {
+            Ok(Rect::default())
+        }
+
+        fn existing_clients(&self) -> penrose::Result<Vec<Xid>> {
+            Ok(vec![])
+        }
+
+        fn map(&self, _: Xid) -> penrose::Result<()> {
+            Ok(())
+        }
+
+        fn unmap(&self, _: Xid) -> penrose::Result<()> {
+            Ok(())
+        }
+
+        fn kill(&self, _: Xid) -> penrose::Result<()> {
+            Ok(())
+        }
+
+        fn focus(&self, _: Xid) -> penrose::Result<()> {
+            Ok(())
+        }
+
+        fn get_prop(&self, _: Xid, _: &str) -> penrose::Result<Option<Prop>> {
+            Ok(None)
+        }
+
+        fn list_props(&self, _: Xid) -> penrose::Result<Vec<String>> {
+            Ok(vec![])
+        }
+
+        fn get_wm_state(&self, _: Xid) -> penrose::Result<Option<WmState>> {
+            Ok(None)
+        }
+
+        fn get_window_attributes(&self, _: Xid) -> penrose::Result<WindowAttributes> {
+            Ok(WindowAttributes::new(
+                false,
+                MapState::Viewable,
+                WindowClass::InputOutput,
+            ))
+        }
+
+        fn set_wm_state(&self, _: Xid, _: WmState) -> penrose::Result<()> {
+            Ok(())
+        }
+
+        fn set_prop(&self, _: Xid, _: &str, _: Prop) -> penrose::Result<()> {
+            Ok(())
+        }
+
+        fn delete_prop(&self, _: Xid, _: &str) -> penrose::Result<()> {
+            Ok(())
+        }
+
+        fn set_client_attributes(&self, _: Xid, _: &[ClientAttr]) -> penrose::Result<()> {
+            Ok(())
+        }
+
+        fn set_client_config(&self, _: Xid, _: &[ClientConfig]) -> penrose::Result<()> {
+            Ok(())
+        }
+
+        fn send_client_message(&self, _: ClientMessage) -> penrose::Result<()> {
+            Ok(())
+        }
+
+        fn send_configure_notify(&self, _: Xid, _: Rect, _: u32) -> penrose::Result<()> {
+            Ok(())
+        }
+
+        fn warp_pointer(&self, _: Xid, _: i16, _: i16) -> penrose::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn state() -> State<StubXConn> {
+        WindowManager::new(
+            Default::default(),
+            KeyBindings::default(),
+            HashMap::new(),
+            StubXConn,
+        )
+        .expect("stub window manager to be valid")
+        .state
+    }
+
+    fn style() -> TextStyle {
+        TextStyle {
+            font: "mono".to_owned(),
+            point_size: 10,
+            fg: 0xffffff.into(),
+            bg: None,
+            padding: (0.0, 0.0),
+        }
+    }
+
+    #[test]
+    fn unhandled_scroll_over_a_widget_is_passed_on_to_mouse_bindings() {
+        let mut state = state();
+        let mut widgets: Vec<Box<dyn Widget<StubXConn>>> = vec![
+            Box::new(Text::new("", &style(), true, false)),
+            Box::new(Workspaces::new(&style(), 0x0000ff, 0x888888)),
+        ];
+
+        for w in widgets.iter_mut() {
+            let handled = click_handled(
+                w.as_mut(),
+                MouseButton::ScrollUp,
+                Point::default(),
+                &mut state,
+                &StubXConn,
+            );
+
+            assert!(!handled);
+        }
+    }
+
+    #[test]
+    fn clicks_used_by_a_widget_are_handled() {
+        let mut state = state();
+        let mut w = CurrentLayout::new(&style());
+
+        let handled = click_handled(
+            &mut w,
+            MouseButton::ScrollUp,
+            Point::default(),
+            &mut state,
+            &StubXConn,
+        );
+
+        assert!(handled);
+    }
+
+    #[test]
+    fn buttons_ignored_by_a_widget_are_not_handled() {
+        let mut state = state();
+        let mut w = CurrentLayout::new(&style());
+
+        let handled = click_handled(
+            &mut w,
+            MouseButton::Middle,
+            Point::default(),
+            &mut state,
+            &StubXConn,
+        );
+
+        assert!(!handled);
+    }
+
+    #[test]
+    fn each_screen_is_drawn_with_its_own_index() {
+        let screens = vec![
+            (Xid::from(1), 100.0),
+            (Xid::from(2), 200.0),
+            (Xid::from(3), 300.0),
+        ];
+
+        let targets: Vec<_> = draw_targets(&screens, 1).collect();
+
+        assert_eq!(
+            targets,
+            vec![
+                (0, Xid::from(1), 100.0, false),
+                (1, Xid::from(2), 200.0, true),
+                (2, Xid::from(3), 300.0, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_screen_has_focus_if_active_screen_is_out_of_range() {
+        let screens = vec![(Xid::from(1), 100.0), (Xid::from(2), 200.0)];
+
+        assert!(draw_targets(&screens, 5).all(|(_, _, _, focused)| !focused));
+    }
+}
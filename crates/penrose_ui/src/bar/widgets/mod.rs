@@ -1,18 +1,22 @@
 //! Self rendering building blocks for text based UI elements
 use crate::{Context, Result, TextStyle};
 use penrose::{
-    core::State,
+    core::{bindings::MouseButton, State},
+    pure::geometry::Point,
     x::{XConn, XEvent},
     Color, Xid,
 };
+use std::os::unix::io::RawFd;
 
 pub mod debug;
 mod simple;
 mod sys;
+mod systray;
 mod workspaces;
 
-pub use simple::{ActiveWindowName, CurrentLayout, RootWindowName};
+pub use simple::{ActiveWindowName, CurrentLayout, CurrentMode, RootWindowName};
 pub use sys::{amixer_volume, battery_summary, current_date_and_time, wifi_network};
+pub use systray::SysTray;
 pub use workspaces::Workspaces;
 
 /// A status bar widget that can be rendered using a [Context]
@@ -64,6 +68,37 @@ where
     fn on_new_client(&mut self, id: Xid, state: &mut State<X>, x: &X) -> Result<()> {
         Ok(())
     }
+
+    #[allow(unused_variables)]
+    /// Handle a mouse button being pressed over this Widget.
+    ///
+    /// The position of the click is given relative to the top left corner of the Widget as it
+    /// was last drawn in the status bar. Returns whether or not the click was handled: clicks
+    /// that are not handled are passed on to the window manager so that they are able to run
+    /// any matching mouse bindings.
+    fn on_click(
+        &mut self,
+        button: MouseButton,
+        pt: Point,
+        state: &mut State<X>,
+        x: &X,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// A file descriptor to be polled by the window manager main loop on behalf of this Widget.
+    ///
+    /// This is checked once when the status bar is added to the window manager and
+    /// [Widget::on_fd_ready] will be called each time the file descriptor becomes readable.
+    fn event_fd(&self) -> Option<RawFd> {
+        None
+    }
+
+    #[allow(unused_variables)]
+    /// Handle the file descriptor returned by [Widget::event_fd] becoming readable
+    fn on_fd_ready(&mut self, state: &mut State<X>, x: &X) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// A simple piece of static text with an optional background color.
@@ -5,8 +5,10 @@ use crate::{
     Result,
 };
 use penrose::{
-    core::State,
+    core::{bindings::MouseButton, State},
+    pure::geometry::Point,
     x::{event::PropertyEvent, Atom, XConn, XConnExt, XEvent},
+    Xid,
 };
 
 /// A text widget that is set via updating the root window name a la dwm
@@ -113,8 +115,8 @@ impl<X: XConn> Widget<X> for ActiveWindowName {
     }
 
     fn on_refresh(&mut self, state: &mut State<X>, x: &X) -> Result<()> {
-        if let Some(id) = state.client_set.current_client() {
-            self.set_text(&x.window_title(*id)?)
+        if let Some(&id) = state.client_set.current_client() {
+            self.set_text(&cached_title(id, state, x)?)
         } else {
             self.set_text("")
         }
@@ -122,15 +124,17 @@ impl<X: XConn> Widget<X> for ActiveWindowName {
         Ok(())
     }
 
+    // Client properties are updated by penrose before event hooks are run so we are able
+    // to pick up title changes for the focused client without waiting for a refresh.
     fn on_event(&mut self, event: &XEvent, state: &mut State<X>, x: &X) -> Result<()> {
         let name_props = [Atom::NetWmName.as_ref(), Atom::WmName.as_ref()];
 
-        if let Some(focused) = state.client_set.current_client() {
+        if let Some(&focused) = state.client_set.current_client() {
             match event {
                 XEvent::PropertyNotify(PropertyEvent { id, atom, .. })
-                    if id == focused && name_props.contains(&atom.as_ref()) =>
+                    if *id == focused && name_props.contains(&atom.as_ref()) =>
                 {
-                    self.inner.set_text(x.window_title(*id)?)
+                    self.set_text(&cached_title(focused, state, x)?)
                 }
 
                 _ => (),
@@ -141,7 +145,17 @@ impl<X: XConn> Widget<X> for ActiveWindowName {
     }
 }
 
-/// A text widget that shows the current layout name
+fn cached_title<X: XConn>(id: Xid, state: &State<X>, x: &X) -> Result<String> {
+    match state.client_properties(&id) {
+        Some(props) => Ok(props.title.clone()),
+        None => Ok(x.window_title(id)?),
+    }
+}
+
+/// A text widget that shows the current layout name.
+///
+/// Clicking or scrolling on the widget will cycle through the available layouts for the
+/// current workspace.
 #[derive(Clone, Debug, PartialEq)]
 pub struct CurrentLayout {
     inner: Text,
@@ -179,4 +193,66 @@ impl<X: XConn> Widget<X> for CurrentLayout {
 
         Ok(())
     }
+
+    fn on_click(
+        &mut self,
+        button: MouseButton,
+        _: Point,
+        state: &mut State<X>,
+        x: &X,
+    ) -> Result<bool> {
+        match button {
+            MouseButton::Left | MouseButton::ScrollUp => {
+                x.modify_and_refresh(state, |cs| cs.next_layout())?
+            }
+            MouseButton::Right | MouseButton::ScrollDown => {
+                x.modify_and_refresh(state, |cs| cs.previous_layout())?
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+}
+
+/// A text widget that shows the name of the active key binding mode (if there is one)
+#[derive(Clone, Debug, PartialEq)]
+pub struct CurrentMode {
+    inner: Text,
+}
+
+impl CurrentMode {
+    /// Create a new CurrentMode widget
+    pub fn new(style: &TextStyle) -> Self {
+        Self {
+            inner: Text::new("", style, false, false),
+        }
+    }
+}
+
+impl<X: XConn> Widget<X> for CurrentMode {
+    fn draw(&mut self, ctx: &mut Context, s: usize, f: bool, w: f64, h: f64) -> Result<()> {
+        Widget::<X>::draw(&mut self.inner, ctx, s, f, w, h)
+    }
+
+    fn current_extent(&mut self, ctx: &mut Context, h: f64) -> Result<(f64, f64)> {
+        Widget::<X>::current_extent(&mut self.inner, ctx, h)
+    }
+
+    fn is_greedy(&self) -> bool {
+        Widget::<X>::is_greedy(&self.inner)
+    }
+
+    fn require_draw(&self) -> bool {
+        Widget::<X>::require_draw(&self.inner)
+    }
+
+    fn on_refresh(&mut self, state: &mut State<X>, _: &X) -> Result<()> {
+        match state.current_mode() {
+            Some(mode) => self.inner.set_text(format!("<{mode}>")),
+            None => self.inner.set_text(""),
+        }
+
+        Ok(())
+    }
 }
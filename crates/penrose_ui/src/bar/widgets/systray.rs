@@ -0,0 +1,572 @@
+//! A system tray for embedding the icons of applications such as nm-applet.
+//!
+//! See the freedesktop [System Tray Protocol][1] and [XEmbed Protocol][2] specifications for
+//! details of how clients interact with the tray.
+//!
+//! [1]: https://specifications.freedesktop.org/systemtray-spec/systemtray-spec-latest.html
+//! [2]: https://specifications.freedesktop.org/xembed-spec/xembed-spec-latest.html
+use crate::{bar::widgets::Widget, core::Context, Result};
+use penrose::{
+    core::State,
+    x::{Atom, XConn, XEvent},
+    x11rb::XcbConn,
+    Color, Xid,
+};
+use std::os::unix::io::RawFd;
+use tracing::{debug, info, warn};
+use x11rb::{
+    connection::Connection,
+    protocol::{xproto::*, Event},
+    wrapper::ConnectionExt as _,
+    CURRENT_TIME, NONE,
+};
+
+const PADDING: f64 = 2.0;
+const DEFAULT_ICON_SIZE: u32 = 16;
+
+// Opcodes and flags from the system tray and XEmbed specs
+const SYSTEM_TRAY_REQUEST_DOCK: u32 = 0;
+const SYSTEM_TRAY_ORIENTATION_HORZ: u32 = 0;
+const XEMBED_EMBEDDED_NOTIFY: u32 = 0;
+const XEMBED_MAPPED: u32 = 1 << 0;
+const XEMBED_VERSION: u32 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Icon {
+    id: Xid,
+    mapped: bool,
+}
+
+// The icons docked in the tray in the order they were docked. Methods that modify the icons
+// return whether or not the visible icons have changed and the tray needs to be redrawn.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct Icons(Vec<Icon>);
+
+impl Icons {
+    fn contains(&self, id: Xid) -> bool {
+        self.0.iter().any(|i| i.id == id)
+    }
+
+    // Newly docked icons are not shown until we see them being mapped so inserting an icon never
+    // requires a redraw.
+    fn insert(&mut self, id: Xid) {
+        if !self.contains(id) {
+            self.0.push(Icon { id, mapped: false });
+        }
+    }
+
+    fn set_mapped(&mut self, id: Xid, mapped: bool) -> bool {
+        match self.0.iter_mut().find(|i| i.id == id) {
+            Some(icon) if icon.mapped != mapped => {
+                icon.mapped = mapped;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn remove(&mut self, id: Xid) -> bool {
+        let n = self.0.len();
+        self.0.retain(|i| i.id != id);
+
+        self.0.len() != n
+    }
+
+    fn mapped(&self) -> Vec<Xid> {
+        self.0.iter().filter(|i| i.mapped).map(|i| i.id).collect()
+    }
+}
+
+// Icons are square and fill the height of the bar other than the padding above and below.
+fn icon_size(h: f64) -> u32 {
+    (h - 2.0 * PADDING).max(1.0) as u32
+}
+
+// The width required to show n icons with padding between each icon and at either end.
+fn tray_width(n: usize, icon_size: u32) -> f64 {
+    if n == 0 {
+        0.0
+    } else {
+        n as f64 * (icon_size as f64 + PADDING) + PADDING
+    }
+}
+
+// The position of the i'th visible icon within the tray window, with icons laid out from left
+// to right and centered vertically.
+fn icon_position(i: usize, icon_size: u32, h: f64) -> (i32, i32) {
+    let x = PADDING + i as f64 * (icon_size as f64 + PADDING);
+    let y = (h as u32).saturating_sub(icon_size) / 2;
+
+    (x as i32, y as i32)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TrayAtoms {
+    selection: u32,
+    opcode: u32,
+    xembed: u32,
+    xembed_info: u32,
+}
+
+/// A system tray implementing the freedesktop system tray protocol.
+///
+/// Applications request that their icons are docked in the tray and the tray then embeds
+/// them in the status bar using XEmbed. Only one system tray can be running at a time: if
+/// another tray already owns the `_NET_SYSTEM_TRAY_S0` selection then this widget will
+/// remain empty.
+///
+/// Icons are shown in the status bar for a single screen (the first screen by default) but
+/// space for the icons is reserved in the status bar of every screen.
+#[derive(Debug)]
+pub struct SysTray {
+    conn: XcbConn,
+    screen: usize,
+    bg: Color,
+    atoms: Option<TrayAtoms>,
+    win: Option<Xid>,
+    bar: Option<(Xid, i32, i32)>,
+    icons: Icons,
+    icon_size: u32,
+    require_draw: bool,
+}
+
+impl SysTray {
+    /// Construct a new SysTray using the given background color.
+    ///
+    /// The tray uses its own connection to the X server in order to receive requests from
+    /// applications wanting to dock their icons.
+    pub fn new(bg: impl Into<Color>) -> Result<Self> {
+        Ok(Self {
+            conn: XcbConn::new()?,
+            screen: 0,
+            bg: bg.into(),
+            atoms: None,
+            win: None,
+            bar: None,
+            icons: Icons::default(),
+            icon_size: DEFAULT_ICON_SIZE,
+            require_draw: true,
+        })
+    }
+
+    /// Show the tray in the status bar for the screen with the given index.
+    pub fn on_screen(mut self, screen: usize) -> Self {
+        self.screen = screen;
+
+        self
+    }
+
+    fn acquire_selection(&mut self) -> Result<()> {
+        let conn = self.conn.connection();
+        let root = *self.conn.root();
+        let atoms = TrayAtoms {
+            selection: *self.conn.intern_atom(Atom::NetSystemTrayS0.as_ref())?,
+            opcode: *self.conn.intern_atom(Atom::NetSystemTrayOpcode.as_ref())?,
+            xembed: *self.conn.intern_atom(Atom::XEmbed.as_ref())?,
+            xembed_info: *self.conn.intern_atom(Atom::XEmbedInfo.as_ref())?,
+        };
+
+        if conn.get_selection_owner(atoms.selection)?.reply()?.owner != NONE {
+            warn!("another system tray is already running");
+            return Ok(());
+        }
+
+        // Docked icons are embedded in this window which is positioned over the status bar
+        let win = conn.generate_id()?;
+        let aux = CreateWindowAux::new()
+            .override_redirect(1)
+            .background_pixel(self.bg.rgb_u32())
+            .event_mask(EventMask::SUBSTRUCTURE_NOTIFY | EventMask::PROPERTY_CHANGE);
+
+        conn.create_window(
+            x11rb::COPY_DEPTH_FROM_PARENT,
+            win,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            x11rb::COPY_FROM_PARENT,
+            &aux,
+        )?;
+
+        let orientation = *self
+            .conn
+            .intern_atom(Atom::NetSystemTrayOrientation.as_ref())?;
+        conn.change_property32(
+            PropMode::REPLACE,
+            win,
+            orientation,
+            AtomEnum::CARDINAL,
+            &[SYSTEM_TRAY_ORIENTATION_HORZ],
+        )?;
+
+        let time = self.server_time(win)?;
+        conn.set_selection_owner(win, atoms.selection, time)?;
+        if conn.get_selection_owner(atoms.selection)?.reply()?.owner != win {
+            warn!("unable to acquire the system tray selection");
+            conn.destroy_window(win)?;
+            conn.flush()?;
+            return Ok(());
+        }
+
+        // Let any running applications know that there is now a tray available
+        let manager = *self.conn.intern_atom(Atom::Manager.as_ref())?;
+        let event = ClientMessageEvent::new(32, root, manager, [time, atoms.selection, win, 0, 0]);
+        conn.send_event(false, root, EventMask::STRUCTURE_NOTIFY, event)?;
+        conn.flush()?;
+
+        info!(%win, "acquired system tray selection");
+        self.atoms = Some(atoms);
+        self.win = Some(Xid::from(win));
+
+        Ok(())
+    }
+
+    // ICCCM forbids acquiring a selection using CurrentTime so we obtain a real timestamp from
+    // the server by making a zero length append to a property on our own window and reading the
+    // time from the resulting PropertyNotify event.
+    fn server_time(&self, win: u32) -> Result<u32> {
+        let conn = self.conn.connection();
+        let wm_name = u32::from(AtomEnum::WM_NAME);
+        conn.change_property8(PropMode::APPEND, win, wm_name, AtomEnum::STRING, &[])?;
+        conn.flush()?;
+
+        loop {
+            match conn.wait_for_event()? {
+                Event::PropertyNotify(e) if e.window == win && e.atom == wm_name => {
+                    return Ok(e.time)
+                }
+                event => debug!(?event, "discarding event while waiting for server time"),
+            }
+        }
+    }
+
+    fn process_events(&mut self) -> Result<()> {
+        while let Some(event) = self.conn.connection().poll_for_event()? {
+            if let Err(e) = self.handle_event(event) {
+                warn!(%e, "error handling system tray event");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_event(&mut self, event: Event) -> Result<()> {
+        let (atoms, win) = match (self.atoms, self.win) {
+            (Some(atoms), Some(win)) => (atoms, *win),
+            _ => return Ok(()),
+        };
+
+        match event {
+            Event::ClientMessage(e) if e.type_ == atoms.opcode && e.format == 32 => {
+                let data = e.data.as_data32();
+                if data[1] == SYSTEM_TRAY_REQUEST_DOCK {
+                    self.dock(Xid::from(data[2]), atoms, win)?;
+                }
+            }
+
+            Event::PropertyNotify(e) if e.atom == atoms.xembed_info => {
+                let id = Xid::from(e.window);
+                if self.icons.contains(id) {
+                    self.apply_xembed_info(id, atoms)?;
+                }
+            }
+
+            Event::MapNotify(e) => {
+                self.require_draw |= self.icons.set_mapped(Xid::from(e.window), true);
+            }
+            Event::UnmapNotify(e) => {
+                self.require_draw |= self.icons.set_mapped(Xid::from(e.window), false);
+            }
+            Event::DestroyNotify(e) => self.remove(Xid::from(e.window)),
+            Event::ReparentNotify(e) if e.parent != win => self.remove(Xid::from(e.window)),
+
+            Event::SelectionClear(e) if e.selection == atoms.selection => {
+                warn!("another application has taken ownership of the system tray");
+                self.release()?;
+            }
+
+            Event::Error(e) => debug!(?e, "X error from system tray connection"),
+
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    fn dock(&mut self, id: Xid, atoms: TrayAtoms, win: u32) -> Result<()> {
+        if self.icons.contains(id) {
+            return Ok(());
+        }
+
+        info!(%id, "docking system tray icon");
+        let conn = self.conn.connection();
+        let aux = ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE);
+        conn.change_window_attributes(*id, &aux)?;
+
+        // Icons are returned to the root window if we exit without releasing them
+        conn.change_save_set(SetMode::INSERT, *id)?;
+        conn.reparent_window(*id, win, 0, 0)?;
+        let aux = ConfigureWindowAux::new()
+            .width(self.icon_size)
+            .height(self.icon_size);
+        conn.configure_window(*id, &aux)?;
+
+        let event = ClientMessageEvent::new(
+            32,
+            *id,
+            atoms.xembed,
+            [CURRENT_TIME, XEMBED_EMBEDDED_NOTIFY, 0, win, XEMBED_VERSION],
+        );
+        conn.send_event(false, *id, EventMask::NO_EVENT, event)?;
+
+        self.icons.insert(id);
+        self.apply_xembed_info(id, atoms)
+    }
+
+    // Map or unmap an icon based on the flags in its _XEMBED_INFO property. Icons that do not
+    // set the property are always shown.
+    fn apply_xembed_info(&self, id: Xid, atoms: TrayAtoms) -> Result<()> {
+        let conn = self.conn.connection();
+        let reply = conn
+            .get_property(false, *id, atoms.xembed_info, atoms.xembed_info, 0, 2)?
+            .reply()?;
+
+        let flags = reply.value32().and_then(|mut vals| vals.nth(1));
+        let mapped = !matches!(flags, Some(f) if f & XEMBED_MAPPED == 0);
+
+        if mapped {
+            conn.map_window(*id)?;
+        } else {
+            conn.unmap_window(*id)?;
+        }
+        conn.flush()?;
+
+        Ok(())
+    }
+
+    fn remove(&mut self, id: Xid) {
+        if self.icons.remove(id) {
+            info!(%id, "removing system tray icon");
+            self.require_draw = true;
+        }
+    }
+
+    // Hand any docked icons back to the root window so that they can be picked up by the tray
+    // that now owns the selection.
+    fn release(&mut self) -> Result<()> {
+        let conn = self.conn.connection();
+        let root = *self.conn.root();
+
+        for icon in std::mem::take(&mut self.icons).0 {
+            conn.unmap_window(*icon.id)?;
+            conn.reparent_window(*icon.id, root, 0, 0)?;
+        }
+
+        if let Some(win) = self.win.take() {
+            conn.destroy_window(*win)?;
+        }
+        conn.flush()?;
+
+        self.atoms = None;
+        self.bar = None;
+        self.require_draw = true;
+
+        Ok(())
+    }
+
+    // Position the tray window over our section of the status bar and lay out the icons that
+    // are currently mapped from left to right.
+    fn position_icons(&mut self, win: Xid, bar: Xid, offset: f64, w: f64, h: f64) -> Result<()> {
+        let conn = self.conn.connection();
+        let (bx, by) = match self.bar {
+            Some((id, x, y)) if id == bar => (x, y),
+            _ => {
+                let g = conn.get_geometry(*bar)?.reply()?;
+                self.bar = Some((bar, g.x as i32, g.y as i32));
+                (g.x as i32, g.y as i32)
+            }
+        };
+
+        let mapped = self.icons.mapped();
+
+        if mapped.is_empty() || w < 1.0 {
+            conn.unmap_window(*win)?;
+            conn.flush()?;
+            return Ok(());
+        }
+
+        let aux = ConfigureWindowAux::new()
+            .x(bx + offset as i32)
+            .y(by)
+            .width(w as u32)
+            .height(h as u32)
+            .sibling(*bar)
+            .stack_mode(StackMode::ABOVE);
+        conn.configure_window(*win, &aux)?;
+        conn.map_window(*win)?;
+
+        let size = self.icon_size;
+        for (i, id) in mapped.iter().enumerate() {
+            let (x, y) = icon_position(i, size, h);
+            let aux = ConfigureWindowAux::new().x(x).y(y).width(size).height(size);
+            conn.configure_window(**id, &aux)?;
+        }
+        conn.flush()?;
+
+        Ok(())
+    }
+}
+
+impl<X: XConn> Widget<X> for SysTray {
+    fn draw(&mut self, ctx: &mut Context, screen: usize, _: bool, w: f64, h: f64) -> Result<()> {
+        ctx.color(&self.bg);
+        ctx.rectangle(0.0, 0.0, w, h)?;
+
+        if let (true, Some(win), Some(bar)) = (screen == self.screen, self.win, ctx.window()) {
+            let (offset, _) = ctx.offset();
+            self.position_icons(win, bar, offset, w, h)?;
+        }
+
+        self.require_draw = false;
+
+        Ok(())
+    }
+
+    fn current_extent(&mut self, _: &mut Context, h: f64) -> Result<(f64, f64)> {
+        self.icon_size = icon_size(h);
+        let w = tray_width(self.icons.mapped().len(), self.icon_size);
+
+        Ok((w, h))
+    }
+
+    fn is_greedy(&self) -> bool {
+        false
+    }
+
+    fn require_draw(&self) -> bool {
+        self.require_draw
+    }
+
+    fn on_startup(&mut self, _: &mut State<X>, _: &X) -> Result<()> {
+        self.acquire_selection()
+    }
+
+    fn on_event(&mut self, _: &XEvent, _: &mut State<X>, _: &X) -> Result<()> {
+        self.process_events()
+    }
+
+    fn on_refresh(&mut self, _: &mut State<X>, _: &X) -> Result<()> {
+        self.process_events()
+    }
+
+    fn event_fd(&self) -> Option<RawFd> {
+        Some(self.conn.connection_fd())
+    }
+
+    fn on_fd_ready(&mut self, _: &mut State<X>, _: &X) -> Result<()> {
+        self.process_events()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn icons(ids: &[(u32, bool)]) -> Icons {
+        Icons(
+            ids.iter()
+                .map(|&(id, mapped)| Icon {
+                    id: Xid::from(id),
+                    mapped,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn docked_icons_are_hidden_until_mapped() {
+        let mut icons = Icons::default();
+
+        icons.insert(Xid::from(1));
+        assert!(icons.contains(Xid::from(1)));
+        assert!(icons.mapped().is_empty());
+
+        assert!(icons.set_mapped(Xid::from(1), true));
+        assert_eq!(icons.mapped(), vec![Xid::from(1)]);
+    }
+
+    #[test]
+    fn docking_an_icon_twice_is_a_no_op() {
+        let mut icons = icons(&[(1, true)]);
+
+        icons.insert(Xid::from(1));
+
+        assert_eq!(icons, self::icons(&[(1, true)]));
+    }
+
+    #[test]
+    fn set_mapped_only_reports_changes() {
+        let mut icons = icons(&[(1, true), (2, false)]);
+
+        assert!(!icons.set_mapped(Xid::from(1), true));
+        assert!(icons.set_mapped(Xid::from(2), true));
+        assert!(!icons.set_mapped(Xid::from(3), true));
+        assert!(icons.set_mapped(Xid::from(1), false));
+
+        assert_eq!(icons.mapped(), vec![Xid::from(2)]);
+    }
+
+    #[test]
+    fn remove_only_reports_known_icons() {
+        let mut icons = icons(&[(1, true), (2, true), (3, false)]);
+
+        assert!(icons.remove(Xid::from(2)));
+        assert!(!icons.remove(Xid::from(2)));
+        assert!(!icons.contains(Xid::from(2)));
+
+        assert_eq!(icons.mapped(), vec![Xid::from(1)]);
+    }
+
+    #[test]
+    fn mapped_icons_preserve_docking_order() {
+        let icons = icons(&[(3, true), (1, false), (2, true)]);
+
+        assert_eq!(icons.mapped(), vec![Xid::from(3), Xid::from(2)]);
+    }
+
+    #[test]
+    fn icon_size_fills_the_bar_height_inside_the_padding() {
+        assert_eq!(icon_size(20.0), 16);
+        assert_eq!(icon_size(18.5), 14);
+        assert_eq!(icon_size(2.0), 1);
+    }
+
+    #[test]
+    fn tray_width_is_zero_without_icons() {
+        assert_eq!(tray_width(0, 16), 0.0);
+    }
+
+    #[test]
+    fn tray_width_pads_between_and_around_icons() {
+        assert_eq!(tray_width(1, 16), 20.0);
+        assert_eq!(tray_width(3, 16), 56.0);
+    }
+
+    #[test]
+    fn icons_are_laid_out_left_to_right_and_centered() {
+        let positions: Vec<_> = (0..3).map(|i| icon_position(i, 16, 24.0)).collect();
+
+        assert_eq!(positions, vec![(2, 4), (20, 4), (38, 4)]);
+    }
+
+    #[test]
+    fn last_icon_fits_inside_the_tray_width() {
+        let (x, _) = icon_position(2, 16, 20.0);
+
+        assert_eq!(x as f64 + 16.0 + PADDING, tray_width(3, 16));
+    }
+}
@@ -5,9 +5,11 @@ use crate::{
     Result,
 };
 use penrose::{
-    core::{ClientSpace, State},
-    x::XConn,
-    Color,
+    core::{bindings::MouseButton, ClientSpace, State},
+    extensions::hooks::UrgentClients,
+    pure::geometry::Point,
+    x::{XConn, XConnExt},
+    Color, Xid,
 };
 
 const PADDING: f64 = 3.0;
@@ -16,15 +18,21 @@ const PADDING: f64 = 3.0;
 struct WsMeta {
     tag: String,
     occupied: bool,
+    urgent: bool,
     extent: (f64, f64),
 }
 
 impl WsMeta {
     fn from_state<X: XConn>(state: &State<X>) -> Vec<Self> {
+        let urgent = urgent_clients(state);
+
         state
             .client_set
             .ordered_workspaces()
-            .map(WsMeta::from)
+            .map(|w| WsMeta {
+                urgent: w.clients().any(|c| urgent.contains(c)),
+                ..WsMeta::from(w)
+            })
             .collect()
     }
 }
@@ -34,11 +42,28 @@ impl From<&ClientSpace> for WsMeta {
         Self {
             tag: w.tag().to_owned(),
             occupied: !w.is_empty(),
+            urgent: false,
             extent: (0.0, 0.0),
         }
     }
 }
 
+// Urgent clients are only tracked if the urgency extension is in use. The focused client is
+// excluded as its urgency will be cleared as part of the current refresh.
+fn urgent_clients<X: XConn>(state: &State<X>) -> Vec<Xid> {
+    let focused = state.client_set.current_client();
+
+    match state.extension::<UrgentClients>() {
+        Ok(urgent) => urgent
+            .borrow()
+            .iter()
+            .filter(|&c| Some(c) != focused)
+            .copied()
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
 fn focused_workspaces<X: XConn>(state: &State<X>) -> Vec<String> {
     let mut indexed_screens: Vec<(usize, String)> = state
         .client_set
@@ -51,7 +76,9 @@ fn focused_workspaces<X: XConn>(state: &State<X>) -> Vec<String> {
     indexed_screens.into_iter().map(|(_, tag)| tag).collect()
 }
 
-/// A simple workspace indicator for a status bar
+/// A simple workspace indicator for a status bar.
+///
+/// Left clicking on a workspace tag will focus that workspace.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Workspaces {
     workspaces: Vec<WsMeta>,
@@ -63,6 +90,7 @@ pub struct Workspaces {
     fg_2: Color,
     bg_1: Color,
     bg_2: Color,
+    urgent_bg: Option<Color>,
     require_draw: bool,
 }
 
@@ -79,14 +107,39 @@ impl Workspaces {
             fg_2: empty_fg.into(),
             bg_1: highlight.into(),
             bg_2: style.bg.unwrap_or_else(|| 0x000000.into()),
+            urgent_bg: None,
             require_draw: true,
         }
     }
 
+    /// Highlight workspaces containing urgent clients using the given background color.
+    ///
+    /// **NOTE**: This requires the urgency extension to be enabled using
+    ///           [add_urgency_hook][penrose::extensions::hooks::add_urgency_hook].
+    pub fn with_urgent_highlight(mut self, urgent_bg: impl Into<Color>) -> Self {
+        self.urgent_bg = Some(urgent_bg.into());
+
+        self
+    }
+
     fn tags(&self) -> Vec<&str> {
         self.workspaces.iter().map(|w| w.tag.as_ref()).collect()
     }
 
+    // The tag drawn at the given x offset from the left edge of the widget
+    fn tag_at(&self, x: f64) -> Option<&str> {
+        let mut offset = PADDING;
+
+        for ws in self.workspaces.iter() {
+            if x >= offset && x < offset + ws.extent.0 {
+                return Some(&ws.tag);
+            }
+            offset += ws.extent.0;
+        }
+
+        None
+    }
+
     fn update_from_state<X: XConn>(&mut self, state: &State<X>) {
         let wss = WsMeta::from_state(state);
         let focused_ws = focused_workspaces(state);
@@ -98,7 +151,8 @@ impl Workspaces {
             self.require_draw = true;
         }
 
-        if self.occupied_changed(&wss) || self.focused_ws != focused_ws {
+        if self.occupied_changed(&wss) || self.urgent_changed(&wss) || self.focused_ws != focused_ws
+        {
             self.require_draw = true;
         }
 
@@ -120,12 +174,21 @@ impl Workspaces {
             .any(|(l, r)| l.occupied != r.occupied)
     }
 
+    // NOTE: Called after tags_changed above so we assume that tags are matching
+    fn urgent_changed(&self, workspaces: &[WsMeta]) -> bool {
+        self.workspaces
+            .iter()
+            .zip(workspaces)
+            .any(|(l, r)| l.urgent != r.urgent)
+    }
+
     fn ws_colors(
         &self,
         tag: &str,
         screen: usize,
         screen_has_focus: bool,
         occupied: bool,
+        urgent: bool,
     ) -> (&Color, Option<&Color>) {
         let focused_on_this_screen = match &self.focused_ws.get(screen) {
             &Some(focused_tag) => tag == focused_tag,
@@ -139,6 +202,8 @@ impl Workspaces {
             let fg = if occupied { &self.fg_1 } else { &self.fg_2 };
 
             (fg, Some(&self.bg_1))
+        } else if let (true, Some(c)) = (urgent, &self.urgent_bg) {
+            (&self.fg_1, Some(c))
         } else if focused {
             let fg = if focused_other {
                 &self.bg_1
@@ -171,7 +236,8 @@ impl<X: XConn> Widget<X> for Workspaces {
         let (_, eh) = <Self as Widget<X>>::current_extent(self, ctx, h)?;
 
         for ws in self.workspaces.iter() {
-            let (fg, bg) = self.ws_colors(&ws.tag, screen, screen_has_focus, ws.occupied);
+            let (fg, bg) =
+                self.ws_colors(&ws.tag, screen, screen_has_focus, ws.occupied, ws.urgent);
             if let Some(c) = bg {
                 ctx.color(c);
                 ctx.rectangle(0.0, 0.0, ws.extent.0, h)?;
@@ -228,4 +294,24 @@ impl<X: XConn> Widget<X> for Workspaces {
 
         Ok(())
     }
+
+    fn on_click(
+        &mut self,
+        button: MouseButton,
+        pt: Point,
+        state: &mut State<X>,
+        x: &X,
+    ) -> Result<bool> {
+        if button != MouseButton::Left {
+            return Ok(false);
+        }
+
+        match self.tag_at(pt.x as f64).map(|t| t.to_owned()) {
+            Some(tag) => {
+                x.modify_and_refresh(state, |cs| cs.focus_tag(&tag))?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
 }
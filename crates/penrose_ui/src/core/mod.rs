@@ -4,13 +4,16 @@ use pango::{EllipsizeMode, FontDescription, SCALE};
 use pangocairo::functions::{create_layout, show_layout};
 use penrose::{
     pure::geometry::Rect,
-    x::{WinType, XConn},
+    x::{ClientAttr, WinType, XConn},
     x11rb::XcbConn,
     Color, Xid,
 };
 use std::collections::HashMap;
 use tracing::{debug, info};
-use x11rb::{connection::Connection, protocol::xproto::Screen};
+use x11rb::{
+    connection::Connection,
+    protocol::xproto::{ConnectionExt as _, Screen},
+};
 
 // A rust version of XCB's `xcb_visualtype_t` struct for FFI.
 // Taken from https://github.com/psychon/x11rb/blob/c3894c092101a16cedf4c45e487652946a3c4284/cairo-example/src/main.rs
@@ -72,6 +75,25 @@ impl Draw {
         Ok(id)
     }
 
+    /// Destroy a window that was created using [Draw::new_window] along with its surface.
+    pub fn destroy_window(&mut self, id: Xid) -> Result<()> {
+        self.surfaces.remove(&id);
+        self.conn.connection().destroy_window(*id)?;
+        self.conn.flush();
+
+        Ok(())
+    }
+
+    /// Request that mouse button events for a window created using [Draw::new_window] are
+    /// sent to the window manager.
+    ///
+    /// Windows created by a [Draw] use its own connection to the X server so, by default, button
+    /// presses on them are not seen by the window manager's event hooks. Selecting the events
+    /// using the window manager's connection allows clicks to be handled in an event hook.
+    pub fn select_button_events<X: XConn>(&self, id: Xid, x: &X) -> penrose::Result<()> {
+        x.set_client_attributes(id, &[ClientAttr::ButtonEventMask])
+    }
+
     fn surface(&self, id: u32, screen: &Screen, w: i32, h: i32) -> Result<XCBSurface> {
         let mut visual = self.find_xcb_visualtype(screen.root_visual);
 
@@ -129,6 +151,7 @@ impl Draw {
 
         Ok(Context {
             ctx,
+            window: Some(id),
             font: None,
             fonts: self.fonts.clone(),
         })
@@ -142,6 +165,7 @@ impl Draw {
 
         Ok(Context {
             ctx,
+            window: None,
             font: None,
             fonts: self.fonts.clone(),
         })
@@ -162,6 +186,7 @@ impl Draw {
 #[derive(Clone, Debug)]
 pub struct Context {
     ctx: cairo::Context,
+    window: Option<Xid>,
     font: Option<FontDescription>,
     fonts: HashMap<String, FontDescription>,
 }
@@ -200,6 +225,16 @@ impl Context {
         self.ctx.translate(dx, dy)
     }
 
+    /// The window being drawn to if this context is for an X window
+    pub fn window(&self) -> Option<Xid> {
+        self.window
+    }
+
+    /// The current (x, y) offset from the top left corner of the drawing surface
+    pub fn offset(&self) -> (f64, f64) {
+        self.ctx.matrix().transform_point(0.0, 0.0)
+    }
+
     pub fn set_x_offset(&self, x: f64) {
         let (_, y_offset) = self.ctx.matrix().transform_point(0.0, 0.0);
         self.ctx.set_matrix(Matrix::identity());
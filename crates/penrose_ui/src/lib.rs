@@ -2,9 +2,11 @@ use penrose::{x::XConn, Color, Xid};
 
 pub mod bar;
 pub mod core;
+pub mod tabs;
 
 pub use crate::core::{Context, Draw, TextStyle};
 pub use bar::{Position, StatusBar};
+pub use tabs::{TabBar, Tabbed};
 
 use bar::widgets::{ActiveWindowName, CurrentLayout, RootWindowName, Workspaces};
 
@@ -30,6 +32,15 @@ pub enum Error {
 
     #[error("'{font}' is has not been registered as a font")]
     UnknownFont { font: String },
+
+    #[error(transparent)]
+    X11rbConnection(#[from] x11rb::errors::ConnectionError),
+
+    #[error(transparent)]
+    X11rbReplyError(#[from] x11rb::errors::ReplyError),
+
+    #[error(transparent)]
+    X11rbReplyOrIdError(#[from] x11rb::errors::ReplyOrIdError),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
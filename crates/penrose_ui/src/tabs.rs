@@ -0,0 +1,401 @@
+//! A tabbed layout with a tab bar showing the title of each client on the workspace.
+//!
+//! The [Tabbed] layout gives all of the available space to the focused client (in the same way
+//! as [penrose::builtin::layout::Monocle]) but reserves a strip at the top of the screen that
+//! is used by a [TabBar] to render a tab for each client. Clicking on a tab will focus the
+//! corresponding client.
+//!
+//! ```no_run
+//! # use penrose::{core::{Config, WindowManager}, stack, builtin::layout::MainAndStack, x11rb::RustConn};
+//! # use penrose_ui::{TabBar, TextStyle};
+//! # fn example(style: &TextStyle) -> penrose_ui::Result<()> {
+//! let tabs = TabBar::try_new(20, style, 0x458588)?;
+//! let config = Config {
+//!     default_layouts: stack!(Box::new(MainAndStack::default()), tabs.layout()),
+//!     ..Config::default()
+//! };
+//!
+//! let conn = RustConn::new()?;
+//! let wm = tabs.add_to(WindowManager::new(config, Default::default(), Default::default(), conn)?);
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    core::{Context, Draw, TextStyle},
+    Result,
+};
+use penrose::{
+    core::{
+        bindings::MouseEventKind,
+        layout::{Layout, Message},
+        State, WindowManager,
+    },
+    pure::{geometry::Rect, Stack},
+    x::{
+        event::{PropertyEvent, XEvent},
+        Atom, Prop, WinType, XConn, XConnExt,
+    },
+    Color, Xid,
+};
+use std::{cell::RefCell, collections::HashMap, mem::take, rc::Rc};
+use tracing::error;
+
+const ELLIPSIS: &str = "...";
+
+// The tabs to render for a workspace that was most recently laid out using a Tabbed layout
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Tabs {
+    r: Rect,
+    clients: Vec<Xid>,
+    focus: Xid,
+}
+
+type SharedTabs = Rc<RefCell<HashMap<String, Tabs>>>;
+
+/// A [Layout] that shows only the focused client along with a tab for each client on the
+/// workspace.
+///
+/// The tabs themselves are rendered by the [TabBar] that this layout was created from so this
+/// layout can only be constructed using [TabBar::layout].
+#[derive(Debug, Clone)]
+pub struct Tabbed {
+    height: u32,
+    tabs: SharedTabs,
+}
+
+impl Tabbed {
+    // Split off the strip used for the tab bar if there is room for it
+    fn split(&self, r: Rect) -> (Option<Rect>, Rect) {
+        match r.split_at_height(self.height) {
+            Some((strip, rest)) => (Some(strip), rest),
+            None => (None, r),
+        }
+    }
+}
+
+impl Layout for Tabbed {
+    fn name(&self) -> String {
+        "Tabs".to_owned()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Layout> {
+        Box::new(self.clone())
+    }
+
+    fn layout_workspace(
+        &mut self,
+        tag: &str,
+        stack: &Option<Stack<Xid>>,
+        r: Rect,
+    ) -> (Option<Box<dyn Layout>>, Vec<(Xid, Rect)>) {
+        let s = match stack {
+            Some(s) => s,
+            None => return self.layout_empty(r),
+        };
+
+        let (strip, rest) = self.split(r);
+        if let Some(strip) = strip {
+            let tabs = Tabs {
+                r: strip,
+                clients: s.iter().copied().collect(),
+                focus: *s.focused(),
+            };
+            self.tabs.borrow_mut().insert(tag.to_owned(), tabs);
+        }
+
+        (None, vec![(*s.focused(), rest)])
+    }
+
+    fn layout(&mut self, s: &Stack<Xid>, r: Rect) -> (Option<Box<dyn Layout>>, Vec<(Xid, Rect)>) {
+        let (_, rest) = self.split(r);
+
+        (None, vec![(*s.focused(), rest)])
+    }
+
+    fn handle_message(&mut self, _: &Message) -> Option<Box<dyn Layout>> {
+        None
+    }
+}
+
+// A window used to render the tabs for a single workspace
+#[derive(Debug)]
+struct TabWindow {
+    id: Xid,
+    tabs: Tabs,
+}
+
+/// Renders the tabs for each visible workspace that is currently using a [Tabbed] layout.
+///
+/// The focused client is highlighted and clicking on a tab will focus its client.
+#[derive(Debug)]
+pub struct TabBar {
+    draw: Draw,
+    height: u32,
+    style: TextStyle,
+    highlight: Color,
+    tabs: SharedTabs,
+    windows: HashMap<String, TabWindow>,
+}
+
+impl TabBar {
+    /// Try to initialise a new tab bar with tabs of the given height. Can fail if we are unable
+    /// to connect to the X server.
+    pub fn try_new(height: u32, style: &TextStyle, highlight: impl Into<Color>) -> Result<Self> {
+        let mut draw = Draw::new()?;
+        draw.register_font(&style.font);
+
+        Ok(Self {
+            draw,
+            height,
+            style: style.clone(),
+            highlight: highlight.into(),
+            tabs: Rc::default(),
+            windows: HashMap::new(),
+        })
+    }
+
+    /// Create a new [Tabbed] [Layout] whose tabs will be rendered by this tab bar.
+    pub fn layout(&self) -> Box<dyn Layout> {
+        Box::new(Tabbed {
+            height: self.height,
+            tabs: self.tabs.clone(),
+        })
+    }
+
+    /// Add this tab bar to an existing [WindowManager].
+    pub fn add_to<X>(self, mut wm: WindowManager<X>) -> WindowManager<X>
+    where
+        X: XConn + 'static,
+    {
+        wm.state.add_extension(self);
+        wm.state.config.compose_or_set_event_hook(event_hook);
+        wm.state.config.compose_or_set_refresh_hook(refresh_hook);
+
+        wm
+    }
+
+    // Sync our windows with the workspaces that were laid out as part of the last refresh.
+    fn update<X: XConn>(&mut self, state: &State<X>, x: &X) -> Result<()> {
+        let tabs = take(&mut *self.tabs.borrow_mut());
+
+        let stale: Vec<String> = self
+            .windows
+            .keys()
+            .filter(|tag| !tabs.contains_key(*tag))
+            .cloned()
+            .collect();
+
+        for tag in stale {
+            if let Some(w) = self.windows.remove(&tag) {
+                self.draw.destroy_window(w.id)?;
+            }
+        }
+
+        for (tag, t) in tabs {
+            let id = match self.windows.remove(&tag) {
+                Some(w) if w.tabs.r == t.r => w.id,
+                Some(w) => {
+                    self.draw.destroy_window(w.id)?;
+                    self.new_window(t.r, x)?
+                }
+                None => self.new_window(t.r, x)?,
+            };
+
+            self.redraw(id, &t, state)?;
+            self.windows.insert(tag, TabWindow { id, tabs: t });
+        }
+
+        Ok(())
+    }
+
+    // Redraw any windows showing a tab for the given client, returning true if there were any.
+    fn redraw_for_client<X: XConn>(&mut self, client: Xid, state: &State<X>) -> Result<bool> {
+        let to_redraw: Vec<(Xid, Tabs)> = self
+            .windows
+            .values()
+            .filter(|w| w.tabs.clients.contains(&client))
+            .map(|w| (w.id, w.tabs.clone()))
+            .collect();
+
+        for (id, t) in to_redraw.iter() {
+            self.redraw(*id, t, state)?;
+        }
+
+        Ok(!to_redraw.is_empty())
+    }
+
+    fn new_window<X: XConn>(&mut self, r: Rect, x: &X) -> Result<Xid> {
+        let ty = WinType::InputOutput(Atom::NetWindowTypeToolbar);
+        let id = self.draw.new_window(ty, r, false)?;
+
+        let p = Prop::UTF8String(vec!["penrose-tabbar".to_string()]);
+        for atom in &[Atom::NetWmName, Atom::WmName, Atom::WmClass] {
+            self.draw.conn.set_prop(id, atom.as_ref(), p.clone())?;
+        }
+        self.draw.select_button_events(id, x)?;
+
+        Ok(id)
+    }
+
+    fn redraw<X: XConn>(&mut self, id: Xid, t: &Tabs, state: &State<X>) -> Result<()> {
+        let mut ctx = self.draw.context_for(id)?;
+        ctx.clear()?;
+        ctx.font(&self.style.font, self.style.point_size)?;
+
+        let (l, r) = self.style.padding;
+        let w = t.r.w as f64 / t.clients.len() as f64;
+        let h = t.r.h as f64;
+        let bg = self.style.bg.unwrap_or_else(|| 0x000000.into());
+
+        for (i, c) in t.clients.iter().enumerate() {
+            ctx.set_x_offset(i as f64 * w);
+            ctx.color(if *c == t.focus { &self.highlight } else { &bg });
+            ctx.rectangle(0.0, 0.0, w, h)?;
+
+            let title = state
+                .client_properties(c)
+                .map(|p| p.title.as_str())
+                .unwrap_or_default();
+            let txt = fit_text(&ctx, title, w - l - r)?;
+            let (_, th) = ctx.text_extent(&txt)?;
+
+            ctx.color(&self.style.fg);
+            ctx.text(&txt, (h - th) / 2.0, self.style.padding)?;
+        }
+
+        ctx.flush();
+        self.draw.flush(id)
+    }
+
+    // The client whose tab is at the given x offset within one of our windows
+    fn client_at(&self, id: Xid, px: u32) -> Option<Xid> {
+        let t = &self.windows.values().find(|w| w.id == id)?.tabs;
+        let tab_width = t.r.w as f64 / t.clients.len() as f64;
+
+        t.clients.get((px as f64 / tab_width) as usize).copied()
+    }
+}
+
+// Truncate text to fit within the given width, marking it with an ellipsis if anything
+// was removed.
+fn fit_text(ctx: &Context, txt: &str, max_w: f64) -> Result<String> {
+    if ctx.text_extent(txt)?.0 <= max_w {
+        return Ok(txt.to_owned());
+    }
+
+    let mut chars: Vec<char> = txt.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let s = format!("{}{ELLIPSIS}", chars.iter().collect::<String>());
+        if ctx.text_extent(&s)?.0 <= max_w {
+            return Ok(s);
+        }
+    }
+
+    Ok(String::new())
+}
+
+/// Update the tabs for each workspace that has been laid out using a [Tabbed] layout.
+pub fn refresh_hook<X: XConn + 'static>(state: &mut State<X>, x: &X) -> penrose::Result<()> {
+    let s = state.extension::<TabBar>()?;
+    let mut bar = s.borrow_mut();
+
+    if let Err(e) = bar.update(state, x) {
+        error!(%e, "error updating tab bar");
+    }
+
+    Ok(())
+}
+
+/// Focus the client whose tab was clicked on and redraw tabs when a client's title changes.
+///
+/// As with clicks handled by status bar widgets (see [event_hook][crate::bar::event_hook]),
+/// clicks on a tab do not run any mouse bindings.
+pub fn event_hook<X: XConn + 'static>(
+    event: &XEvent,
+    state: &mut State<X>,
+    x: &X,
+) -> penrose::Result<bool> {
+    let clicked = match event {
+        XEvent::MouseEvent(e) if e.kind == MouseEventKind::Press => state
+            .extension::<TabBar>()?
+            .borrow()
+            .client_at(e.id, e.wpt.x),
+
+        // Client properties are updated before event hooks are run so the new title is
+        // available to us when redrawing.
+        XEvent::PropertyNotify(PropertyEvent { id, atom, .. })
+            if atom == Atom::WmName.as_ref() || atom == Atom::NetWmName.as_ref() =>
+        {
+            let s = state.extension::<TabBar>()?;
+            if let Err(e) = s.borrow_mut().redraw_for_client(*id, state) {
+                error!(%e, "error redrawing tab bar");
+            }
+
+            None
+        }
+
+        _ => None,
+    };
+
+    match clicked {
+        Some(id) => {
+            x.modify_and_refresh(state, |cs| cs.focus_client(&id))?;
+            Ok(false)
+        }
+
+        None => Ok(true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use penrose::stack;
+
+    fn tabbed() -> Tabbed {
+        Tabbed {
+            height: 20,
+            tabs: Rc::default(),
+        }
+    }
+
+    #[test]
+    fn layout_workspace_reserves_a_strip_for_the_tabs() {
+        let mut l = tabbed();
+        let s = stack!([Xid::from(1)], Xid::from(2), [Xid::from(3)]);
+
+        let (_, positions) = l.layout_workspace("1", &Some(s), Rect::new(0, 0, 1000, 800));
+
+        assert_eq!(positions, vec![(Xid::from(2), Rect::new(0, 20, 1000, 780))]);
+        assert_eq!(
+            l.tabs.borrow().get("1"),
+            Some(&Tabs {
+                r: Rect::new(0, 0, 1000, 20),
+                clients: vec![Xid::from(1), Xid::from(2), Xid::from(3)],
+                focus: Xid::from(2),
+            })
+        );
+    }
+
+    #[test]
+    fn layout_workspace_without_room_for_tabs_uses_the_full_screen() {
+        let mut l = tabbed();
+        let s = stack!(Xid::from(1));
+
+        let (_, positions) = l.layout_workspace("1", &Some(s), Rect::new(0, 0, 1000, 20));
+
+        assert_eq!(positions, vec![(Xid::from(1), Rect::new(0, 0, 1000, 20))]);
+        assert!(l.tabs.borrow().is_empty());
+    }
+
+    #[test]
+    fn layout_workspace_for_an_empty_workspace_has_no_tabs() {
+        let mut l = tabbed();
+
+        let (_, positions) = l.layout_workspace("1", &None, Rect::new(0, 0, 1000, 800));
+
+        assert!(positions.is_empty());
+        assert!(l.tabs.borrow().is_empty());
+    }
+}
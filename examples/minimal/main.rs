@@ -8,7 +8,7 @@ use penrose::{
         layout::messages::{ExpandMain, IncMain, ShrinkMain},
     },
     core::{
-        bindings::{parse_keybindings_with_xmodmap, KeyEventHandler},
+        bindings::{parse_keybindings, KeyEventHandler},
         Config, WindowManager,
     },
     map,
@@ -64,7 +64,7 @@ fn main() -> Result<()> {
         .init();
 
     let conn = RustConn::new()?;
-    let key_bindings = parse_keybindings_with_xmodmap(raw_key_bindings())?;
+    let key_bindings = parse_keybindings(raw_key_bindings(), &conn)?;
     let wm = WindowManager::new(Config::default(), key_bindings, HashMap::new(), conn)?;
 
     wm.run()
@@ -73,6 +73,7 @@ fn main() -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use penrose::core::bindings::parse_keybindings_with_xmodmap;
 
     #[test]
     fn bindings_parse_correctly_with_xmodmap() {
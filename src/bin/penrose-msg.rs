@@ -0,0 +1,135 @@
+//! Send commands to a running penrose window manager over its IPC socket.
+//!
+//! See the [penrose::extensions::util::ipc] module for details of how to enable the
+//! IPC server in your window manager.
+use penrose::extensions::util::ipc::{
+    default_socket_path, send_command, subscribe, IpcCommand, IpcResponse, LayoutMessage,
+};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+const USAGE: &str = "\
+usage: penrose-msg [--socket PATH] COMMAND [ARGS...]
+
+commands:
+  focus-tag TAG                 focus the workspace with the given tag
+  move-client TAG [CLIENT]      move a client (default: focused) to the given tag
+  layout-message MESSAGE [N]    send a message to the current layout:
+                                  inc-main N | expand-main | shrink-main | rotate | mirror
+  query-state                   print a JSON summary of the current state
+  subscribe                     print each state change event as a line of JSON
+  raw JSON                      send a raw JSON encoded command
+";
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let mut socket = default_socket_path();
+
+    if args.first().map(String::as_str) == Some("--socket") {
+        if args.len() < 2 {
+            usage_error("--socket requires a path");
+        }
+        socket = PathBuf::from(args.remove(1));
+        args.remove(0);
+    }
+
+    if args == ["subscribe"] {
+        stream_events(&socket);
+    }
+
+    let command = match parse_command(&args) {
+        Ok(command) => command,
+        Err(msg) => usage_error(&msg),
+    };
+
+    match send_command(&socket, &command) {
+        Ok(IpcResponse::Success) => (),
+        Ok(IpcResponse::State(summary)) => match serde_json::to_string_pretty(&summary) {
+            Ok(s) => println!("{s}"),
+            Err(e) => fail(&e.to_string()),
+        },
+        Ok(IpcResponse::Error { message }) => fail(&message),
+        Err(e) => fail(&format!(
+            "unable to send command to {}: {e}",
+            socket.display()
+        )),
+    }
+}
+
+fn stream_events(socket: &Path) -> ! {
+    let events = match subscribe(socket) {
+        Ok(events) => events,
+        Err(e) => fail(&format!("unable to subscribe to {}: {e}", socket.display())),
+    };
+
+    for event in events {
+        match event.and_then(|e| Ok(serde_json::to_string(&e)?)) {
+            Ok(s) => println!("{s}"),
+            Err(e) => fail(&e.to_string()),
+        }
+    }
+
+    exit(0)
+}
+
+fn parse_command(args: &[String]) -> Result<IpcCommand, String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let command = match args.as_slice() {
+        ["focus-tag", tag] => IpcCommand::FocusTag {
+            tag: tag.to_string(),
+        },
+
+        ["move-client", tag] => IpcCommand::MoveClient {
+            tag: tag.to_string(),
+            client: None,
+        },
+
+        ["move-client", tag, client] => IpcCommand::MoveClient {
+            tag: tag.to_string(),
+            client: Some(parse_num::<u32>(client)?.into()),
+        },
+
+        ["layout-message", "inc-main", n] => IpcCommand::SendLayoutMessage {
+            message: LayoutMessage::IncMain(parse_num(n)?),
+        },
+
+        ["layout-message", msg] => IpcCommand::SendLayoutMessage {
+            message: match *msg {
+                "expand-main" => LayoutMessage::ExpandMain,
+                "shrink-main" => LayoutMessage::ShrinkMain,
+                "rotate" => LayoutMessage::Rotate,
+                "mirror" => LayoutMessage::Mirror,
+                _ => return Err(format!("unknown layout message: {msg}")),
+            },
+        },
+
+        ["query-state"] => IpcCommand::QueryState,
+
+        ["raw", json] => serde_json::from_str(json).map_err(|e| e.to_string())?,
+
+        [] => return Err("no command provided".to_string()),
+
+        _ => return Err(format!("invalid command: {}", args.join(" "))),
+    };
+
+    Ok(command)
+}
+
+fn parse_num<T: std::str::FromStr>(s: &str) -> Result<T, String> {
+    s.parse()
+        .map_err(|_| format!("expected a number, got '{s}'"))
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("error: {msg}\n\n{USAGE}");
+    exit(2)
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("error: {msg}");
+    exit(1)
+}
@@ -1,9 +1,15 @@
 //! Actions for manipulating floating windows.
 use crate::{
     builtin::actions::{key_handler, modify_with},
-    core::bindings::KeyEventHandler,
+    core::{
+        bindings::{KeyEventHandler, MouseDragHandler, MouseEvent, MouseEventHandler},
+        State,
+    },
+    pure::geometry::{Point, Rect},
     x::{XConn, XConnExt},
+    Result, Xid,
 };
+use tracing::trace;
 
 /// Resize a currently floating window by a given (width, height) delta
 ///
@@ -72,7 +78,9 @@ pub fn sink_focused<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
 /// Float all windows in their current tiled position
 pub fn float_all<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
     key_handler(|state, x: &X| {
-        let positions = state.client_set.visible_client_positions();
+        let positions = state
+            .client_set
+            .visible_client_positions(&state.client_props);
 
         x.modify_and_refresh(state, |cs| {
             for &(c, r) in positions.iter() {
@@ -86,3 +94,265 @@ pub fn float_all<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
 pub fn sink_all<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
     modify_with(|cs| cs.floating.clear())
 }
+
+/// Move a window by dragging it with the mouse.
+///
+/// This should be bound to a mouse button press: the clicked window is focused and then
+/// follows the pointer until the button is released. While moving a floating window, the
+/// edges of the window will snap to the edges of screens and other visible windows that are
+/// within `snap` pixels (use 0 to disable snapping).
+///
+/// Dragging a tiled window will either swap it with the tiled window it is dropped on or
+/// float it in its new position if it is not dropped on another tiled window. In both cases,
+/// dropping the window on a different screen will move it to the workspace on that screen.
+pub fn drag_move<X: XConn>(snap: u32) -> Box<dyn MouseEventHandler<X>> {
+    Box::new(move |evt: &MouseEvent, state: &mut State<X>, x: &X| {
+        if state.client_set.floating.contains_key(&evt.id) {
+            start_floating_drag(DragKind::Move, snap, evt, state, x)
+        } else {
+            start_tiled_drag(evt, state, x)
+        }
+    })
+}
+
+/// Resize a floating window by dragging its bottom right corner with the mouse.
+///
+/// This should be bound to a mouse button press: the clicked window is focused and then
+/// resized as the pointer moves until the button is released. While resizing, the right and
+/// bottom edges of the window will snap to the edges of screens and other visible windows
+/// that are within `snap` pixels (use 0 to disable snapping).
+///
+/// Clicking on a window that is not floating is a no-op.
+pub fn drag_resize<X: XConn>(snap: u32) -> Box<dyn MouseEventHandler<X>> {
+    Box::new(move |evt: &MouseEvent, state: &mut State<X>, x: &X| {
+        start_floating_drag(DragKind::Resize, snap, evt, state, x)
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DragKind {
+    Move,
+    Resize,
+}
+
+#[derive(Debug)]
+struct FloatingDrag {
+    id: Xid,
+    kind: DragKind,
+    snap: u32,
+    start: Point,
+    initial: Rect,
+    current: Rect,
+    targets: Vec<Rect>,
+}
+
+fn start_floating_drag<X: XConn>(
+    kind: DragKind,
+    snap: u32,
+    evt: &MouseEvent,
+    state: &mut State<X>,
+    x: &X,
+) -> Result<()> {
+    let id = evt.id;
+    if !state.client_set.floating.contains_key(&id) {
+        return Ok(());
+    }
+
+    x.modify_and_refresh(state, |cs| cs.focus_client(&id))?;
+
+    let initial = match state.client_set.floating.get(&id) {
+        Some(rr) => rr.applied_to(&state.client_set.current_screen().geometry()),
+        None => return Ok(()),
+    };
+
+    let targets = state
+        .client_set
+        .screens()
+        .map(|s| s.geometry())
+        .chain(
+            state
+                .diff
+                .after
+                .positions
+                .iter()
+                .filter(|&&(c, _)| c != id)
+                .map(|&(_, r)| r),
+        )
+        .collect();
+
+    trace!(%id, ?kind, ?initial, "starting floating window drag");
+    state.start_mouse_drag(
+        FloatingDrag {
+            id,
+            kind,
+            snap,
+            start: evt.rpt,
+            initial,
+            current: initial,
+            targets,
+        },
+        x,
+    )
+}
+
+impl<X: XConn> MouseDragHandler<X> for FloatingDrag {
+    fn on_motion(&mut self, evt: &MouseEvent, state: &mut State<X>, x: &X) -> Result<()> {
+        let dx = evt.rpt.x as i32 - self.start.x as i32;
+        let dy = evt.rpt.y as i32 - self.start.y as i32;
+        let bw = state.config.border_width;
+        let mut r = self.initial;
+
+        r = match self.kind {
+            DragKind::Move => {
+                r.reposition(dx, dy);
+                r.snap_position(&self.targets, self.snap)
+            }
+
+            DragKind::Resize => {
+                r.resize(dx, dy);
+                let mut r = r.snap_size(&self.targets, self.snap);
+                r.w = r.w.max(2 * bw + 1);
+                r.h = r.h.max(2 * bw + 1);
+                r
+            }
+        };
+
+        if r == self.current {
+            return Ok(());
+        }
+
+        self.current = r;
+        state.client_set.float_unchecked(self.id, r);
+
+        x.position_client(self.id, r.shrink_in(bw))
+    }
+
+    fn on_release(&mut self, evt: &MouseEvent, state: &mut State<X>, x: &X) -> Result<()> {
+        trace!(id = %self.id, r = ?self.current, "floating window drag complete");
+
+        drop_floating(self.id, self.current, evt.rpt, state, x)
+    }
+}
+
+// Tiled windows need to be dragged a short distance before we treat the drag as a move so that
+// a click without moving the pointer leaves the window where it is.
+const TILED_DRAG_THRESHOLD: u32 = 5;
+
+#[derive(Debug)]
+struct TiledDrag {
+    id: Xid,
+    start: Point,
+    initial: Rect,
+    current: Rect,
+    moved: bool,
+}
+
+fn start_tiled_drag<X: XConn>(evt: &MouseEvent, state: &mut State<X>, x: &X) -> Result<()> {
+    let id = evt.id;
+    if !state.client_set.contains(&id) {
+        return Ok(());
+    }
+
+    x.modify_and_refresh(state, |cs| cs.focus_client(&id))?;
+
+    let initial = match state.diff.after.positions.iter().find(|&&(c, _)| c == id) {
+        Some(&(_, r)) => r,
+        None => return Ok(()),
+    };
+
+    // Raise the window being dragged above everything else on screen
+    let mut stacking: Vec<Xid> = state
+        .diff
+        .after
+        .positions
+        .iter()
+        .map(|&(c, _)| c)
+        .filter(|&c| c != id)
+        .collect();
+    stacking.push(id);
+    x.restack(stacking.iter())?;
+
+    trace!(%id, ?initial, "starting tiled window drag");
+    state.start_mouse_drag(
+        TiledDrag {
+            id,
+            start: evt.rpt,
+            initial,
+            current: initial,
+            moved: false,
+        },
+        x,
+    )
+}
+
+impl<X: XConn> MouseDragHandler<X> for TiledDrag {
+    fn on_motion(&mut self, evt: &MouseEvent, state: &mut State<X>, x: &X) -> Result<()> {
+        let dx = evt.rpt.x as i32 - self.start.x as i32;
+        let dy = evt.rpt.y as i32 - self.start.y as i32;
+
+        if !self.moved && dx.unsigned_abs().max(dy.unsigned_abs()) < TILED_DRAG_THRESHOLD {
+            return Ok(());
+        }
+
+        self.moved = true;
+        self.current = self.initial;
+        self.current.reposition(dx, dy);
+
+        x.position_client(self.id, self.current.shrink_in(state.config.border_width))
+    }
+
+    fn on_release(&mut self, evt: &MouseEvent, state: &mut State<X>, x: &X) -> Result<()> {
+        let id = self.id;
+        if !self.moved {
+            return x.refresh(state);
+        }
+
+        // Positions are ordered from the bottom of the window stack to the top so we search in
+        // reverse to find the window that is visible under the pointer
+        let dropped_on = state
+            .diff
+            .after
+            .positions
+            .iter()
+            .rev()
+            .find(|&&(c, r)| c != id && r.contains_point(evt.rpt))
+            .map(|&(c, _)| c)
+            .filter(|c| !state.client_set.floating.contains_key(c));
+
+        match dropped_on {
+            Some(other) => {
+                trace!(%id, %other, "tiled window dropped on tiled window: swapping");
+                x.modify_and_refresh(state, |cs| {
+                    cs.swap_clients(&id, &other);
+                    cs.focus_client(&id);
+                })
+            }
+
+            None => {
+                trace!(%id, r = ?self.current, "tiled window dropped: floating");
+                drop_floating(id, self.current, evt.rpt, state, x)
+            }
+        }
+    }
+}
+
+// Float a client at the given position, moving it to the workspace on the screen containing
+// the pointer if that differs from its current workspace.
+fn drop_floating<X: XConn>(id: Xid, r: Rect, pt: Point, state: &mut State<X>, x: &X) -> Result<()> {
+    let current_tag = state.client_set.tag_for_client(&id).map(String::from);
+    let target_tag = state
+        .client_set
+        .screens()
+        .find(|s| s.geometry().contains_point(pt))
+        .map(|s| s.workspace.tag.clone())
+        .filter(|tag| Some(tag) != current_tag.as_ref());
+
+    x.modify_and_refresh(state, |cs| {
+        if let Some(tag) = &target_tag {
+            cs.move_client_to_tag(&id, tag);
+            cs.focus_client(&id);
+        }
+        // Floating positions are stored relative to the focused screen
+        cs.float_unchecked(id, r);
+    })
+}
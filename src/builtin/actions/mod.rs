@@ -1,6 +1,10 @@
 //! Helpers and pre-defined actions for use in user defined key bindings
 use crate::{
-    core::{bindings::KeyEventHandler, layout::IntoMessage, ClientSet, State},
+    core::{
+        bindings::{KeyEventHandler, KeyTrigger},
+        layout::IntoMessage,
+        ClientSet, State,
+    },
     util,
     x::{XConn, XConnExt},
     Result,
@@ -22,6 +26,45 @@ where
     Box::new(f)
 }
 
+/// Run an existing [KeyEventHandler] when its key binding is released rather than when it is
+/// pressed.
+pub fn on_release<X>(handler: Box<dyn KeyEventHandler<X>>) -> Box<dyn KeyEventHandler<X>>
+where
+    X: XConn + 'static,
+{
+    Box::new(Triggered {
+        inner: handler,
+        trigger: KeyTrigger::Release,
+    })
+}
+
+/// Run an existing [KeyEventHandler] only once when its key binding is pressed, ignoring the
+/// repeated key presses that are generated while the key is held down.
+pub fn no_repeat<X>(handler: Box<dyn KeyEventHandler<X>>) -> Box<dyn KeyEventHandler<X>>
+where
+    X: XConn + 'static,
+{
+    Box::new(Triggered {
+        inner: handler,
+        trigger: KeyTrigger::PressOnce,
+    })
+}
+
+struct Triggered<X: XConn> {
+    inner: Box<dyn KeyEventHandler<X>>,
+    trigger: KeyTrigger,
+}
+
+impl<X: XConn> KeyEventHandler<X> for Triggered<X> {
+    fn call(&mut self, state: &mut State<X>, x: &X) -> Result<()> {
+        self.inner.call(state, x)
+    }
+
+    fn trigger(&self) -> KeyTrigger {
+        self.trigger
+    }
+}
+
 /// Mutate the [ClientSet] and refresh the on screen state
 pub fn modify_with<F, X>(f: F) -> Box<dyn KeyEventHandler<X>>
 where
@@ -74,6 +117,72 @@ pub fn exit<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
     key_handler(|_, _| std::process::exit(0))
 }
 
+/// Restart penrose in place
+///
+/// The current [ClientSet] is written to a temporary file before re-executing the binary that
+/// penrose was started from (with the same arguments). The new process will then restore the
+/// persisted state on startup so that every existing client is re-adopted on its previous
+/// workspace and screen. See [State::persist_client_set] for details of what is restored.
+///
+/// If you are rebuilding your window manager binary between restarts, make sure that the new
+/// binary is written to the same path as the one that is currently running.
+#[cfg(feature = "serde")]
+pub fn restart<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
+    use crate::core::PERSISTED_STATE_ENV_VAR;
+    use std::{env, os::unix::process::CommandExt, process::Command};
+
+    key_handler(|s: &mut State<X>, x: &X| {
+        let path = env::temp_dir().join(format!("penrose-{}.json", std::process::id()));
+        info!(?path, "persisting state before restart");
+        s.persist_client_set(&path)?;
+        x.flush();
+
+        // Prefer argv[0] over current_exe as the latter will point to the old (now deleted)
+        // binary if it has been rebuilt since we started.
+        let mut args = env::args_os();
+        let prog = match args.next() {
+            Some(prog) => prog,
+            None => env::current_exe()?.into_os_string(),
+        };
+
+        info!(?prog, "restarting");
+        let err = Command::new(prog)
+            .args(args)
+            .env(PERSISTED_STATE_ENV_VAR, &path)
+            .exec();
+
+        // exec only returns if it failed to replace the current process
+        std::fs::remove_file(&path)?;
+
+        Err(err.into())
+    })
+}
+
+/// Enter the named key binding mode.
+///
+/// See [KeyBindings::add_mode][crate::core::bindings::KeyBindings::add_mode] for details of
+/// how to define the bindings for a mode.
+pub fn enter_mode<X: XConn>(name: impl Into<String>) -> Box<dyn KeyEventHandler<X>> {
+    let name = name.into();
+
+    key_handler(move |s: &mut State<X>, x: &X| s.enter_mode(name.clone(), x))
+}
+
+/// Exit the current key binding mode, returning to the default key bindings.
+pub fn exit_mode<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
+    key_handler(|s: &mut State<X>, x: &X| s.exit_mode(x))
+}
+
+/// Toggle the visibility of dock windows such as external status bars.
+///
+/// See [State::set_docks_visible] for details.
+pub fn toggle_docks<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
+    key_handler(|s: &mut State<X>, x: &X| {
+        let visible = s.docks_visible();
+        s.set_docks_visible(!visible, x)
+    })
+}
+
 /// Info log the current window manager [State] for debugging purposes.
 pub fn log_current_state<X: XConn + std::fmt::Debug>() -> Box<dyn KeyEventHandler<X>> {
     key_handler(|s: &mut State<X>, _| {
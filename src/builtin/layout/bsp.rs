@@ -0,0 +1,544 @@
+//! A binary space partitioning layout in the style of bspwm.
+use crate::{
+    builtin::layout::messages::{Balance, ExpandMain, Mirror, Preselect, Rotate, ShrinkMain},
+    core::layout::{Layout, Message},
+    pure::{geometry::Rect, RelativePosition, Stack},
+    Xid,
+};
+
+const MIN_RATIO: f32 = 0.05;
+const MAX_RATIO: f32 = 0.95;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Split {
+    Columns,
+    Rows,
+}
+
+#[derive(Debug, Clone)]
+struct Branch {
+    split: Split,
+    ratio: f32,
+    first: Box<Node>,
+    second: Box<Node>,
+}
+
+impl Branch {
+    fn rects(&self, r: Rect) -> (Rect, Rect) {
+        match self.split {
+            Split::Columns => {
+                let w = (r.w as f32 * self.ratio) as u32;
+                (
+                    Rect::new(r.x, r.y, w, r.h),
+                    Rect::new(r.x + w, r.y, r.w - w, r.h),
+                )
+            }
+            Split::Rows => {
+                let h = (r.h as f32 * self.ratio) as u32;
+                (
+                    Rect::new(r.x, r.y, r.w, h),
+                    Rect::new(r.x, r.y + h, r.w, r.h - h),
+                )
+            }
+        }
+    }
+
+    // Rotate this subtree by 90 degrees clockwise. Clients that were above one another are now
+    // placed side by side so the children of a Rows split need to swap places in order for the
+    // top client to end up on the right.
+    fn rotate(&mut self) {
+        self.split = match self.split {
+            Split::Columns => Split::Rows,
+            Split::Rows => {
+                std::mem::swap(&mut self.first, &mut self.second);
+                self.ratio = 1.0 - self.ratio;
+                Split::Columns
+            }
+        };
+
+        for child in [&mut self.first, &mut self.second] {
+            if let Node::Branch(b) = child.as_mut() {
+                b.rotate();
+            }
+        }
+    }
+
+    // Mirror this subtree along the given axis while preserving the size of each client. Only
+    // splits that run in the same direction as the axis being mirrored are flipped.
+    fn flip(&mut self, axis: Split) {
+        if self.split == axis {
+            std::mem::swap(&mut self.first, &mut self.second);
+            self.ratio = 1.0 - self.ratio;
+        }
+
+        for child in [&mut self.first, &mut self.second] {
+            if let Node::Branch(b) = child.as_mut() {
+                b.flip(axis);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Leaf(Xid),
+    Branch(Branch),
+}
+
+impl Node {
+    fn contains(&self, id: Xid) -> bool {
+        match self {
+            Node::Leaf(c) => *c == id,
+            Node::Branch(b) => b.first.contains(id) || b.second.contains(id),
+        }
+    }
+
+    fn last_leaf(&self) -> Xid {
+        match self {
+            Node::Leaf(c) => *c,
+            Node::Branch(b) => b.second.last_leaf(),
+        }
+    }
+
+    // Drop any leaves for clients that are no longer in the stack, replacing their parent with
+    // the remaining sibling.
+    fn retain(self, s: &Stack<Xid>) -> Option<Node> {
+        match self {
+            Node::Leaf(id) => s.contains(&id).then_some(Node::Leaf(id)),
+            Node::Branch(Branch {
+                split,
+                ratio,
+                first,
+                second,
+            }) => match (first.retain(s), second.retain(s)) {
+                (Some(first), Some(second)) => Some(Node::Branch(Branch {
+                    split,
+                    ratio,
+                    first: Box::new(first),
+                    second: Box::new(second),
+                })),
+                (Some(n), None) | (None, Some(n)) => Some(n),
+                (None, None) => None,
+            },
+        }
+    }
+
+    // Split the leaf holding `target` in order to make space for `id`. Without a preselected
+    // position the split is made along the longest side of the target with the new client placed
+    // second.
+    fn insert(
+        &mut self,
+        target: Xid,
+        id: Xid,
+        r: Rect,
+        presel: Option<RelativePosition>,
+        ratio: f32,
+    ) -> bool {
+        match self {
+            Node::Leaf(c) if *c == target => {
+                let (split, new_first) = match presel {
+                    Some(RelativePosition::Left) => (Split::Columns, true),
+                    Some(RelativePosition::Right) => (Split::Columns, false),
+                    Some(RelativePosition::Above) => (Split::Rows, true),
+                    Some(RelativePosition::Below) => (Split::Rows, false),
+                    None if r.w >= r.h => (Split::Columns, false),
+                    None => (Split::Rows, false),
+                };
+
+                let (first, second) = if new_first {
+                    (Node::Leaf(id), Node::Leaf(target))
+                } else {
+                    (Node::Leaf(target), Node::Leaf(id))
+                };
+
+                *self = Node::Branch(Branch {
+                    split,
+                    ratio,
+                    first: Box::new(first),
+                    second: Box::new(second),
+                });
+
+                true
+            }
+
+            Node::Leaf(_) => false,
+
+            Node::Branch(b) => {
+                let (r1, r2) = b.rects(r);
+                b.first.insert(target, id, r1, presel, ratio)
+                    || b.second.insert(target, id, r2, presel, ratio)
+            }
+        }
+    }
+
+    // The branch directly containing the leaf for `id` along with whether or not it is the
+    // first child of that branch.
+    fn parent_mut(&mut self, id: Xid) -> Option<(&mut Branch, bool)> {
+        let b = match self {
+            Node::Branch(b) => b,
+            Node::Leaf(_) => return None,
+        };
+
+        if matches!(*b.first, Node::Leaf(c) if c == id) {
+            return Some((b, true));
+        } else if matches!(*b.second, Node::Leaf(c) if c == id) {
+            return Some((b, false));
+        }
+
+        if b.first.contains(id) {
+            b.first.parent_mut(id)
+        } else {
+            b.second.parent_mut(id)
+        }
+    }
+
+    // Set split ratios so that every leaf is given an equal area, returning the number of leaves
+    // in this subtree.
+    fn balance(&mut self) -> usize {
+        match self {
+            Node::Leaf(_) => 1,
+            Node::Branch(b) => {
+                let n_first = b.first.balance();
+                let n_second = b.second.balance();
+                b.ratio = n_first as f32 / (n_first + n_second) as f32;
+
+                n_first + n_second
+            }
+        }
+    }
+
+    fn positions(&self, r: Rect, positions: &mut Vec<(Xid, Rect)>) {
+        match self {
+            Node::Leaf(id) => positions.push((*id, r)),
+            Node::Branch(b) => {
+                let (r1, r2) = b.rects(r);
+                b.first.positions(r1, positions);
+                b.second.positions(r2, positions);
+            }
+        }
+    }
+}
+
+/// A [Layout] that recursively splits the screen in two in order to make space for new clients.
+///
+/// Rather than being computed from the order of the clients in the [Stack], each workspace
+/// maintains its own tree of splits: new clients split the space occupied by the focused client
+/// along its longest side and removing a client gives its space back to its sibling.
+///
+/// The following messages are supported, each acting on the split containing the focused client:
+/// - [ExpandMain] and [ShrinkMain] grow and shrink the focused client.
+/// - [Rotate] rotates the split (and everything inside of it) by 90 degrees clockwise.
+/// - [Mirror] flips the split so that the focused client swaps places with its sibling.
+/// - [Balance] resets all split ratios so that each client has an equal share of the screen.
+/// - [Preselect] chooses where the next client will be placed relative to the focused client.
+#[derive(Debug, Clone)]
+pub struct BinarySpacePartition {
+    root: Option<Node>,
+    focus: Option<Xid>,
+    presel: Option<RelativePosition>,
+    ratio: f32,
+    ratio_step: f32,
+}
+
+impl Default for BinarySpacePartition {
+    fn default() -> Self {
+        Self::new(0.5, 0.05)
+    }
+}
+
+impl BinarySpacePartition {
+    /// Create a new [BinarySpacePartition] [Layout] as a boxed trait object.
+    pub fn boxed(ratio: f32, ratio_step: f32) -> Box<dyn Layout> {
+        Box::new(Self::new(ratio, ratio_step))
+    }
+
+    /// Create a new [BinarySpacePartition] [Layout] where new splits are made using the given
+    /// ratio.
+    pub fn new(ratio: f32, ratio_step: f32) -> Self {
+        Self {
+            root: None,
+            focus: None,
+            presel: None,
+            ratio,
+            ratio_step,
+        }
+    }
+
+    fn contains(&self, id: Xid) -> bool {
+        self.root.as_ref().map(|n| n.contains(id)).unwrap_or(false)
+    }
+
+    // Bring the split tree in line with the clients currently in the stack
+    fn sync(&mut self, s: &Stack<Xid>, r: Rect) {
+        self.root = self.root.take().and_then(|n| n.retain(s));
+
+        let mut target = [Some(s.focus), self.focus]
+            .into_iter()
+            .flatten()
+            .find(|&id| self.contains(id))
+            .or_else(|| self.root.as_ref().map(|n| n.last_leaf()));
+
+        for &id in s.iter() {
+            if self.contains(id) {
+                continue;
+            }
+
+            match (&mut self.root, target) {
+                (Some(root), Some(t)) => {
+                    root.insert(t, id, r, self.presel.take(), self.ratio);
+                }
+                _ => self.root = Some(Node::Leaf(id)),
+            }
+
+            target = Some(id);
+        }
+
+        self.focus = Some(s.focus);
+    }
+
+    fn adjust_ratio(&mut self, grow: bool) {
+        let (focus, step) = match self.focus {
+            Some(id) => (id, self.ratio_step),
+            None => return,
+        };
+
+        if let Some((b, is_first)) = self.root.as_mut().and_then(|n| n.parent_mut(focus)) {
+            let delta = if grow == is_first { step } else { -step };
+            b.ratio = (b.ratio + delta).clamp(MIN_RATIO, MAX_RATIO);
+        }
+    }
+
+    fn focused_parent(&mut self) -> Option<&mut Branch> {
+        let focus = self.focus?;
+
+        self.root
+            .as_mut()
+            .and_then(|n| n.parent_mut(focus))
+            .map(|(b, _)| b)
+    }
+}
+
+impl Layout for BinarySpacePartition {
+    fn name(&self) -> String {
+        "BSP".to_owned()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Layout> {
+        Box::new(self.clone())
+    }
+
+    fn layout(&mut self, s: &Stack<Xid>, r: Rect) -> (Option<Box<dyn Layout>>, Vec<(Xid, Rect)>) {
+        self.sync(s, r);
+
+        let mut positions = Vec::with_capacity(s.len());
+        if let Some(root) = &self.root {
+            root.positions(r, &mut positions);
+        }
+
+        (None, positions)
+    }
+
+    fn layout_empty(&mut self, _r: Rect) -> (Option<Box<dyn Layout>>, Vec<(Xid, Rect)>) {
+        self.root = None;
+        self.focus = None;
+
+        (None, vec![])
+    }
+
+    fn handle_message(&mut self, m: &Message) -> Option<Box<dyn Layout>> {
+        if let Some(&ExpandMain) = m.downcast_ref() {
+            self.adjust_ratio(true);
+        } else if let Some(&ShrinkMain) = m.downcast_ref() {
+            self.adjust_ratio(false);
+        } else if let Some(&Rotate) = m.downcast_ref() {
+            if let Some(b) = self.focused_parent() {
+                b.rotate();
+            }
+        } else if let Some(&Mirror) = m.downcast_ref() {
+            if let Some(b) = self.focused_parent() {
+                b.flip(b.split);
+            }
+        } else if let Some(&Balance) = m.downcast_ref() {
+            if let Some(root) = self.root.as_mut() {
+                root.balance();
+            }
+        } else if let Some(&Preselect(presel)) = m.downcast_ref() {
+            self.presel = presel;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::layout::IntoMessage, stack};
+
+    const R: Rect = Rect {
+        x: 0,
+        y: 0,
+        w: 100,
+        h: 100,
+    };
+
+    // Add clients 1..=n one at a time, focusing each as it is added
+    fn bsp_with_clients(n: u32) -> BinarySpacePartition {
+        let mut l = BinarySpacePartition::default();
+        for i in 1..=n {
+            l.layout(&Stack::new((1..i).map(Xid), Xid(i), []), R);
+        }
+
+        l
+    }
+
+    fn sorted_positions(l: &mut BinarySpacePartition, s: &Stack<Xid>) -> Vec<(Xid, Rect)> {
+        let (_, mut positions) = l.layout(s, R);
+        positions.sort_by_key(|&(id, _)| id);
+
+        positions
+    }
+
+    #[test]
+    fn new_clients_split_the_focused_client() {
+        let mut l = bsp_with_clients(3);
+        let s = stack!([Xid(1), Xid(2)], Xid(3));
+
+        assert_eq!(
+            sorted_positions(&mut l, &s),
+            vec![
+                (Xid(1), Rect::new(0, 0, 50, 100)),
+                (Xid(2), Rect::new(50, 0, 50, 50)),
+                (Xid(3), Rect::new(50, 50, 50, 50)),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_tree_is_independent_of_stack_order() {
+        let mut l = bsp_with_clients(3);
+        let s = stack!([Xid(3), Xid(2)], Xid(1));
+
+        assert_eq!(
+            sorted_positions(&mut l, &s),
+            vec![
+                (Xid(1), Rect::new(0, 0, 50, 100)),
+                (Xid(2), Rect::new(50, 0, 50, 50)),
+                (Xid(3), Rect::new(50, 50, 50, 50)),
+            ]
+        );
+    }
+
+    #[test]
+    fn removed_clients_give_their_space_to_their_sibling() {
+        let mut l = bsp_with_clients(3);
+        let s = stack!([Xid(1)], Xid(3));
+
+        assert_eq!(
+            sorted_positions(&mut l, &s),
+            vec![
+                (Xid(1), Rect::new(0, 0, 50, 100)),
+                (Xid(3), Rect::new(50, 0, 50, 100)),
+            ]
+        );
+    }
+
+    #[test]
+    fn preselect_controls_the_position_of_the_next_client() {
+        let mut l = bsp_with_clients(1);
+        l.handle_message(&Preselect(Some(RelativePosition::Above)).into_message());
+        let s = stack!([Xid(1)], Xid(2));
+
+        assert_eq!(
+            sorted_positions(&mut l, &s),
+            vec![
+                (Xid(1), Rect::new(0, 50, 100, 50)),
+                (Xid(2), Rect::new(0, 0, 100, 50)),
+            ]
+        );
+    }
+
+    #[test]
+    fn messages_act_on_the_focused_split() {
+        let mut l = bsp_with_clients(2);
+        let s = stack!([Xid(1)], Xid(2));
+
+        l.handle_message(&ExpandMain.into_message());
+        l.handle_message(&Rotate.into_message());
+        l.handle_message(&Mirror.into_message());
+
+        assert_eq!(
+            sorted_positions(&mut l, &s),
+            vec![
+                (Xid(1), Rect::new(0, 55, 100, 45)),
+                (Xid(2), Rect::new(0, 0, 100, 55)),
+            ]
+        );
+    }
+
+    // Three clients with mixed split axes are laid out as:
+    //   1 | 2
+    //     | -
+    //     | 3
+    #[test]
+    fn mirror_only_flips_splits_along_the_mirrored_axis() {
+        let mut l = bsp_with_clients(3);
+        let s = stack!([Xid(2), Xid(3)], Xid(1));
+        l.layout(&s, R);
+
+        l.handle_message(&Mirror.into_message());
+
+        assert_eq!(
+            sorted_positions(&mut l, &s),
+            vec![
+                (Xid(1), Rect::new(50, 0, 50, 100)),
+                (Xid(2), Rect::new(0, 0, 50, 50)),
+                (Xid(3), Rect::new(0, 50, 50, 50)),
+            ]
+        );
+    }
+
+    #[test]
+    fn rotate_turns_the_split_tree_clockwise() {
+        let mut l = bsp_with_clients(3);
+        let s = stack!([Xid(2), Xid(3)], Xid(1));
+        l.layout(&s, R);
+
+        l.handle_message(&Rotate.into_message());
+
+        assert_eq!(
+            sorted_positions(&mut l, &s),
+            vec![
+                (Xid(1), Rect::new(0, 0, 100, 50)),
+                (Xid(2), Rect::new(50, 50, 50, 50)),
+                (Xid(3), Rect::new(0, 50, 50, 50)),
+            ]
+        );
+    }
+
+    #[test]
+    fn rotating_four_times_restores_the_original_layout() {
+        let mut l = bsp_with_clients(4);
+        let s = stack!([Xid(2), Xid(3), Xid(4)], Xid(1));
+        l.handle_message(&ExpandMain.into_message());
+        let original = sorted_positions(&mut l, &s);
+
+        for _ in 0..4 {
+            l.handle_message(&Rotate.into_message());
+        }
+
+        assert_eq!(sorted_positions(&mut l, &s), original);
+    }
+
+    #[test]
+    fn balance_gives_each_client_equal_space() {
+        let mut l = bsp_with_clients(3);
+        l.handle_message(&Balance.into_message());
+        let s = stack!([Xid(1), Xid(2)], Xid(3));
+
+        let (_, positions) = l.layout(&s, Rect::new(0, 0, 300, 100));
+        let widths: Vec<u32> = positions.iter().map(|(_, r)| r.w).collect();
+
+        assert_eq!(widths, vec![100, 200, 200]);
+    }
+}
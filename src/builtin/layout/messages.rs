@@ -49,3 +49,16 @@ msg!(UnwrapTransformer);
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Hide;
 msg!(Hide);
+
+/// Reset the split ratios of a [crate::core::layout::Layout] so that each client is given an
+/// equal share of the screen.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Balance;
+msg!(Balance);
+
+/// Choose where the next client added to a [crate::core::layout::Layout] should be placed
+/// relative to the currently focused client. Sending `Preselect(None)` cancels any existing
+/// preselection.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Preselect(pub Option<crate::pure::RelativePosition>);
+msg!(Preselect);
@@ -1,14 +1,19 @@
 //! Built-in layouts.
 use crate::{
     builtin::layout::messages::{ExpandMain, IncMain, Mirror, Rotate, ShrinkMain},
-    core::layout::{Layout, Message},
+    core::layout::{ClientInfo, Layout, Message},
     pure::{geometry::Rect, Stack},
-    Xid,
+    Result, Xid,
 };
 
 pub mod messages;
 pub mod transformers;
 
+mod bsp;
+
+#[doc(inline)]
+pub use bsp::BinarySpacePartition;
+
 #[derive(Debug, Clone, Copy)]
 enum StackPosition {
     Side,
@@ -182,35 +187,517 @@ impl Layout for MainAndStack {
     }
 
     fn handle_message(&mut self, m: &Message) -> Option<Box<dyn Layout>> {
-        if let Some(&ExpandMain) = m.downcast_ref() {
-            self.ratio += self.ratio_step;
-            if self.ratio > 1.0 {
-                self.ratio = 1.0;
-            }
-        } else if let Some(&ShrinkMain) = m.downcast_ref() {
-            self.ratio -= self.ratio_step;
-            if self.ratio < 0.0 {
-                self.ratio = 0.0;
-            }
-        } else if let Some(&IncMain(n)) = m.downcast_ref() {
-            if n < 0 {
-                self.max_main = self.max_main.saturating_sub((-n) as u32);
+        handle_main_message(
+            m,
+            &mut self.max_main,
+            &mut self.ratio,
+            self.ratio_step,
+            &mut self.pos,
+            &mut self.mirrored,
+        );
+
+        None
+    }
+}
+
+/// A [Layout] that arranges clients in a near-square grid of rows and columns.
+///
+/// By default all clients are placed in the grid but sending an [IncMain] message will reserve a
+/// main region for that many clients to the side of the grid, sized using [ShrinkMain] and
+/// [ExpandMain]. Sending a [Rotate] message switches between filling the grid column by column
+/// (with the main region to the left) and row by row (with the main region on top), and a [Mirror]
+/// message flips the layout so that the main region is on the opposite side.
+#[derive(Debug, Clone, Copy)]
+pub struct Grid {
+    pos: StackPosition,
+    max_main: u32,
+    ratio: f32,
+    ratio_step: f32,
+    mirrored: bool,
+}
+
+impl Grid {
+    /// Create a new [Grid] [Layout] as a boxed trait object.
+    pub fn boxed(max_main: u32, ratio: f32, ratio_step: f32) -> Box<dyn Layout> {
+        Box::new(Self::new(max_main, ratio, ratio_step))
+    }
+
+    /// Create a new [Grid] [Layout] with a main region for `max_main` clients to the left of the
+    /// grid. Setting `max_main` to 0 places all clients in the grid.
+    pub fn new(max_main: u32, ratio: f32, ratio_step: f32) -> Self {
+        Self {
+            pos: StackPosition::Side,
+            max_main,
+            ratio,
+            ratio_step,
+            mirrored: false,
+        }
+    }
+
+    fn layout_columns(&self, r: Rect, n: u32) -> Vec<Rect> {
+        if self.max_main == 0 || n <= self.max_main {
+            return grid_columns(r, n);
+        }
+
+        let split = (r.w as f32 * self.ratio) as u32;
+        match r.split_at_width(split) {
+            Some((main, rest)) if split > 0 => main
+                .as_rows(self.max_main)
+                .into_iter()
+                .chain(grid_columns(rest, n - self.max_main))
+                .collect(),
+            _ => grid_columns(r, n),
+        }
+    }
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self::new(0, 0.6, 0.1)
+    }
+}
+
+impl Layout for Grid {
+    fn name(&self) -> String {
+        "Grid".to_owned()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Layout> {
+        Box::new(*self)
+    }
+
+    fn layout(&mut self, s: &Stack<Xid>, r: Rect) -> (Option<Box<dyn Layout>>, Vec<(Xid, Rect)>) {
+        let rects = oriented(self.pos, self.mirrored, r, |r| {
+            self.layout_columns(r, s.len() as u32)
+        });
+
+        (
+            None,
+            rects.into_iter().zip(s).map(|(r, c)| (*c, r)).collect(),
+        )
+    }
+
+    fn handle_message(&mut self, m: &Message) -> Option<Box<dyn Layout>> {
+        handle_main_message(
+            m,
+            &mut self.max_main,
+            &mut self.ratio,
+            self.ratio_step,
+            &mut self.pos,
+            &mut self.mirrored,
+        );
+
+        None
+    }
+}
+
+/// A three column [Layout] with the main region in the center of the screen and the remaining
+/// clients split between columns on either side.
+///
+/// If there is only a single client outside of the main region then it is placed to the right of
+/// the main region rather than leaving an empty column. The main region can be adjusted using the
+/// [IncMain], [ExpandMain] and [ShrinkMain] messages in the same way as for [MainAndStack]. Sending
+/// a [Rotate] message switches to using rows rather than columns and sending a [Mirror] message
+/// swaps the two side columns.
+#[derive(Debug, Clone, Copy)]
+pub struct CenteredMain {
+    pos: StackPosition,
+    max_main: u32,
+    ratio: f32,
+    ratio_step: f32,
+    mirrored: bool,
+}
+
+impl CenteredMain {
+    /// Create a new [CenteredMain] [Layout] as a boxed trait object.
+    pub fn boxed(max_main: u32, ratio: f32, ratio_step: f32) -> Box<dyn Layout> {
+        Box::new(Self::new(max_main, ratio, ratio_step))
+    }
+
+    /// Create a new [CenteredMain] [Layout] with three columns.
+    pub fn new(max_main: u32, ratio: f32, ratio_step: f32) -> Self {
+        Self {
+            pos: StackPosition::Side,
+            max_main,
+            ratio,
+            ratio_step,
+            mirrored: false,
+        }
+    }
+
+    fn layout_columns(&self, r: Rect, n: u32) -> Vec<Rect> {
+        let n_main = if self.ratio == 0.0 {
+            0
+        } else {
+            self.max_main.min(n)
+        };
+        let n_stack = n - n_main;
+        let w_main = (r.w as f32 * self.ratio) as u32;
+
+        if n_stack == 0 || self.ratio == 1.0 {
+            return r.as_rows(n);
+        }
+
+        if n_main == 0 {
+            let n_left = n.div_ceil(2);
+            let (left, right) = match r.split_at_width(r.w / 2) {
+                Some(cols) if n > 1 => cols,
+                _ => return r.as_rows(n),
+            };
+
+            return left
+                .as_rows(n_left)
+                .into_iter()
+                .chain(right.as_rows(n - n_left))
+                .collect();
+        }
+
+        if n_stack == 1 {
+            return match r.split_at_width(w_main) {
+                Some((main, stack)) => main.as_rows(n_main).into_iter().chain([stack]).collect(),
+                None => r.as_rows(n),
+            };
+        }
+
+        let w_side = (r.w - w_main) / 2;
+        let left = Rect::new(r.x, r.y, w_side, r.h);
+        let main = Rect::new(r.x + w_side, r.y, w_main, r.h);
+        let right = Rect::new(r.x + w_side + w_main, r.y, r.w - w_side - w_main, r.h);
+        let n_left = n_stack.div_ceil(2);
+
+        main.as_rows(n_main)
+            .into_iter()
+            .chain(left.as_rows(n_left))
+            .chain(right.as_rows(n_stack - n_left))
+            .collect()
+    }
+}
+
+impl Default for CenteredMain {
+    fn default() -> Self {
+        Self::new(1, 0.5, 0.1)
+    }
+}
+
+impl Layout for CenteredMain {
+    fn name(&self) -> String {
+        "Center".to_owned()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Layout> {
+        Box::new(*self)
+    }
+
+    fn layout(&mut self, s: &Stack<Xid>, r: Rect) -> (Option<Box<dyn Layout>>, Vec<(Xid, Rect)>) {
+        let rects = oriented(self.pos, self.mirrored, r, |r| {
+            self.layout_columns(r, s.len() as u32)
+        });
+
+        (
+            None,
+            rects.into_iter().zip(s).map(|(r, c)| (*c, r)).collect(),
+        )
+    }
+
+    fn handle_message(&mut self, m: &Message) -> Option<Box<dyn Layout>> {
+        handle_main_message(
+            m,
+            &mut self.max_main,
+            &mut self.ratio,
+            self.ratio_step,
+            &mut self.pos,
+            &mut self.mirrored,
+        );
+
+        None
+    }
+}
+
+/// A [Layout] that gives each client a fixed share of the space remaining after the clients
+/// before it have been placed, alternating between splitting by width and by height.
+///
+/// - `Spiral::spiral` places clients in an inward spiral (left, top, right, bottom, ...).
+/// - `Spiral::dwindle` always places the remaining clients to the right or bottom of the
+///   current client.
+///
+/// The share given to each client can be adjusted by sending [ExpandMain] and [ShrinkMain]
+/// messages and the layout can be flipped horizontally by sending a [Mirror] message.
+#[derive(Debug, Clone, Copy)]
+pub struct Spiral {
+    ratio: f32,
+    ratio_step: f32,
+    dwindle: bool,
+    mirrored: bool,
+}
+
+impl Spiral {
+    /// Create a new [Spiral] [Layout] that places clients in an inward spiral.
+    pub fn spiral(ratio: f32, ratio_step: f32) -> Box<dyn Layout> {
+        Box::new(Self::new(ratio, ratio_step, false))
+    }
+
+    /// Create a new [Spiral] [Layout] that places clients in a dwindling sequence towards the
+    /// bottom right of the screen.
+    pub fn dwindle(ratio: f32, ratio_step: f32) -> Box<dyn Layout> {
+        Box::new(Self::new(ratio, ratio_step, true))
+    }
+
+    /// Create a new [Spiral] [Layout] without boxing it.
+    pub fn new(ratio: f32, ratio_step: f32, dwindle: bool) -> Self {
+        Self {
+            ratio,
+            ratio_step,
+            dwindle,
+            mirrored: false,
+        }
+    }
+
+    fn layout_columns(&self, r: Rect, n: u32) -> Vec<Rect> {
+        let mut rects = Vec::with_capacity(n as usize);
+        let mut remaining = r;
+
+        for i in 0..n.saturating_sub(1) {
+            // Clients take the first half of the split except when spiraling back round on
+            // the right and bottom edges of the screen.
+            let first = self.dwindle || i % 4 < 2;
+            let ratio = if first { self.ratio } else { 1.0 - self.ratio };
+
+            let split = if i % 2 == 0 {
+                remaining.split_at_width((remaining.w as f32 * ratio) as u32)
             } else {
-                self.max_main += n as u32;
+                remaining.split_at_height((remaining.h as f32 * ratio) as u32)
+            };
+
+            match split {
+                Some((a, b)) if a.w > 0 && a.h > 0 => {
+                    let (client, rest) = if first { (a, b) } else { (b, a) };
+                    rects.push(client);
+                    remaining = rest;
+                }
+
+                // Once we can no longer split the remaining space the rest of the clients
+                // are stacked within it.
+                _ => {
+                    rects.extend(remaining.as_rows(n - i));
+                    return rects;
+                }
             }
+        }
+
+        if n > 0 {
+            rects.push(remaining);
+        }
+
+        rects
+    }
+}
+
+impl Default for Spiral {
+    fn default() -> Self {
+        Self::new(0.5, 0.1, false)
+    }
+}
+
+impl Layout for Spiral {
+    fn name(&self) -> String {
+        if self.dwindle {
+            "Dwindle".to_owned()
+        } else {
+            "Spiral".to_owned()
+        }
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Layout> {
+        Box::new(*self)
+    }
+
+    fn layout(&mut self, s: &Stack<Xid>, r: Rect) -> (Option<Box<dyn Layout>>, Vec<(Xid, Rect)>) {
+        let rects = oriented(StackPosition::Side, self.mirrored, r, |r| {
+            self.layout_columns(r, s.len() as u32)
+        });
+
+        (
+            None,
+            rects.into_iter().zip(s).map(|(r, c)| (*c, r)).collect(),
+        )
+    }
+
+    fn handle_message(&mut self, m: &Message) -> Option<Box<dyn Layout>> {
+        if let Some(&ExpandMain) = m.downcast_ref() {
+            self.ratio = (self.ratio + self.ratio_step).min(1.0);
+        } else if let Some(&ShrinkMain) = m.downcast_ref() {
+            self.ratio = (self.ratio - self.ratio_step).max(0.0);
         } else if let Some(&Mirror) = m.downcast_ref() {
             self.mirrored = !self.mirrored;
-        } else if let Some(&Rotate) = m.downcast_ref() {
-            self.pos = match self.pos {
-                StackPosition::Side => StackPosition::Bottom,
-                StackPosition::Bottom => StackPosition::Side,
-            };
         }
 
         None
     }
 }
 
+// Split a Rect into a near-square grid of n cells, filling column by column. When the clients do
+// not divide evenly between the columns, the extra clients are placed in the right-most columns.
+fn grid_columns(r: Rect, n: u32) -> Vec<Rect> {
+    if n == 0 {
+        return vec![];
+    }
+
+    let n_cols = (n as f64).sqrt().ceil() as u32;
+    let per_col = n / n_cols;
+    let extra = n % n_cols;
+
+    r.as_columns(n_cols)
+        .into_iter()
+        .enumerate()
+        .flat_map(|(i, col)| {
+            let in_col = if i as u32 >= n_cols - extra {
+                per_col + 1
+            } else {
+                per_col
+            };
+
+            col.as_rows(in_col)
+        })
+        .collect()
+}
+
+// Run a layout function that is written in terms of columns in the requested orientation,
+// mirroring the resulting positions if needed. Rows are handled by transposing the x and y
+// axes before and after running the layout.
+fn oriented<F>(pos: StackPosition, mirrored: bool, r: Rect, f: F) -> Vec<Rect>
+where
+    F: FnOnce(Rect) -> Vec<Rect>,
+{
+    let transpose = |r: Rect| match pos {
+        StackPosition::Side => r,
+        StackPosition::Bottom => Rect::new(r.y, r.x, r.h, r.w),
+    };
+
+    let base = transpose(r);
+
+    f(base)
+        .into_iter()
+        .map(|c| {
+            if mirrored {
+                Rect::new(base.x + base.x + base.w - c.x - c.w, c.y, c.w, c.h)
+            } else {
+                c
+            }
+        })
+        .map(transpose)
+        .collect()
+}
+
+// The handling of messages that is shared between layouts with a main region.
+fn handle_main_message(
+    m: &Message,
+    max_main: &mut u32,
+    ratio: &mut f32,
+    ratio_step: f32,
+    pos: &mut StackPosition,
+    mirrored: &mut bool,
+) {
+    if let Some(&ExpandMain) = m.downcast_ref() {
+        *ratio = (*ratio + ratio_step).min(1.0);
+    } else if let Some(&ShrinkMain) = m.downcast_ref() {
+        *ratio = (*ratio - ratio_step).max(0.0);
+    } else if let Some(&IncMain(n)) = m.downcast_ref() {
+        if n < 0 {
+            *max_main = max_main.saturating_sub((-n) as u32);
+        } else {
+            *max_main += n as u32;
+        }
+    } else if let Some(&Mirror) = m.downcast_ref() {
+        *mirrored = !*mirrored;
+    } else if let Some(&Rotate) = m.downcast_ref() {
+        *pos = match pos {
+            StackPosition::Side => StackPosition::Bottom,
+            StackPosition::Bottom => StackPosition::Side,
+        };
+    }
+}
+
+/// A [MainAndStack] layout that places clients based on their WM_CLASS rather than their
+/// position in the stack.
+///
+/// Clients whose class name is one of the provided classes are placed in the main region and all
+/// other clients are placed in the secondary stack, allowing for setups such as keeping a browser
+/// in the main region with terminals in the side column. If the properties of the clients are not
+/// available then this behaves the same as the wrapped [MainAndStack].
+#[derive(Debug, Clone)]
+pub struct MainByClass {
+    classes: Vec<String>,
+    inner: MainAndStack,
+}
+
+impl MainByClass {
+    /// Create a new [MainByClass] [Layout] placing clients with the given class names in the main
+    /// region of `inner`, as a boxed trait object.
+    pub fn boxed<S: Into<String>>(
+        classes: impl IntoIterator<Item = S>,
+        inner: MainAndStack,
+    ) -> Box<dyn Layout> {
+        Box::new(Self::new(classes, inner))
+    }
+
+    /// Create a new [MainByClass] [Layout] placing clients with the given class names in the main
+    /// region of `inner`.
+    pub fn new<S: Into<String>>(classes: impl IntoIterator<Item = S>, inner: MainAndStack) -> Self {
+        Self {
+            classes: classes.into_iter().map(Into::into).collect(),
+            inner,
+        }
+    }
+}
+
+impl Layout for MainByClass {
+    fn name(&self) -> String {
+        "MainByClass".to_owned()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Layout> {
+        Box::new(self.clone())
+    }
+
+    fn layout_workspace_with_info(
+        &mut self,
+        _tag: &str,
+        stack: &Option<Stack<Xid>>,
+        r: Rect,
+        info: &dyn ClientInfo,
+    ) -> Result<(Option<Box<dyn Layout>>, Vec<(Xid, Rect)>)> {
+        let s = match stack {
+            Some(s) => s,
+            None => return Ok(self.layout_empty(r)),
+        };
+
+        let mut main = Vec::new();
+        let mut rest = Vec::new();
+        for &id in s.iter() {
+            let class = info.class_name(id)?;
+            if self.classes.iter().any(|c| c == class) {
+                main.push(id);
+            } else {
+                rest.push(id);
+            }
+        }
+
+        let mut l = self.inner;
+        l.max_main = main.len() as u32;
+        let s = Stack::from_iter_unchecked(main.into_iter().chain(rest));
+
+        Ok(l.layout(&s, r))
+    }
+
+    fn layout(&mut self, s: &Stack<Xid>, r: Rect) -> (Option<Box<dyn Layout>>, Vec<(Xid, Rect)>) {
+        self.inner.layout(s, r)
+    }
+
+    fn handle_message(&mut self, m: &Message) -> Option<Box<dyn Layout>> {
+        self.inner.handle_message(m)
+    }
+}
+
 /// A simple monolce layout that gives the maximum available space to the currently
 /// focused client and unmaps all other windows.
 #[derive(Debug, Clone, Copy)]
@@ -246,7 +733,11 @@ mod tests {
     use crate::{
         builtin::layout::{messages::IncMain, *},
         core::layout::IntoMessage,
+        stack,
+        x::property::ClientProperties,
     };
+    use simple_test_case::test_case;
+    use std::collections::HashMap;
 
     #[test]
     fn message_handling() {
@@ -256,4 +747,191 @@ mod tests {
 
         assert_eq!(l.max_main, 3);
     }
+
+    #[test_case(1, vec![Rect::new(0, 0, 120, 120)]; "single client")]
+    #[test_case(
+        3,
+        vec![
+            Rect::new(0, 0, 60, 120),
+            Rect::new(60, 0, 60, 60),
+            Rect::new(60, 60, 60, 60),
+        ];
+        "uneven columns"
+    )]
+    #[test_case(
+        4,
+        vec![
+            Rect::new(0, 0, 60, 60),
+            Rect::new(0, 60, 60, 60),
+            Rect::new(60, 0, 60, 60),
+            Rect::new(60, 60, 60, 60),
+        ];
+        "square"
+    )]
+    #[test]
+    fn grid_positions(n: u32, expected: Vec<Rect>) {
+        let s = Stack::try_from_iter((1..=n).map(Xid)).unwrap();
+        let (_, positions) = Grid::default().layout(&s, Rect::new(0, 0, 120, 120));
+        let rects: Vec<Rect> = positions.into_iter().map(|(_, r)| r).collect();
+
+        assert_eq!(rects, expected);
+    }
+
+    #[test_case(Grid::new(1, 0.6, 0.1); "constructed with main region")]
+    #[test_case({
+        let mut l = Grid::default();
+        l.handle_message(&IncMain(1).into_message());
+        l
+    }; "main region added by message")]
+    #[test]
+    fn grid_with_main_region(mut l: Grid) {
+        let s = stack!(Xid(1), [Xid(2), Xid(3)]);
+
+        let (_, positions) = l.layout(&s, Rect::new(0, 0, 100, 100));
+
+        assert_eq!(
+            positions,
+            vec![
+                (Xid(1), Rect::new(0, 0, 60, 100)),
+                (Xid(2), Rect::new(60, 0, 20, 100)),
+                (Xid(3), Rect::new(80, 0, 20, 100)),
+            ]
+        );
+    }
+
+    #[test_case(1, vec![Rect::new(0, 0, 100, 100)]; "main only")]
+    #[test_case(
+        2,
+        vec![Rect::new(0, 0, 50, 100), Rect::new(50, 0, 50, 100)];
+        "single stack client"
+    )]
+    #[test_case(
+        4,
+        vec![
+            Rect::new(25, 0, 50, 100),
+            Rect::new(0, 0, 25, 50),
+            Rect::new(0, 50, 25, 50),
+            Rect::new(75, 0, 25, 100),
+        ];
+        "three columns"
+    )]
+    #[test]
+    fn centered_main_positions(n: u32, expected: Vec<Rect>) {
+        let s = Stack::try_from_iter((1..=n).map(Xid)).unwrap();
+        let (_, positions) = CenteredMain::default().layout(&s, Rect::new(0, 0, 100, 100));
+        let rects: Vec<Rect> = positions.into_iter().map(|(_, r)| r).collect();
+
+        assert_eq!(rects, expected);
+    }
+
+    #[test]
+    fn centered_main_rotate_and_mirror() {
+        let mut l = CenteredMain::default();
+        l.handle_message(&Rotate.into_message());
+        l.handle_message(&Mirror.into_message());
+        let s = stack!(Xid(1), [Xid(2), Xid(3)]);
+
+        let (_, positions) = l.layout(&s, Rect::new(0, 0, 100, 100));
+
+        assert_eq!(
+            positions,
+            vec![
+                (Xid(1), Rect::new(0, 25, 100, 50)),
+                (Xid(2), Rect::new(0, 75, 100, 25)),
+                (Xid(3), Rect::new(0, 0, 100, 25)),
+            ]
+        );
+    }
+
+    #[test_case(
+        Spiral::new(0.5, 0.1, false),
+        vec![
+            Rect::new(0, 0, 50, 100),
+            Rect::new(50, 0, 50, 50),
+            Rect::new(75, 50, 25, 50),
+            Rect::new(50, 50, 25, 50),
+        ];
+        "spiral"
+    )]
+    #[test_case(
+        Spiral::new(0.5, 0.1, true),
+        vec![
+            Rect::new(0, 0, 50, 100),
+            Rect::new(50, 0, 50, 50),
+            Rect::new(50, 50, 25, 50),
+            Rect::new(75, 50, 25, 50),
+        ];
+        "dwindle"
+    )]
+    #[test]
+    fn spiral_positions(mut l: Spiral, expected: Vec<Rect>) {
+        let s = stack!(Xid(1), [Xid(2), Xid(3), Xid(4)]);
+        let (_, positions) = l.layout(&s, Rect::new(0, 0, 100, 100));
+        let rects: Vec<Rect> = positions.into_iter().map(|(_, r)| r).collect();
+
+        assert_eq!(rects, expected);
+    }
+
+    #[test]
+    fn spiral_messages() {
+        let mut l = Spiral::new(0.5, 0.1, true);
+        l.handle_message(&ExpandMain.into_message());
+        l.handle_message(&Mirror.into_message());
+        let s = stack!(Xid(1), [Xid(2)]);
+
+        let (_, positions) = l.layout(&s, Rect::new(0, 0, 100, 100));
+
+        assert_eq!(
+            positions,
+            vec![
+                (Xid(1), Rect::new(40, 0, 60, 100)),
+                (Xid(2), Rect::new(0, 0, 40, 100)),
+            ]
+        );
+    }
+
+    fn class_info(classes: &[(u32, &str)]) -> HashMap<Xid, ClientProperties> {
+        classes
+            .iter()
+            .map(|&(id, class)| {
+                let props = ClientProperties {
+                    class_name: class.to_owned(),
+                    ..Default::default()
+                };
+
+                (Xid(id), props)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn main_by_class_places_matching_clients_in_main() {
+        let mut l = MainByClass::new(["firefox"], MainAndStack::default());
+        let info = class_info(&[(1, "Alacritty"), (2, "firefox"), (3, "Alacritty")]);
+        let s = Some(stack!(Xid(1), [Xid(2), Xid(3)]));
+
+        let (_, positions) = l
+            .layout_workspace_with_info("1", &s, Rect::new(0, 0, 100, 100), &info)
+            .unwrap();
+
+        assert_eq!(
+            positions,
+            vec![
+                (Xid(2), Rect::new(0, 0, 60, 100)),
+                (Xid(1), Rect::new(60, 0, 40, 50)),
+                (Xid(3), Rect::new(60, 50, 40, 50)),
+            ]
+        );
+    }
+
+    #[test]
+    fn main_by_class_errors_for_unknown_clients() {
+        let mut l = MainByClass::new(["firefox"], MainAndStack::default());
+        let info = class_info(&[(1, "Alacritty")]);
+        let s = Some(stack!(Xid(1), [Xid(2)]));
+
+        let res = l.layout_workspace_with_info("1", &s, Rect::new(0, 0, 100, 100), &info);
+
+        assert!(res.is_err());
+    }
 }
@@ -115,7 +115,9 @@ impl LayoutTransformer for Gaps {
 
 /// Reserve `px` pixels at the top of the screen.
 ///
-/// Typically used for providing space for a status bar.
+/// Typically used for providing space for a status bar. Docks and status bars that set
+/// `_NET_WM_STRUT` or `_NET_WM_STRUT_PARTIAL` have space reserved for them automatically so
+/// this is only needed for bars that do not.
 #[derive(Debug, Clone)]
 pub struct ReserveTop {
     /// The wrapped inner layout
@@ -9,9 +9,16 @@ use crate::{
 use penrose_keysyms::XKeySym;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, convert::TryFrom, fmt, process::Command};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    fmt,
+    iter::FromIterator,
+    ops::{Deref, DerefMut},
+    process::Command,
+};
 use strum::{EnumIter, IntoEnumIterator};
-use tracing::trace;
+use tracing::{trace, warn};
 
 /// Run the xmodmap command to dump the system keymap table.
 ///
@@ -20,25 +27,48 @@ use tracing::trace;
 /// would expect while also ensuring that it is east to debug any odd
 /// issues with bindings by referring the user to the xmodmap output.
 ///
-/// # Panics
-/// This function will panic if it is unable to fetch keycodes using the xmodmap
-/// binary on your system or if the output of `xmodmap -pke` is not valid
+/// An error is returned if the xmodmap binary can not be run or if the output
+/// of `xmodmap -pke` is not valid.
 pub fn keycodes_from_xmodmap() -> Result<HashMap<String, u8>> {
     let output = Command::new("xmodmap").arg("-pke").output()?;
-    let m = String::from_utf8(output.stdout)?
-        .lines()
-        .flat_map(|l| {
-            let mut words = l.split_whitespace(); // keycode <code> = <names ...>
-            let key_code: u8 = match words.nth(1) {
-                Some(word) => match word.parse() {
-                    Ok(val) => val,
-                    Err(e) => panic!("{}", e),
-                },
-                None => panic!("unexpected output format from xmodmap -pke"),
-            };
-            words.skip(1).map(move |name| (name.into(), key_code))
-        })
-        .collect();
+    let mut m = HashMap::new();
+
+    for l in String::from_utf8(output.stdout)?.lines() {
+        let mut words = l.split_whitespace(); // keycode <code> = <names ...>
+        let key_code: u8 = match words.nth(1) {
+            Some(word) => word.parse()?,
+            None => return Err(Error::InvalidXmodmapOutput { line: l.to_owned() }),
+        };
+
+        m.extend(words.skip(1).map(|name| (name.to_owned(), key_code)));
+    }
+
+    Ok(m)
+}
+
+/// Fetch the current keyboard mapping directly from the X server and resolve the
+/// name of each keysym using [XKeySym].
+///
+/// The names returned match those used by `xmodmap` so this can be used as a drop in
+/// replacement for [keycodes_from_xmodmap] without needing the xmodmap binary to be
+/// installed. Keysyms without a known name are skipped.
+#[cfg(feature = "keysyms")]
+pub fn keycodes_from_xconn<X: XConn>(x: &X) -> Result<HashMap<String, u8>> {
+    use strum::IntoEnumIterator as _;
+
+    let mut names: HashMap<u32, Vec<XKeySym>> = HashMap::new();
+    for sym in XKeySym::iter() {
+        names.entry(sym.as_u32()).or_default().push(sym);
+    }
+
+    let mut m = HashMap::new();
+    for (code, syms) in x.keyboard_mapping()? {
+        for sym in syms.iter().flat_map(|s| names.get(s)).flatten() {
+            // Prefer the lowest key code if a keysym is bound to multiple keys
+            let entry = m.entry(sym.as_ref().to_owned()).or_insert(code);
+            *entry = code.min(*entry);
+        }
+    }
 
     Ok(m)
 }
@@ -70,6 +100,9 @@ fn parse_binding(pattern: &str, known_codes: &HashMap<String, u8>) -> Result<Key
 /// Parse string format key bindings into [KeyCode] based [KeyBindings] using
 /// the command line `xmodmap` utility.
 ///
+/// The string form of each binding is retained so that the bindings can be re-parsed
+/// if the keyboard mapping changes while the window manager is running.
+///
 /// See [keycodes_from_xmodmap] for details of how `xmodmap` is used.
 pub fn parse_keybindings_with_xmodmap<S, X>(
     str_bindings: HashMap<S, Box<dyn KeyEventHandler<X>>>,
@@ -78,14 +111,89 @@ where
     S: AsRef<str>,
     X: XConn,
 {
-    let m = keycodes_from_xmodmap()?;
+    parse_keybindings_with_keycodes(str_bindings, &keycodes_from_xmodmap()?)
+}
+
+/// Parse string format key bindings into [KeyCode] based [KeyBindings] using the
+/// keyboard mapping reported by the X server.
+///
+/// This behaves in the same way as [parse_keybindings_with_xmodmap] but does not require
+/// the `xmodmap` binary to be installed. See [keycodes_from_xconn] for details.
+#[cfg(feature = "keysyms")]
+pub fn parse_keybindings<S, X>(
+    str_bindings: HashMap<S, Box<dyn KeyEventHandler<X>>>,
+    x: &X,
+) -> Result<KeyBindings<X>>
+where
+    S: AsRef<str>,
+    X: XConn,
+{
+    parse_keybindings_with_keycodes(str_bindings, &keycodes_from_xconn(x)?)
+}
+
+fn parse_keybindings_with_keycodes<S, X>(
+    str_bindings: HashMap<S, Box<dyn KeyEventHandler<X>>>,
+    m: &HashMap<String, u8>,
+) -> Result<KeyBindings<X>>
+where
+    S: AsRef<str>,
+    X: XConn,
+{
+    let mut bindings = KeyBindings::default();
+    bindings.set_special_codes(m);
+
+    for (s, handler) in str_bindings.into_iter() {
+        let seq = parse_sequence(s.as_ref(), m)?;
+        bindings.names.insert(seq.clone(), s.as_ref().to_owned());
+
+        if seq.len() == 1 {
+            bindings.bindings.insert(seq[0], handler);
+        } else {
+            bindings.chords.insert(seq, handler);
+        }
+    }
+
+    Ok(bindings)
+}
+
+// Key sequences are space separated bindings, e.g. "M-w h"
+fn parse_sequence(pattern: &str, known_codes: &HashMap<String, u8>) -> Result<Vec<KeyCode>> {
+    let seq: Vec<KeyCode> = pattern
+        .split_whitespace()
+        .map(|p| parse_binding(p, known_codes))
+        .collect::<Result<_>>()?;
+
+    if seq.is_empty() {
+        return Err(Error::UnknownKeyName {
+            name: pattern.to_owned(),
+        });
+    }
 
-    str_bindings
-        .into_iter()
-        .map(|(s, v)| parse_binding(s.as_ref(), &m).map(|k| (k, v)))
-        .collect()
+    Ok(seq)
 }
 
+// Keys that only act as modifiers along with the modifier mask bit that they set using the
+// default X modifier mapping. These are ignored while we are waiting for the next key in a
+// chord or running a mode so that bindings using modifiers can be pressed.
+const MODIFIER_KEYS: &[(&str, KeyCodeMask)] = &[
+    ("Shift_L", 1 << 0),
+    ("Shift_R", 1 << 0),
+    ("Caps_Lock", 1 << 1),
+    ("Control_L", 1 << 2),
+    ("Control_R", 1 << 2),
+    ("Alt_L", 1 << 3),
+    ("Alt_R", 1 << 3),
+    ("Meta_L", 1 << 3),
+    ("Meta_R", 1 << 3),
+    ("Num_Lock", 1 << 4),
+    ("Super_L", 1 << 6),
+    ("Super_R", 1 << 6),
+    ("Hyper_L", 1 << 6),
+    ("Hyper_R", 1 << 6),
+    ("ISO_Level3_Shift", 1 << 7),
+    ("Mode_switch", 1 << 7),
+];
+
 /// Some action to be run by a user key binding
 pub trait KeyEventHandler<X>
 where
@@ -93,6 +201,29 @@ where
 {
     /// Call this handler with the current window manager state
     fn call(&mut self, state: &mut State<X>, x: &X) -> Result<()>;
+
+    /// The key events that this handler should be called for.
+    ///
+    /// By default handlers are called each time their key binding is pressed, including for
+    /// repeated presses generated by the key being held down.
+    fn trigger(&self) -> KeyTrigger {
+        KeyTrigger::Press
+    }
+}
+
+/// When a [KeyEventHandler] should be run in response to its key binding.
+///
+/// See [on_release][crate::builtin::actions::on_release] and
+/// [no_repeat][crate::builtin::actions::no_repeat] for wrapping existing handlers.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum KeyTrigger {
+    /// Run on each press of the key, including auto-repeated presses while it is held down
+    #[default]
+    Press,
+    /// Run only when the key is initially pressed, ignoring auto-repeat
+    PressOnce,
+    /// Run when the key is released
+    Release,
 }
 
 impl<X: XConn> fmt::Debug for Box<dyn KeyEventHandler<X>> {
@@ -112,7 +243,216 @@ where
 }
 
 /// User defined key bindings
-pub type KeyBindings<X> = HashMap<KeyCode, Box<dyn KeyEventHandler<X>>>;
+///
+/// [KeyBindings] can be used as a `HashMap<KeyCode, Box<dyn KeyEventHandler<X>>>` for single
+/// key bindings. When parsed from their string form using [parse_keybindings] or
+/// [parse_keybindings_with_xmodmap], the original string for each binding is also stored so
+/// that the bindings can be re-parsed when the keyboard mapping changes (for example after
+/// running `setxkbmap`).
+///
+/// # Chords
+/// Bindings made up of multiple space separated keys (such as `"M-w h"`) are run once each
+/// key in the sequence has been pressed in turn. The keyboard is grabbed after the first key
+/// of a chord has been pressed and pressing a key that does not continue any known chord will
+/// cancel it. If a single key binding is also bound as the start of a chord then the single
+/// key binding takes precedence.
+///
+/// # Modes
+/// Named modes can be added using [KeyBindings::add_mode] and entered using
+/// [enter_mode][crate::builtin::actions::enter_mode]. While a mode is active the keyboard is
+/// grabbed and only the bindings for that mode are run. Pressing `Escape` will exit the
+/// current mode unless it has been bound within the mode itself. The name of the active mode
+/// is available from [State::current_mode].
+#[derive(Debug)]
+pub struct KeyBindings<X>
+where
+    X: XConn,
+{
+    bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<X>>>,
+    chords: HashMap<Vec<KeyCode>, Box<dyn KeyEventHandler<X>>>,
+    modes: HashMap<String, KeyBindings<X>>,
+    names: HashMap<Vec<KeyCode>, String>,
+    modifier_codes: HashMap<KeyCodeValue, KeyCodeMask>,
+    escape_code: Option<KeyCodeValue>,
+}
+
+impl<X: XConn> Default for KeyBindings<X> {
+    fn default() -> Self {
+        Self {
+            bindings: HashMap::new(),
+            chords: HashMap::new(),
+            modes: HashMap::new(),
+            names: HashMap::new(),
+            modifier_codes: HashMap::new(),
+            escape_code: None,
+        }
+    }
+}
+
+impl<X: XConn> Deref for KeyBindings<X> {
+    type Target = HashMap<KeyCode, Box<dyn KeyEventHandler<X>>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.bindings
+    }
+}
+
+impl<X: XConn> DerefMut for KeyBindings<X> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.bindings
+    }
+}
+
+impl<X: XConn> From<HashMap<KeyCode, Box<dyn KeyEventHandler<X>>>> for KeyBindings<X> {
+    fn from(bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<X>>>) -> Self {
+        Self {
+            bindings,
+            ..Default::default()
+        }
+    }
+}
+
+impl<X: XConn> FromIterator<(KeyCode, Box<dyn KeyEventHandler<X>>)> for KeyBindings<X> {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = (KeyCode, Box<dyn KeyEventHandler<X>>)>,
+    {
+        HashMap::from_iter(iter).into()
+    }
+}
+
+impl<X: XConn> KeyBindings<X> {
+    /// The string form of the binding for the given [KeyCode] if it is known.
+    pub fn binding_name(&self, k: &KeyCode) -> Option<&str> {
+        self.names.get(&vec![*k]).map(String::as_str)
+    }
+
+    /// Add a named mode with its own set of key bindings, replacing any existing mode with the
+    /// same name.
+    pub fn add_mode(&mut self, name: impl Into<String>, bindings: KeyBindings<X>) {
+        self.modes.insert(name.into(), bindings);
+    }
+
+    /// The names of all modes that have been added to these bindings.
+    pub fn mode_names(&self) -> impl Iterator<Item = &str> {
+        self.modes.keys().map(String::as_str)
+    }
+
+    pub(crate) fn mode_mut(&mut self, name: &str) -> Option<&mut KeyBindings<X>> {
+        self.modes.get_mut(name)
+    }
+
+    pub(crate) fn handler_for(
+        &mut self,
+        seq: &[KeyCode],
+    ) -> Option<&mut Box<dyn KeyEventHandler<X>>> {
+        match seq {
+            [k] => self.bindings.get_mut(k),
+            _ => self.chords.get_mut(seq),
+        }
+    }
+
+    pub(crate) fn is_chord_prefix(&self, seq: &[KeyCode]) -> bool {
+        self.chords
+            .keys()
+            .any(|chord| chord.len() > seq.len() && chord.starts_with(seq))
+    }
+
+    pub(crate) fn is_modifier(&self, code: KeyCodeValue) -> bool {
+        self.modifier_codes.contains_key(&code)
+    }
+
+    // The modifier mask bit set by holding the given key (0 if it is not a modifier)
+    pub(crate) fn modifier_mask(&self, code: KeyCodeValue) -> KeyCodeMask {
+        self.modifier_codes.get(&code).copied().unwrap_or(0)
+    }
+
+    pub(crate) fn is_escape(&self, k: &KeyCode) -> bool {
+        self.escape_code == Some(k.code) && k.mask == 0
+    }
+
+    /// The [KeyCode]s that need to be grabbed in order to run these bindings: each single key
+    /// binding along with the first key of each chord.
+    pub(crate) fn grab_codes(&self) -> Vec<KeyCode> {
+        let codes: HashSet<KeyCode> = self
+            .bindings
+            .keys()
+            .chain(self.chords.keys().map(|chord| &chord[0]))
+            .copied()
+            .collect();
+
+        codes.into_iter().collect()
+    }
+
+    fn set_special_codes(&mut self, known_codes: &HashMap<String, u8>) {
+        self.modifier_codes = MODIFIER_KEYS
+            .iter()
+            .flat_map(|(name, mask)| known_codes.get(*name).map(|&code| (code, *mask)))
+            .collect();
+        self.escape_code = known_codes.get("Escape").copied();
+    }
+
+    /// Re-parse the string form of each binding against an updated keymap.
+    ///
+    /// Bindings without a known string form, or whose keys are no longer present in the
+    /// keymap, are left bound to their current [KeyCode]s. If two bindings end up bound to the
+    /// same keys after the mapping change then a warning is logged and only one is kept:
+    /// bindings without a string form take precedence, followed by the first binding when
+    /// ordered by their string form.
+    pub(crate) fn remap(&mut self, known_codes: &HashMap<String, u8>) {
+        let mut existing: Vec<_> = self
+            .bindings
+            .drain()
+            .map(|(k, handler)| (vec![k], handler))
+            .chain(self.chords.drain())
+            .map(|(seq, handler)| (self.names.remove(&seq), seq, handler))
+            .collect();
+        existing.sort_by(|(n1, _, _), (n2, _, _)| n1.cmp(n2));
+
+        let mut names = HashMap::with_capacity(existing.len());
+        let mut remapped: HashMap<Vec<KeyCode>, _> = HashMap::with_capacity(existing.len());
+
+        for (name, seq, handler) in existing {
+            let new_seq = match name.as_ref().map(|name| parse_sequence(name, known_codes)) {
+                Some(Ok(new_seq)) => new_seq,
+                Some(Err(e)) => {
+                    warn!(%e, ?name, "unable to re-parse key binding: keeping previous key code");
+                    seq
+                }
+                None => seq,
+            };
+
+            if remapped.contains_key(&new_seq) {
+                warn!(
+                    ?name,
+                    existing = ?names.get(&new_seq),
+                    "key binding collides with an existing binding after keyboard mapping change: dropping it"
+                );
+                continue;
+            }
+
+            if let Some(name) = name {
+                names.insert(new_seq.clone(), name);
+            }
+            remapped.insert(new_seq, handler);
+        }
+
+        for (seq, handler) in remapped {
+            if seq.len() == 1 {
+                self.bindings.insert(seq[0], handler);
+            } else {
+                self.chords.insert(seq, handler);
+            }
+        }
+
+        self.names = names;
+        self.set_special_codes(known_codes);
+
+        for mode in self.modes.values_mut() {
+            mode.remap(known_codes);
+        }
+    }
+}
 
 /// An action to be run in response to a mouse event
 pub trait MouseEventHandler<X>
@@ -139,7 +479,36 @@ where
     }
 }
 
+/// An in progress mouse drag that was started by a [MouseEventHandler] using
+/// [State::start_mouse_drag].
+///
+/// While a drag is in progress all mouse events are passed to the drag handler rather than
+/// being used to look up user mouse bindings.
+pub trait MouseDragHandler<X>
+where
+    X: XConn,
+{
+    /// Called for each pointer motion event while the drag is in progress
+    fn on_motion(&mut self, evt: &MouseEvent, state: &mut State<X>, x: &X) -> Result<()>;
+
+    /// Called when a mouse button is released to complete the drag
+    fn on_release(&mut self, evt: &MouseEvent, state: &mut State<X>, x: &X) -> Result<()>;
+}
+
+impl<X: XConn> fmt::Debug for Box<dyn MouseDragHandler<X>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MouseDragHandler").finish()
+    }
+}
+
 /// User defined mouse bindings
+///
+/// Bindings that include at least one modifier are grabbed on the root window so they will
+/// be run regardless of which window was clicked. Bindings without any modifiers (such as
+/// scrolling over the desktop or a status bar to change workspace) are only run for clicks on
+/// the root window itself and windows owned by the window manager rather than client windows.
+/// The [MouseEvent] passed to the handler contains the [Xid] of the window that was clicked
+/// along with the position of the click.
 pub type MouseBindings<X> = HashMap<(MouseEventKind, MouseState), Box<dyn MouseEventHandler<X>>>;
 
 /// Abstraction layer for working with key presses
@@ -393,3 +762,152 @@ impl MouseEvent {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::x::mock::MockXConn;
+    use simple_test_case::test_case;
+
+    const META: KeyCodeMask = 1 << 6;
+
+    fn codes(names: &[(&str, u8)]) -> HashMap<String, u8> {
+        names.iter().map(|&(s, c)| (s.to_owned(), c)).collect()
+    }
+
+    fn known_codes() -> HashMap<String, u8> {
+        codes(&[("a", 38), ("h", 43), ("j", 44), ("w", 25), ("Escape", 9)])
+    }
+
+    fn k(mask: KeyCodeMask, code: KeyCodeValue) -> KeyCode {
+        KeyCode { mask, code }
+    }
+
+    fn bindings(names: &[&str]) -> KeyBindings<MockXConn> {
+        let str_bindings: HashMap<&str, Box<dyn KeyEventHandler<MockXConn>>> = names
+            .iter()
+            .map(|&name| {
+                let handler: Box<dyn KeyEventHandler<MockXConn>> =
+                    Box::new(|_: &mut State<MockXConn>, _: &MockXConn| Ok(()));
+                (name, handler)
+            })
+            .collect();
+
+        parse_keybindings_with_keycodes(str_bindings, &known_codes()).unwrap()
+    }
+
+    fn set(codes: impl IntoIterator<Item = KeyCode>) -> HashSet<KeyCode> {
+        codes.into_iter().collect()
+    }
+
+    #[test_case("a", vec![k(0, 38)]; "single key")]
+    #[test_case("M-a", vec![k(META, 38)]; "single key with modifier")]
+    #[test_case("C-S-a", vec![k(0b101, 38)]; "single key with multiple modifiers")]
+    #[test_case("M-w h", vec![k(META, 25), k(0, 43)]; "chord")]
+    #[test_case("  M-w   h  ", vec![k(META, 25), k(0, 43)]; "chord with extra whitespace")]
+    #[test]
+    fn parse_sequence_valid(pattern: &str, expected: Vec<KeyCode>) {
+        let seq = parse_sequence(pattern, &known_codes()).unwrap();
+
+        assert_eq!(seq, expected);
+    }
+
+    #[test_case(""; "empty")]
+    #[test_case("   "; "only whitespace")]
+    #[test_case("M-nope"; "unknown key")]
+    #[test_case("M-w nope"; "unknown key in chord")]
+    #[test_case("X-a"; "unknown modifier")]
+    #[test_case("M-"; "missing key")]
+    #[test]
+    fn parse_sequence_invalid(pattern: &str) {
+        assert!(parse_sequence(pattern, &known_codes()).is_err());
+    }
+
+    #[test_case(vec![k(META, 25)], true; "first key of chords")]
+    #[test_case(vec![k(META, 25), k(0, 44)], true; "first two keys of a longer chord")]
+    #[test_case(vec![k(META, 25), k(0, 43)], false; "complete chord")]
+    #[test_case(vec![k(0, 43)], false; "second key of a chord")]
+    #[test_case(vec![k(META, 38)], false; "single key binding")]
+    #[test]
+    fn is_chord_prefix(seq: Vec<KeyCode>, expected: bool) {
+        let b = bindings(&["M-a", "M-w h", "M-w j a"]);
+
+        assert_eq!(b.is_chord_prefix(&seq), expected);
+    }
+
+    #[test_case(&["M-a", "M-h"], vec![k(META, 38), k(META, 43)]; "single keys")]
+    #[test_case(&["M-w h", "M-w j"], vec![k(META, 25)]; "shared chord prefix")]
+    #[test_case(&["M-w", "M-w h"], vec![k(META, 25)]; "single key conflicting with chord prefix")]
+    #[test_case(&["M-a", "M-w h", "M-w j a"], vec![k(META, 25), k(META, 38)]; "mixed")]
+    #[test]
+    fn grab_codes(names: &[&str], expected: Vec<KeyCode>) {
+        let b = bindings(names);
+
+        assert_eq!(set(b.grab_codes()), set(expected));
+    }
+
+    #[test]
+    fn remap_rebinds_keys_from_their_names() {
+        let mut b = bindings(&["M-a", "M-w h"]);
+        b.add_mode("resize", bindings(&["h"]));
+        let new_codes = codes(&[("a", 50), ("h", 51), ("w", 52), ("Escape", 9)]);
+
+        b.remap(&new_codes);
+
+        assert_eq!(set(b.keys().copied()), set([k(META, 50)]));
+        assert!(b.chords.contains_key(&vec![k(META, 52), k(0, 51)]));
+        assert_eq!(b.binding_name(&k(META, 50)), Some("M-a"));
+
+        let mode = b.mode_mut("resize").unwrap();
+        assert_eq!(mode.keys().copied().collect::<Vec<_>>(), vec![k(0, 51)]);
+        assert_eq!(mode.binding_name(&k(0, 51)), Some("h"));
+    }
+
+    #[test]
+    fn remap_keeps_bindings_whose_keys_are_no_longer_known() {
+        let mut b = bindings(&["M-a", "M-j"]);
+        let new_codes = codes(&[("a", 50)]);
+
+        b.remap(&new_codes);
+
+        assert_eq!(set(b.keys().copied()), set([k(META, 44), k(META, 50)]));
+        assert_eq!(b.binding_name(&k(META, 44)), Some("M-j"));
+    }
+
+    #[test]
+    fn remap_leaves_unnamed_bindings_unchanged() {
+        let mut b = bindings(&["M-a"]);
+        let handler: Box<dyn KeyEventHandler<MockXConn>> =
+            Box::new(|_: &mut State<MockXConn>, _: &MockXConn| Ok(()));
+        b.insert(k(0, 99), handler);
+
+        b.remap(&codes(&[("a", 50)]));
+
+        assert_eq!(set(b.keys().copied()), set([k(0, 99), k(META, 50)]));
+        assert_eq!(b.binding_name(&k(0, 99)), None);
+    }
+
+    #[test]
+    fn remap_drops_bindings_that_collide_after_a_mapping_change() {
+        let mut b = bindings(&["M-h", "M-a"]);
+
+        b.remap(&codes(&[("a", 50), ("h", 50)]));
+
+        assert_eq!(set(b.keys().copied()), set([k(META, 50)]));
+        assert_eq!(b.binding_name(&k(META, 50)), Some("M-a"));
+        assert_eq!(b.names.len(), 1);
+    }
+
+    #[test]
+    fn remap_prefers_unnamed_bindings_when_they_collide() {
+        let mut b = bindings(&["M-a"]);
+        let handler: Box<dyn KeyEventHandler<MockXConn>> =
+            Box::new(|_: &mut State<MockXConn>, _: &MockXConn| Ok(()));
+        b.insert(k(META, 50), handler);
+
+        b.remap(&codes(&[("a", 50)]));
+
+        assert_eq!(set(b.keys().copied()), set([k(META, 50)]));
+        assert_eq!(b.binding_name(&k(META, 50)), None);
+    }
+}
@@ -1,19 +1,24 @@
 //! XEvent handlers for use in the main event loop;
 use crate::{
     core::{
-        bindings::{KeyBindings, KeyCode, MouseBindings, MouseEvent},
+        bindings::{
+            KeyBindings, KeyCode, KeyTrigger, MouseBindings, MouseButton, MouseEvent,
+            MouseEventKind,
+        },
         State, Xid,
     },
-    pure::geometry::Point,
+    pure::geometry::{Point, Rect},
     x::{
+        apply_struts,
         atom::Atom,
-        event::{ClientMessage, ClientMessageKind, PointerChange},
+        event::{ClientMessage, ClientMessageKind, ConfigureEvent, PointerChange, PropertyEvent},
         property::{Prop, WmHints},
-        XConn, XConnExt,
+        remove_struts, track_unmanaged, untrack_unmanaged, update_struts, XConn, XConnExt,
     },
     Result,
 };
-use tracing::{error, info, trace};
+use std::str::FromStr;
+use tracing::{error, info, trace, warn};
 
 // Currently no client messages are handled by default (see the ewmh extension for some examples of messages
 // that are handled when that is enabled)
@@ -24,16 +29,227 @@ pub(crate) fn client_message<X: XConn>(msg: ClientMessage, _: &mut State<X>, _:
     Ok(())
 }
 
+// Unmanaged and floating clients are free to position themselves as they wish (subject to their size
+// hints which are applied when positioning the client) but tiled clients are not: for those we let
+// the client know where it actually is on the screen via a synthetic ConfigureNotify.
+pub(crate) fn configure_request<X: XConn>(
+    e: ConfigureEvent,
+    state: &mut State<X>,
+    x: &X,
+) -> Result<()> {
+    let ConfigureEvent { id, r, .. } = e;
+    let bw = state.config.border_width;
+
+    if !state.client_set.contains(&id) {
+        trace!(?id, ?r, "honouring configure request for unmanaged client");
+        return x.position_client(id, r);
+    }
+
+    if state.client_set.floating.contains_key(&id) {
+        trace!(?id, ?r, "honouring configure request for floating client");
+        // Floating positions include the client border so we need to expand the requested
+        // region to account for it being removed again when the client is positioned.
+        let r = Rect::new(r.x, r.y, r.w + 2 * bw, r.h + 2 * bw);
+
+        return x.modify_and_refresh(state, |cs| cs.float_unchecked(id, r));
+    }
+
+    trace!(?id, ?r, "denying configure request for tiled client");
+    let actual = x.client_geometry(id)?;
+
+    x.send_configure_notify(id, actual, bw)
+}
+
+// This is run before the user event hook so that hooks see the updated property values.
+pub(crate) fn update_client_properties<X: XConn>(
+    e: &PropertyEvent,
+    state: &mut State<X>,
+    x: &X,
+) -> Result<()> {
+    let atom = match Atom::from_str(&e.atom) {
+        Ok(atom) => atom,
+        Err(_) => return Ok(()),
+    };
+
+    if let Some(props) = state.client_props.get_mut(&e.id) {
+        if props.update(e.id, atom, x)? {
+            trace!(id = e.id.0, ?atom, ?props, "client properties updated");
+        }
+    }
+
+    Ok(())
+}
+
+// Changes in size hints or transient status require the client to be re-positioned. Everything
+// else we track is simply cached by update_client_properties.
+pub(crate) fn property_notify<X: XConn>(
+    e: PropertyEvent,
+    state: &mut State<X>,
+    x: &X,
+) -> Result<()> {
+    let PropertyEvent { id, atom, is_root } = e;
+
+    if matches!(
+        Atom::from_str(&atom),
+        Ok(Atom::NetWmStrut | Atom::NetWmStrutPartial)
+    ) && update_struts(id, state, x)
+    {
+        trace!(?id, "struts changed: refreshing");
+        return x.refresh(state);
+    }
+
+    if is_root || !state.client_set.contains(&id) {
+        return Ok(());
+    }
+
+    match Atom::from_str(&atom) {
+        Ok(Atom::WmNormalHints) if state.mapped.contains(&id) => {
+            trace!(?id, "size hints changed: refreshing");
+            x.refresh(state)
+        }
+
+        // Layouts are able to position clients based on their class
+        Ok(Atom::WmClass) if state.mapped.contains(&id) => {
+            trace!(?id, "class changed: refreshing");
+            x.refresh(state)
+        }
+
+        Ok(Atom::WmTransientFor) => {
+            let is_transient = state
+                .client_props
+                .get(&id)
+                .map(|p| p.transient_for.is_some())
+                .unwrap_or(false);
+
+            if is_transient && !state.client_set.floating.contains_key(&id) {
+                trace!(?id, "client is now transient: setting to floating state");
+                let r = x.client_geometry(id)?;
+                x.modify_and_refresh(state, |cs| cs.float_unchecked(id, r))
+            } else {
+                Ok(())
+            }
+        }
+
+        _ => Ok(()),
+    }
+}
+
 pub(crate) fn keypress<X: XConn>(
     key: KeyCode,
     bindings: &mut KeyBindings<X>,
     state: &mut State<X>,
     x: &X,
 ) -> Result<()> {
-    if let Some(action) = bindings.get_mut(&key) {
-        trace!(?key, "running user keybinding");
+    let is_repeat = !state.held_keys.insert(key.code);
+
+    // While the keyboard is grabbed we see presses of the modifier keys themselves: these
+    // need to be ignored so that they can be held for the next key in the sequence.
+    if state.keyboard_grabbed && bindings.is_modifier(key.code) {
+        return Ok(());
+    }
+
+    // Holding down a key part way through a chord shouldn't cancel it
+    if is_repeat && !state.pending_chord.is_empty() {
+        return Ok(());
+    }
+
+    let is_escape = bindings.is_escape(&key);
+    let mode = state.current_mode.clone();
+
+    let bindings = match mode.as_deref() {
+        None => bindings,
+        Some(name) => match bindings.mode_mut(name) {
+            Some(mode_bindings) => mode_bindings,
+            None => {
+                warn!(%name, "unknown key binding mode: exiting mode");
+                return state.exit_mode(x);
+            }
+        },
+    };
+
+    let mut seq = std::mem::take(&mut state.pending_chord);
+    seq.push(key);
+
+    if let Some(action) = bindings.handler_for(&seq) {
+        state.sync_keyboard_grab(x)?;
+
+        match action.trigger() {
+            KeyTrigger::PressOnce if is_repeat => (),
+
+            // We only see the release if the key was grabbed: either as a top level binding
+            // or because the keyboard is still grabbed after completing a chord / in a mode.
+            KeyTrigger::Release if seq.len() == 1 || state.keyboard_grabbed => {
+                trace!(?seq, "waiting for key release to run user keybinding");
+                state.pending_release.insert(key.code, (mode, seq));
+            }
+
+            _ => {
+                trace!(?seq, "running user keybinding");
+                if let Err(error) = action.call(state, x) {
+                    error!(%error, ?seq, "error running user keybinding");
+                    return Err(error);
+                }
+            }
+        }
+    } else if bindings.is_chord_prefix(&seq) {
+        trace!(?seq, "waiting for next key in chord");
+        state.pending_chord = seq;
+        state.sync_keyboard_grab(x)?;
+    } else if seq.len() == 1 && state.current_mode.is_some() && is_escape {
+        trace!("escape pressed: exiting key binding mode");
+        state.exit_mode(x)?;
+    } else {
+        if seq.len() > 1 {
+            trace!(?seq, "unbound key sequence: cancelling chord");
+        }
+        state.sync_keyboard_grab(x)?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn keyrelease<X: XConn>(
+    key: KeyCode,
+    bindings: &mut KeyBindings<X>,
+    state: &mut State<X>,
+    x: &X,
+) -> Result<()> {
+    state.held_keys.remove(&key.code);
+
+    // The mask for a release event is the modifier state _before_ the key was released so
+    // we need to drop the bit for the key itself if it was a modifier.
+    if let Some((mask, _)) = &state.modifier_release_hook {
+        let remaining = key.mask & !bindings.modifier_mask(key.code) & mask;
+
+        if remaining == 0 {
+            if let Some((_, mut hook)) = state.modifier_release_hook.take() {
+                trace!("modifiers released: running hook");
+                state.sync_keyboard_grab(x)?;
+                if let Err(error) = hook.call(state, x) {
+                    error!(%error, "error running modifier release hook");
+                    return Err(error);
+                }
+            }
+        }
+    }
+
+    let (mode, seq) = match state.pending_release.remove(&key.code) {
+        Some(pending) => pending,
+        None => return Ok(()),
+    };
+
+    let bindings = match mode.as_deref() {
+        None => bindings,
+        Some(name) => match bindings.mode_mut(name) {
+            Some(mode_bindings) => mode_bindings,
+            None => return Ok(()),
+        },
+    };
+
+    if let Some(action) = bindings.handler_for(&seq) {
+        trace!(?seq, "running user keybinding on release");
         if let Err(error) = action.call(state, x) {
-            error!(%error, ?key, "error running user keybinding");
+            error!(%error, ?seq, "error running user keybinding");
             return Err(error);
         }
     }
@@ -41,12 +257,57 @@ pub(crate) fn keypress<X: XConn>(
     Ok(())
 }
 
+// The keyboard mapping has changed (e.g. via setxkbmap) so the key codes for our bindings may
+// no longer be correct: re-parse them against the new mapping and then re-grab everything.
+pub(crate) fn mapping_notify<X: XConn>(
+    key_bindings: &mut KeyBindings<X>,
+    mouse_bindings: &MouseBindings<X>,
+    x: &X,
+) -> Result<()> {
+    info!("keyboard mapping changed: re-parsing key bindings");
+    #[cfg(feature = "keysyms")]
+    let m = crate::core::bindings::keycodes_from_xconn(x)?;
+    #[cfg(not(feature = "keysyms"))]
+    let m = crate::core::bindings::keycodes_from_xmodmap()?;
+    key_bindings.remap(&m);
+
+    grab(key_bindings, mouse_bindings, x)
+}
+
+pub(crate) fn grab<X: XConn>(
+    key_bindings: &KeyBindings<X>,
+    mouse_bindings: &MouseBindings<X>,
+    x: &X,
+) -> Result<()> {
+    trace!("grabbing key and mouse bindings");
+    let key_codes = key_bindings.grab_codes();
+    // Grabbing bindings without modifiers would steal every click from client windows so
+    // these only run for clicks on the root window and window manager owned windows.
+    let mouse_states: Vec<_> = mouse_bindings
+        .keys()
+        .filter(|(_, state)| !state.modifiers.is_empty())
+        .map(|(_, state)| state.clone())
+        .collect();
+
+    x.grab(&key_codes, &mouse_states)
+}
+
 pub(crate) fn mouse_event<X: XConn>(
     e: MouseEvent,
     bindings: &mut MouseBindings<X>,
     state: &mut State<X>,
     x: &X,
 ) -> Result<()> {
+    if state.mouse_drag.is_some() {
+        return mouse_drag(e, state, x);
+    }
+
+    // Clicks inside of client windows that the client itself doesn't select are propagated
+    // up to the root window: these should only trigger bindings that use modifiers.
+    if e.state.modifiers.is_empty() && state.client_set.contains(&e.id) {
+        return Ok(());
+    }
+
     if let Some(action) = bindings.get_mut(&(e.kind, e.state.clone())) {
         if let Err(error) = action.call(&e, state, x) {
             error!(%error, ?e, "error running user mouse binding");
@@ -57,13 +318,75 @@ pub(crate) fn mouse_event<X: XConn>(
     Ok(())
 }
 
+// While a drag is in progress the handler needs to be removed from state in order to call
+// it so we put it back afterwards unless it was replaced by the handler itself.
+fn mouse_drag<X: XConn>(e: MouseEvent, state: &mut State<X>, x: &X) -> Result<()> {
+    let mut handler = match state.mouse_drag.take() {
+        Some(handler) => handler,
+        None => return Ok(()),
+    };
+
+    let res = match e.kind {
+        MouseEventKind::Press => {
+            state.mouse_drag = Some(handler);
+            return Ok(());
+        }
+
+        MouseEventKind::Motion => {
+            let res = handler.on_motion(&e, state, x);
+            if state.mouse_drag.is_none() {
+                state.mouse_drag = Some(handler);
+            }
+            res
+        }
+
+        // Scrolling or pressing other buttons while dragging should not end the drag
+        MouseEventKind::Release if !ends_drag(&e, state.mouse_drag_button) => {
+            state.mouse_drag = Some(handler);
+            return Ok(());
+        }
+
+        MouseEventKind::Release => {
+            trace!(?e, "mouse drag complete");
+            state.mouse_drag_button = None;
+            x.ungrab_pointer()?;
+            handler.on_release(&e, state, x)
+        }
+    };
+
+    if let Err(error) = &res {
+        error!(%error, ?e, "error running mouse drag handler");
+    }
+
+    res
+}
+
+fn ends_drag(e: &MouseEvent, drag_button: Option<MouseButton>) -> bool {
+    match drag_button {
+        Some(button) => e.state.button == button,
+        None => !matches!(
+            e.state.button,
+            MouseButton::ScrollUp | MouseButton::ScrollDown
+        ),
+    }
+}
+
 pub(crate) fn map_request<X: XConn>(client: Xid, state: &mut State<X>, x: &X) -> Result<()> {
     trace!(?client, "handling new map request");
     let attrs = x.get_window_attributes(client)?;
 
-    if !state.client_set.contains(&client) && !attrs.override_redirect {
+    if state.client_set.contains(&client) || attrs.override_redirect {
+        return Ok(());
+    }
+
+    let unmanaged = track_unmanaged(client, state, x)?;
+    let struts_changed = update_struts(client, state, x);
+
+    if !unmanaged {
         trace!(?client, "managing client");
         x.manage(client, state)?;
+    } else if struts_changed {
+        x.refresh(state)?;
     }
 
     Ok(())
@@ -71,10 +394,16 @@ pub(crate) fn map_request<X: XConn>(client: Xid, state: &mut State<X>, x: &X) ->
 
 pub(crate) fn destroy<X: XConn>(client: Xid, state: &mut State<X>, x: &X) -> Result<()> {
     trace!(?client, "destroying client");
+    let struts_changed = remove_struts(client, state);
+    untrack_unmanaged(client, state);
     x.unmanage(client, state)?;
     state.mapped.remove(&client);
     state.pending_unmap.remove(&client);
 
+    if struts_changed {
+        x.refresh(state)?;
+    }
+
     Ok(())
 }
 
@@ -83,7 +412,12 @@ pub(crate) fn unmap_notify<X: XConn>(client: Xid, state: &mut State<X>, x: &X) -
     let expected = *state.pending_unmap.get(&client).unwrap_or(&0);
 
     if expected == 0 {
+        let struts_changed = remove_struts(client, state);
+        untrack_unmanaged(client, state);
         x.unmanage(client, state)?;
+        if struts_changed {
+            x.refresh(state)?;
+        }
     } else if expected == 1 {
         state.pending_unmap.remove(&client);
     } else {
@@ -142,7 +476,10 @@ pub(crate) fn detect_screens<X: XConn>(state: &mut State<X>, x: &X) -> Result<()
     let rects = x.screen_details()?;
     info!(?rects, "found screens");
 
-    state.client_set.update_screens(rects)
+    state.client_set.update_screens(rects)?;
+    apply_struts(state);
+
+    Ok(())
 }
 
 pub(crate) fn screen_change<X: XConn>(state: &mut State<X>, x: &X) -> Result<()> {
@@ -162,3 +499,179 @@ fn set_screen_from_point<X: XConn>(p: Point, state: &mut State<X>, x: &X) -> Res
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::bindings::{MouseDragHandler, MouseState},
+        x::{
+            mock::{Call, MockXConn},
+            ClientConfig, XEvent,
+        },
+    };
+    use simple_test_case::test_case;
+    use std::{cell::RefCell, rc::Rc};
+
+    const SCREENS: [Rect; 2] = [
+        Rect {
+            x: 0,
+            y: 0,
+            w: 1000,
+            h: 800,
+        },
+        Rect {
+            x: 1000,
+            y: 0,
+            w: 1000,
+            h: 800,
+        },
+    ];
+
+    fn configure(id: Xid, r: Rect) -> ConfigureEvent {
+        ConfigureEvent {
+            id,
+            r,
+            is_root: false,
+        }
+    }
+
+    #[test]
+    fn configure_request_is_honoured_for_unmanaged_clients() {
+        let mut wm = MockXConn::new(SCREENS.to_vec()).into_wm();
+        let r = Rect::new(10, 20, 30, 40);
+
+        configure_request(configure(Xid(42), r), &mut wm.state, &wm.x).unwrap();
+
+        assert_eq!(
+            wm.x.take_calls(),
+            vec![Call::SetClientConfig(
+                Xid(42),
+                vec![ClientConfig::Position(r)]
+            )]
+        );
+    }
+
+    #[test]
+    fn configure_request_is_denied_for_tiled_clients() {
+        let mut conn = MockXConn::new(SCREENS.to_vec());
+        let current = Rect::new(0, 0, 500, 800);
+        conn.geometry.insert(Xid(42), current);
+        let mut wm = conn.into_wm();
+        wm.state.client_set.insert(Xid(42));
+        let bw = wm.state.config.border_width;
+
+        let r = Rect::new(10, 20, 30, 40);
+        configure_request(configure(Xid(42), r), &mut wm.state, &wm.x).unwrap();
+
+        assert_eq!(
+            wm.x.take_calls(),
+            vec![Call::SendConfigureNotify(Xid(42), current, bw)]
+        );
+    }
+
+    #[test]
+    fn configure_request_floats_relative_to_the_client_screen() {
+        let mut wm = MockXConn::new(SCREENS.to_vec()).into_wm();
+        let bw = wm.state.config.border_width;
+        wm.state.client_set.insert(Xid(42));
+        wm.state
+            .client_set
+            .float_unchecked(Xid(42), Rect::new(0, 0, 10, 10));
+        wm.state.client_set.focus_screen(1);
+
+        let r = Rect::new(10, 20, 30, 40);
+        configure_request(configure(Xid(42), r), &mut wm.state, &wm.x).unwrap();
+
+        let expected = Rect::new(10, 20, 30 + 2 * bw, 40 + 2 * bw);
+        assert_eq!(wm.state.diff.after.positions, vec![(Xid(42), expected)]);
+    }
+
+    // Records the buttons of the events it sees
+    struct RecordingDrag(Rc<RefCell<Vec<(MouseEventKind, MouseButton)>>>);
+
+    impl MouseDragHandler<MockXConn> for RecordingDrag {
+        fn on_motion(
+            &mut self,
+            e: &MouseEvent,
+            _: &mut State<MockXConn>,
+            _: &MockXConn,
+        ) -> Result<()> {
+            self.0.borrow_mut().push((e.kind, e.state.button));
+            Ok(())
+        }
+
+        fn on_release(
+            &mut self,
+            e: &MouseEvent,
+            _: &mut State<MockXConn>,
+            _: &MockXConn,
+        ) -> Result<()> {
+            self.0.borrow_mut().push((e.kind, e.state.button));
+            Ok(())
+        }
+    }
+
+    fn mouse(button: MouseButton, kind: MouseEventKind) -> MouseEvent {
+        MouseEvent::new(Xid(0), 0, 0, 0, 0, MouseState::new(button, vec![]), kind)
+    }
+
+    #[test_case(Some(MouseButton::Left), MouseButton::Left, true; "drag button")]
+    #[test_case(Some(MouseButton::Left), MouseButton::Right, false; "other button")]
+    #[test_case(Some(MouseButton::Left), MouseButton::ScrollUp, false; "scroll up")]
+    #[test_case(Some(MouseButton::Right), MouseButton::Left, false; "other button for right drag")]
+    #[test_case(None, MouseButton::Right, true; "any button when unknown")]
+    #[test_case(None, MouseButton::ScrollDown, false; "scroll down when unknown")]
+    #[test]
+    fn mouse_drag_ends_on_release_of_the_drag_button(
+        drag_button: Option<MouseButton>,
+        released: MouseButton,
+        ends: bool,
+    ) {
+        let x = MockXConn::new(SCREENS.to_vec());
+        let mut state = MockXConn::new(SCREENS.to_vec()).into_wm().state;
+        let seen = Rc::new(RefCell::new(Vec::new()));
+
+        state.current_event =
+            drag_button.map(|b| XEvent::MouseEvent(mouse(b, MouseEventKind::Press)));
+        state
+            .start_mouse_drag(RecordingDrag(seen.clone()), &x)
+            .unwrap();
+        state.current_event = None;
+
+        mouse_drag(mouse(released, MouseEventKind::Release), &mut state, &x).unwrap();
+
+        assert_eq!(state.mouse_drag_in_progress(), !ends);
+        let expected = if ends {
+            vec![(MouseEventKind::Release, released)]
+        } else {
+            vec![]
+        };
+        assert_eq!(*seen.borrow(), expected);
+    }
+
+    #[test_case(true, true; "mapped client")]
+    #[test_case(false, false; "unmapped client")]
+    #[test]
+    fn wm_class_changes_refresh_mapped_clients(mapped: bool, refreshed: bool) {
+        let x = MockXConn::new(SCREENS.to_vec());
+        let mut state = MockXConn::new(SCREENS.to_vec()).into_wm().state;
+        state.client_set.insert(Xid(42));
+        if mapped {
+            state.mapped.insert(Xid(42));
+        }
+
+        let e = PropertyEvent {
+            id: Xid(42),
+            atom: Atom::WmClass.as_ref().to_owned(),
+            is_root: false,
+        };
+        property_notify(e, &mut state, &x).unwrap();
+
+        let positioned = x
+            .take_calls()
+            .into_iter()
+            .any(|c| matches!(c, Call::SetClientConfig(Xid(42), _)));
+        assert_eq!(positioned, refreshed);
+    }
+}
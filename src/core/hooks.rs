@@ -4,7 +4,7 @@ use crate::{
     x::{XConn, XEvent},
     Result, Xid,
 };
-use std::fmt;
+use std::{fmt, os::unix::io::RawFd};
 
 /// Handle an [XEvent], return `true` if default event handling should be run afterwards.
 ///
@@ -247,3 +247,37 @@ where
         (self)(state, x)
     }
 }
+
+/// An additional source of events that should wake the main event loop of the
+/// [WindowManager][crate::core::WindowManager] alongside the X server.
+///
+/// The file descriptor returned by `fd` is polled for readability along with the connection
+/// to the X server and `on_ready` is called each time it becomes readable. Implementations
+/// are responsible for consuming any pending data on the file descriptor so that the main
+/// loop is not immediately woken again.
+pub trait EventSource<X>
+where
+    X: XConn,
+{
+    /// The file descriptor to poll for readability
+    fn fd(&self) -> RawFd;
+
+    /// Handle the file descriptor becoming readable
+    fn on_ready(&mut self, state: &mut State<X>, x: &X) -> Result<()>;
+
+    /// Convert to a trait object
+    fn boxed(self) -> Box<dyn EventSource<X>>
+    where
+        Self: Sized + 'static,
+    {
+        Box::new(self)
+    }
+}
+
+impl<X: XConn> fmt::Debug for Box<dyn EventSource<X>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventSource")
+            .field("fd", &self.fd())
+            .finish()
+    }
+}
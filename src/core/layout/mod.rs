@@ -1,9 +1,11 @@
 //! Layouts for positioning client windows on the screen within a given workspace.
 use crate::{
     pure::{geometry::Rect, Stack},
-    stack, Xid,
+    stack,
+    x::property::{ClientProperties, WmNormalHints},
+    Error, Result, Xid,
 };
-use std::{fmt, mem::swap};
+use std::{collections::HashMap, fmt, mem::swap};
 
 mod messages;
 mod transformers;
@@ -13,13 +15,46 @@ pub use messages::{IntoMessage, Message};
 #[doc(inline)]
 pub use transformers::LayoutTransformer;
 
-// TODO: Do I also need versions of the layout methods that have access to the overall X state as well?
-//       That would allow for doing more involved layouts that were aware of things like the specific
-//       program running in each client window or the properties on each client.
-//       The layouts I'm writing initially are all fairly simple from that perspective so there is no
-//       need for it to start, and adding it later is possible using the same default imple deferral
-//       approach used in `layout_workspace` if it _is_ needed in future without it needing to be a
-//       breaking API change to the trait.
+/// Read-only access to the properties of client windows for [Layout]s that need to know more
+/// about the clients they are positioning than just their [Xid].
+///
+/// The only method that needs to be implemented is [ClientInfo::client_properties]: the remaining
+/// methods are convenience lookups that return [Error::UnknownClient] if there are no properties
+/// available for the requested client.
+pub trait ClientInfo {
+    /// The cached [ClientProperties] for the given client, if it is known.
+    fn client_properties(&self, id: Xid) -> Option<&ClientProperties>;
+
+    /// The title of the given client.
+    fn title(&self, id: Xid) -> Result<&str> {
+        lookup(self, id).map(|p| p.title.as_str())
+    }
+
+    /// The application name of the given client (the first string in its WM_CLASS).
+    fn app_name(&self, id: Xid) -> Result<&str> {
+        lookup(self, id).map(|p| p.app_name.as_str())
+    }
+
+    /// The class name of the given client (the second string in its WM_CLASS).
+    fn class_name(&self, id: Xid) -> Result<&str> {
+        lookup(self, id).map(|p| p.class_name.as_str())
+    }
+
+    /// The size hints set by the given client if there are any.
+    fn normal_hints(&self, id: Xid) -> Result<Option<&WmNormalHints>> {
+        lookup(self, id).map(|p| p.normal_hints.as_ref())
+    }
+}
+
+fn lookup<I: ClientInfo + ?Sized>(info: &I, id: Xid) -> Result<&ClientProperties> {
+    info.client_properties(id).ok_or(Error::UnknownClient)
+}
+
+impl ClientInfo for HashMap<Xid, ClientProperties> {
+    fn client_properties(&self, id: Xid) -> Option<&ClientProperties> {
+        self.get(&id)
+    }
+}
 
 /// A [Layout] is responsible for positioning a [Stack] of clients in a given coordinate space denoting
 /// the dimensions of users display.
@@ -68,6 +103,27 @@ pub trait Layout {
         }
     }
 
+    /// Generate screen positions for clients on a given [crate::pure::Workspace] with access to
+    /// the properties of each client via [ClientInfo].
+    ///
+    /// This allows for layouts that position clients based on the program running in each window
+    /// rather than solely on their position in the [Stack]. If this method returns an error (for
+    /// example because the properties of a client could not be found) then the window manager will
+    /// fall back to using [Layout::layout_workspace] instead.
+    ///
+    /// The default implementation of this method ignores the client information and calls
+    /// [Layout::layout_workspace]. See that method for details of how positions should be returned.
+    #[allow(clippy::type_complexity)]
+    fn layout_workspace_with_info(
+        &mut self,
+        tag: &str,
+        stack: &Option<Stack<Xid>>,
+        r: Rect,
+        _info: &dyn ClientInfo,
+    ) -> Result<(Option<Box<dyn Layout>>, Vec<(Xid, Rect)>)> {
+        Ok(self.layout_workspace(tag, stack, r))
+    }
+
     /// Generate screen positions for clients from a given [Stack].
     ///
     /// See [Layout::layout_workspace] for details of how positions should be returned.
@@ -150,6 +206,31 @@ impl LayoutStack {
         }
     }
 
+    /// Set focus to the first [Layout] in this stack with the given name.
+    ///
+    /// Returns `false` and leaves focus unchanged if there is no matching layout.
+    pub fn focus_layout_named(&mut self, name: &str) -> bool {
+        if self.focus.name() == name {
+            return true;
+        }
+
+        let original = self.up.len();
+        self.focus_head();
+        for _ in 0..self.len() {
+            if self.focus.name() == name {
+                return true;
+            }
+            self.focus_down();
+        }
+
+        // No match so restore the original focus
+        for _ in 0..original {
+            self.focus_down();
+        }
+
+        false
+    }
+
     /// Send the given [Message] to every [Layout] in this stack rather that just the
     /// currently active one.
     pub fn broadcast_message<M>(&mut self, m: M)
@@ -187,6 +268,22 @@ impl Layout for LayoutStack {
         )
     }
 
+    fn layout_workspace_with_info(
+        &mut self,
+        tag: &str,
+        stack: &Option<Stack<Xid>>,
+        r: Rect,
+        info: &dyn ClientInfo,
+    ) -> Result<(Option<Box<dyn Layout>>, Vec<(Xid, Rect)>)> {
+        let (new_focus, rs) = self.focus.layout_workspace_with_info(tag, stack, r, info)?;
+
+        if let Some(mut new) = new_focus {
+            self.swap_focus(&mut new);
+        }
+
+        Ok((None, rs))
+    }
+
     fn layout(&mut self, s: &Stack<Xid>, r: Rect) -> (Option<Box<dyn Layout>>, Vec<(Xid, Rect)>) {
         (None, self.run_and_replace(|l| l.layout(s, r)))
     }
@@ -1,8 +1,8 @@
 use crate::{
     builtin::layout::{messages::UnwrapTransformer, Monocle},
-    core::layout::{messages::Message, Layout},
+    core::layout::{messages::Message, ClientInfo, Layout},
     pure::{geometry::Rect, Stack},
-    Xid,
+    Result, Xid,
 };
 use std::mem::swap;
 
@@ -101,6 +101,21 @@ where
         self.run_transform(|r, inner| inner.layout_workspace(tag, stack, r), r)
     }
 
+    fn layout_workspace_with_info(
+        &mut self,
+        tag: &str,
+        stack: &Option<Stack<Xid>>,
+        r: Rect,
+        info: &dyn ClientInfo,
+    ) -> Result<(Option<Box<dyn Layout>>, Vec<(Xid, Rect)>)> {
+        let inner_r = self.transform_initial(r);
+        let res = self
+            .inner_mut()
+            .layout_workspace_with_info(tag, stack, inner_r, info)?;
+
+        Ok(self.run_transform(|_, _| res, r))
+    }
+
     fn layout(&mut self, s: &Stack<Xid>, r: Rect) -> (Option<Box<dyn Layout>>, Vec<(Xid, Rect)>) {
         self.run_transform(|r, inner| inner.layout(s, r), r)
     }
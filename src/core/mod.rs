@@ -1,15 +1,22 @@
 //! Core data structures and user facing functionality for the window manager
+#[cfg(feature = "serde")]
+use crate::x::unmanaged_window_type;
 use crate::{
-    pure::{Diff, StackSet, Workspace},
+    pure::{geometry::Strut, Diff, StackSet, Workspace},
     x::{
-        manage_without_refresh,
-        property::{MapState, WmState},
-        Atom, Prop, WindowAttributes, XConn, XConnExt, XEvent,
+        apply_struts, manage_without_refresh,
+        property::{ClientProperties, MapState, WmState},
+        track_unmanaged, update_struts, Atom, ClientConfig, Prop, WindowAttributes, XConn,
+        XConnExt, XEvent,
     },
     Color, Error, Result,
 };
 use anymap::{any::Any, AnyMap};
-use nix::sys::signal::{signal, SigHandler, Signal};
+use nix::{
+    errno::Errno,
+    poll::{poll, PollFd, PollFlags},
+    sys::signal::{signal, SigHandler, Signal},
+};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use std::{
@@ -19,7 +26,10 @@ use std::{
     fmt,
     ops::Deref,
     sync::Arc,
+    time::{Duration, Instant},
 };
+#[cfg(feature = "serde")]
+use std::{env, fs, path::Path};
 use tracing::{error, info, span, trace, Level};
 
 pub mod bindings;
@@ -27,8 +37,11 @@ pub(crate) mod handle;
 pub mod hooks;
 pub mod layout;
 
-use bindings::{KeyBindings, MouseBindings};
-use hooks::{EventHook, ManageHook, StateHook};
+use bindings::{
+    KeyBindings, KeyCode, KeyCodeMask, KeyCodeValue, MouseBindings, MouseButton, MouseDragHandler,
+    MouseEventKind,
+};
+use hooks::{EventHook, EventSource, ManageHook, StateHook};
 use layout::LayoutStack;
 
 /// An X11 ID for a given resource
@@ -68,6 +81,23 @@ pub type ClientSet = StackSet<Xid>;
 /// The pure client state information for a single [Workspace]
 pub type ClientSpace = Workspace<Xid>;
 
+/// The shortest interval that a timer added using [WindowManager::add_timer] can run at.
+pub const MIN_TIMER_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The environment variable used to pass the path of a persisted [ClientSet] to a newly
+/// started window manager process. See [State::persist_client_set] for details.
+#[cfg(feature = "serde")]
+pub const PERSISTED_STATE_ENV_VAR: &str = "PENROSE_PERSISTED_STATE";
+
+// The on disk format for a persisted ClientSet. Layouts are trait objects so we are only
+// able to store the name of the active layout for each workspace.
+#[cfg(feature = "serde")]
+#[derive(Debug, Serialize, Deserialize)]
+struct PersistedState {
+    client_set: ClientSet,
+    layouts: HashMap<String, String>,
+}
+
 /// Mutable internal state for the window manager
 #[derive(Debug)]
 pub struct State<X>
@@ -84,6 +114,19 @@ where
     pub(crate) pending_unmap: HashMap<Xid, usize>,
     pub(crate) current_event: Option<XEvent>,
     pub(crate) diff: Diff<Xid>,
+    pub(crate) client_props: HashMap<Xid, ClientProperties>,
+    pub(crate) struts: HashMap<Xid, Strut>,
+    pub(crate) docks: HashSet<Xid>,
+    pub(crate) desktops: HashSet<Xid>,
+    pub(crate) docks_hidden: bool,
+    pub(crate) current_mode: Option<String>,
+    pub(crate) pending_chord: Vec<KeyCode>,
+    pub(crate) keyboard_grabbed: bool,
+    pub(crate) held_keys: HashSet<KeyCodeValue>,
+    pub(crate) pending_release: HashMap<KeyCodeValue, (Option<String>, Vec<KeyCode>)>,
+    pub(crate) modifier_release_hook: Option<(KeyCodeMask, Box<dyn StateHook<X>>)>,
+    pub(crate) mouse_drag: Option<Box<dyn MouseDragHandler<X>>>,
+    pub(crate) mouse_drag_button: Option<MouseButton>,
     // pub(crate) mouse_focused: bool,
     // pub(crate) mouse_position: Option<(Point, Point)>,
 }
@@ -102,11 +145,177 @@ where
         &self.mapped
     }
 
+    /// The cached [ClientProperties] for a given client if it is currently managed.
+    ///
+    /// These are updated before the user event hook is run for the `PropertyNotify` event
+    /// that triggered the change so hooks will always see the current values.
+    pub fn client_properties(&self, id: &Xid) -> Option<&ClientProperties> {
+        self.client_props.get(id)
+    }
+
     /// The event currently being processed.
     pub fn current_event(&self) -> Option<&XEvent> {
         self.current_event.as_ref()
     }
 
+    /// The name of the key binding mode that is currently active, if any.
+    ///
+    /// See [KeyBindings::add_mode] for details of how to define modes.
+    pub fn current_mode(&self) -> Option<&str> {
+        self.current_mode.as_deref()
+    }
+
+    /// Enter the named key binding mode, grabbing the keyboard so that only the bindings for
+    /// that mode are run until it is exited.
+    ///
+    /// If the keyboard can not be grabbed then the mode is not entered.
+    pub fn enter_mode(&mut self, name: impl Into<String>, x: &X) -> Result<()> {
+        let name = name.into();
+        trace!(%name, "entering key binding mode");
+        self.current_mode = Some(name);
+        self.pending_chord.clear();
+        self.sync_keyboard_grab(x)?;
+
+        // Refresh so that hooks (such as a status bar) can see the new mode
+        x.refresh(self)
+    }
+
+    /// Exit the current key binding mode (if there is one), releasing the keyboard grab.
+    pub fn exit_mode(&mut self, x: &X) -> Result<()> {
+        self.pending_chord.clear();
+        let name = match self.current_mode.take() {
+            Some(name) => name,
+            None => return self.sync_keyboard_grab(x),
+        };
+
+        trace!(%name, "exiting key binding mode");
+        self.sync_keyboard_grab(x)?;
+
+        x.refresh(self)
+    }
+
+    /// Whether or not dock windows (such as external status bars) are currently visible.
+    pub fn docks_visible(&self) -> bool {
+        !self.docks_hidden
+    }
+
+    /// Show or hide any dock windows, releasing the screen space reserved for them while they
+    /// are hidden.
+    ///
+    /// Docks are windows with a _NET_WM_WINDOW_TYPE of _NET_WM_WINDOW_TYPE_DOCK. They are
+    /// mapped without being managed so they never appear in the [ClientSet].
+    pub fn set_docks_visible(&mut self, visible: bool, x: &X) -> Result<()> {
+        if self.docks_hidden != visible {
+            return Ok(());
+        }
+
+        trace!(%visible, "setting dock visibility");
+        self.docks_hidden = !visible;
+
+        for &id in self.docks.iter() {
+            if visible {
+                x.map(id)?;
+                x.set_client_config(id, &[ClientConfig::StackTop])?;
+            } else {
+                x.unmap(id)?;
+                *self.pending_unmap.entry(id).or_insert(0) += 1;
+            }
+        }
+
+        apply_struts(self);
+
+        x.refresh(self)
+    }
+
+    /// Run the given hook once the modifier keys that were held for the key binding currently
+    /// being run have been released.
+    ///
+    /// This allows for "hold a modifier and tap a key" style bindings (such as alt-tab window
+    /// switching) where repeated presses of a key binding update some pending state that is
+    /// then committed when the modifiers are released. The keyboard is grabbed until the hook
+    /// has been run. Calling this method again before the modifiers have been released will
+    /// replace the pending hook.
+    ///
+    /// If this is called outside of a key binding, or for a key binding that does not use any
+    /// modifiers, then the hook is run immediately.
+    pub fn on_modifiers_released<H>(&mut self, hook: H, x: &X) -> Result<()>
+    where
+        H: StateHook<X> + 'static,
+    {
+        let mask = match self.current_event {
+            Some(XEvent::KeyPress(k)) | Some(XEvent::KeyRelease(k)) => k.mask,
+            _ => 0,
+        };
+
+        if mask == 0 {
+            let mut hook = hook;
+            return hook.call(self, x);
+        }
+
+        self.modifier_release_hook = Some((mask, hook.boxed()));
+
+        self.sync_keyboard_grab(x)
+    }
+
+    /// Start a mouse drag, grabbing the pointer and passing all subsequent mouse events to the
+    /// given [MouseDragHandler] until the mouse button that started the drag is released.
+    ///
+    /// This is intended to be called from a [MouseEventHandler][bindings::MouseEventHandler]
+    /// bound to a button press. Any drag that is already in progress is replaced. If this is
+    /// called outside of a button press then the drag ends when any button other than the
+    /// scroll wheel is released.
+    pub fn start_mouse_drag<H>(&mut self, handler: H, x: &X) -> Result<()>
+    where
+        H: MouseDragHandler<X> + 'static,
+    {
+        x.grab_pointer()?;
+        self.mouse_drag = Some(Box::new(handler));
+        self.mouse_drag_button = match &self.current_event {
+            Some(XEvent::MouseEvent(e)) if e.kind == MouseEventKind::Press => Some(e.state.button),
+            _ => None,
+        };
+
+        Ok(())
+    }
+
+    /// Whether or not there is a mouse drag currently in progress.
+    pub fn mouse_drag_in_progress(&self) -> bool {
+        self.mouse_drag.is_some()
+    }
+
+    // The keyboard is actively grabbed while a mode is active, a chord is in progress or we
+    // are waiting for modifiers to be released so that we see key events for keys that are
+    // not otherwise bound.
+    pub(crate) fn sync_keyboard_grab(&mut self, x: &X) -> Result<()> {
+        let needs_grab = self.current_mode.is_some()
+            || !self.pending_chord.is_empty()
+            || self.modifier_release_hook.is_some();
+
+        match (needs_grab, self.keyboard_grabbed) {
+            (true, false) => {
+                if let Err(e) = x.grab_keyboard() {
+                    self.current_mode = None;
+                    self.pending_chord.clear();
+                    self.modifier_release_hook = None;
+                    return Err(e);
+                }
+                self.keyboard_grabbed = true;
+            }
+
+            (false, true) => {
+                x.ungrab_keyboard()?;
+                self.keyboard_grabbed = false;
+                // We won't see the release of any keys that were pressed while the keyboard
+                // was grabbed so we need to stop tracking them here.
+                self.held_keys.clear();
+            }
+
+            _ => (),
+        }
+
+        Ok(())
+    }
+
     /// Get access to a shared state extension.
     ///
     /// To add an extension to [State] before starting the Window Manager, see the
@@ -156,6 +365,33 @@ where
     pub fn add_extension<E: Any>(&mut self, extension: E) {
         self.extensions.insert(Arc::new(RefCell::new(extension)));
     }
+
+    /// Write the current [ClientSet] to the given file so that it can be restored by a new
+    /// window manager process.
+    ///
+    /// If [PERSISTED_STATE_ENV_VAR] is set to the path of this file when the new process
+    /// starts, the persisted state will be restored in place of the best effort management
+    /// of existing clients that is normally run on startup. Workspace membership, stack
+    /// order, focus and floating positions are restored exactly, along with the active
+    /// layout for each workspace (matched by name against the layouts in your [Config]).
+    #[cfg(feature = "serde")]
+    pub fn persist_client_set(&self, path: impl AsRef<Path>) -> Result<()> {
+        let layouts = self
+            .client_set
+            .workspaces()
+            .map(|w| (w.tag.clone(), w.layout_name()))
+            .collect();
+
+        let persisted = PersistedState {
+            client_set: self.client_set.clone(),
+            layouts,
+        };
+
+        let f = fs::File::create(path)?;
+        serde_json::to_writer(f, &persisted)?;
+
+        Ok(())
+    }
 }
 
 /// The user specified config options for how the window manager should run
@@ -302,6 +538,19 @@ where
     pub state: State<X>,
     key_bindings: KeyBindings<X>,
     mouse_bindings: MouseBindings<X>,
+    event_sources: Vec<Box<dyn EventSource<X>>>,
+    timers: Vec<Timer<X>>,
+}
+
+// A StateHook that is run periodically from the main event loop
+#[derive(Debug)]
+struct Timer<X>
+where
+    X: XConn,
+{
+    interval: Duration,
+    next: Instant,
+    hook: Box<dyn StateHook<X>>,
 }
 
 impl<X> WindowManager<X>
@@ -336,6 +585,19 @@ where
             pending_unmap: HashMap::new(),
             current_event: None,
             diff,
+            client_props: HashMap::new(),
+            struts: HashMap::new(),
+            docks: HashSet::new(),
+            desktops: HashSet::new(),
+            docks_hidden: false,
+            current_mode: None,
+            pending_chord: Vec::new(),
+            keyboard_grabbed: false,
+            held_keys: HashSet::new(),
+            pending_release: HashMap::new(),
+            modifier_release_hook: None,
+            mouse_drag: None,
+            mouse_drag_button: None,
         };
 
         Ok(Self {
@@ -343,6 +605,8 @@ where
             state,
             key_bindings,
             mouse_bindings,
+            event_sources: Vec::new(),
+            timers: Vec::new(),
         })
     }
 
@@ -351,6 +615,37 @@ where
         self.state.add_extension(extension);
     }
 
+    /// Register an additional [EventSource] to be polled alongside the X server connection in
+    /// the main event loop.
+    pub fn add_event_source<S>(&mut self, source: S)
+    where
+        S: EventSource<X> + 'static,
+    {
+        self.event_sources.push(source.boxed());
+    }
+
+    /// Register a [StateHook] to be run every `interval` from the main event loop.
+    ///
+    /// Timers are run in between processing events from the X server so the hook will be
+    /// called even if there is no X activity. If handling events takes longer than `interval`
+    /// then the hook will be run at most once when the loop is next idle rather than being
+    /// run repeatedly to catch up.
+    ///
+    /// Intervals shorter than [MIN_TIMER_INTERVAL] (including a zero interval) are clamped to
+    /// [MIN_TIMER_INTERVAL] so that a timer can not prevent the event loop from blocking.
+    pub fn add_timer<H>(&mut self, interval: Duration, hook: H)
+    where
+        H: StateHook<X> + 'static,
+    {
+        let interval = interval.max(MIN_TIMER_INTERVAL);
+
+        self.timers.push(Timer {
+            interval,
+            next: Instant::now() + interval,
+            hook: hook.boxed(),
+        });
+    }
+
     /// Start the WindowManager and run it until told to exit.
     ///
     /// Any provided startup hooks will be run after setting signal handlers and grabbing
@@ -374,36 +669,103 @@ where
         self.manage_existing_clients()?;
 
         loop {
-            match self.x.next_event() {
-                Ok(event) => {
-                    let span = span!(target: "penrose", Level::INFO, "XEvent", %event);
-                    let _enter = span.enter();
-                    trace!(details = ?event, "event details");
-                    self.state.current_event = Some(event.clone());
-
-                    if let Err(e) = self.handle_xevent(event) {
-                        error!(%e, "Error handling XEvent");
+            loop {
+                match self.x.poll_for_event() {
+                    Ok(Some(event)) => self.process_xevent(event),
+                    Ok(None) => break,
+                    Err(e) => {
+                        error!(%e, "Error pulling next x event");
+                        break;
                     }
-                    self.x.flush();
-
-                    self.state.current_event = None;
                 }
+            }
 
-                Err(e) => error!(%e, "Error pulling next x event"),
+            if let Err(e) = self.wait_for_events() {
+                error!(%e, "Error waiting for events");
             }
         }
     }
 
-    fn grab(&self) -> Result<()> {
-        trace!("grabbing key and mouse bindings");
-        let key_codes: Vec<_> = self.key_bindings.keys().copied().collect();
-        let mouse_states: Vec<_> = self
-            .mouse_bindings
-            .keys()
-            .map(|(_, state)| state.clone())
+    fn process_xevent(&mut self, event: XEvent) {
+        let span = span!(target: "penrose", Level::INFO, "XEvent", %event);
+        let _enter = span.enter();
+        trace!(details = ?event, "event details");
+        self.state.current_event = Some(event.clone());
+
+        if let Err(e) = self.handle_xevent(event) {
+            error!(%e, "Error handling XEvent");
+        }
+        self.x.flush();
+
+        self.state.current_event = None;
+    }
+
+    // Block until either the X server connection or one of our additional event sources is
+    // readable or the next timer is due, running the handlers for any event sources and timers
+    // that are ready. Pending X events are left to be pulled by the main loop.
+    fn wait_for_events(&mut self) -> Result<()> {
+        let mut fds: Vec<PollFd> = Some(self.x.connection_fd())
+            .into_iter()
+            .chain(self.event_sources.iter().map(|s| s.fd()))
+            .map(|fd| PollFd::new(fd, PollFlags::POLLIN))
             .collect();
 
-        self.x.grab(&key_codes, &mouse_states)
+        // Round up to the nearest millisecond so that we don't wake just before a timer is due
+        let timeout = match self.timers.iter().map(|t| t.next).min() {
+            Some(next) => {
+                let remaining = next.saturating_duration_since(Instant::now());
+                remaining.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32
+            }
+            None => -1,
+        };
+
+        match poll(&mut fds, timeout) {
+            Ok(_) | Err(Errno::EINTR) => (),
+            Err(e) => return Err(std::io::Error::from(e).into()),
+        }
+
+        let WindowManager {
+            x,
+            state,
+            event_sources,
+            timers,
+            ..
+        } = self;
+
+        for (source, fd) in event_sources.iter_mut().zip(&fds[1..]) {
+            let ready = fd
+                .revents()
+                .map(|r| r.intersects(PollFlags::POLLIN | PollFlags::POLLHUP))
+                .unwrap_or(false);
+
+            if ready {
+                trace!(fd = source.fd(), "running event source handler");
+                if let Err(e) = source.on_ready(state, x) {
+                    error!(%e, "error returned from event source");
+                }
+                x.flush();
+            }
+        }
+
+        let now = Instant::now();
+        for timer in timers.iter_mut().filter(|t| t.next <= now) {
+            trace!(interval = ?timer.interval, "running timer hook");
+            if let Err(e) = timer.hook.call(state, x) {
+                error!(%e, "error returned from timer hook");
+            }
+            x.flush();
+
+            timer.next += timer.interval;
+            if timer.next <= now {
+                timer.next = now + timer.interval;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn grab(&self) -> Result<()> {
+        handle::grab(&self.key_bindings, &self.mouse_bindings, &self.x)
     }
 
     fn handle_xevent(&mut self, event: XEvent) -> Result<()> {
@@ -414,8 +776,13 @@ where
             state,
             key_bindings,
             mouse_bindings,
+            ..
         } = self;
 
+        if let PropertyNotify(e) = &event {
+            handle::update_client_properties(e, state, x)?;
+        }
+
         let mut hook = state.config.event_hook.take();
         let should_run = match hook {
             Some(ref mut h) => {
@@ -441,18 +808,19 @@ where
         match &event {
             ClientMessage(m) => handle::client_message(m.clone(), state, x)?,
             ConfigureNotify(e) if e.is_root => handle::detect_screens(state, x)?,
-            ConfigureNotify(_) => (),  // Not currently handled
-            ConfigureRequest(_) => (), // Not currently handled
+            ConfigureNotify(_) => (), // Not currently handled
+            ConfigureRequest(e) => handle::configure_request(e.clone(), state, x)?,
             Enter(p) => handle::enter(*p, state, x)?,
             Expose(_) => (), // Not currently handled
             FocusIn(id) => handle::focus_in(*id, state, x)?,
             Destroy(xid) => handle::destroy(*xid, state, x)?,
             KeyPress(code) => handle::keypress(*code, key_bindings, state, x)?,
+            KeyRelease(code) => handle::keyrelease(*code, key_bindings, state, x)?,
             Leave(p) => handle::leave(*p, state, x)?,
-            MappingNotify => (), // Not currently handled
+            MappingNotify => handle::mapping_notify(key_bindings, mouse_bindings, x)?,
             MapRequest(xid) => handle::map_request(*xid, state, x)?,
             MouseEvent(e) => handle::mouse_event(e.clone(), mouse_bindings, state, x)?,
-            PropertyNotify(_) => (), // Not currently handled
+            PropertyNotify(e) => handle::property_notify(e.clone(), state, x)?,
             RandrNotify => handle::detect_screens(state, x)?,
             ScreenChange => handle::screen_change(state, x)?,
             UnmapNotify(xid) => handle::unmap_notify(*xid, state, x)?,
@@ -470,6 +838,11 @@ where
     //       anything that they have set up.
     #[tracing::instrument(level = "info", skip(self))]
     fn manage_existing_clients(&mut self) -> Result<()> {
+        #[cfg(feature = "serde")]
+        if let Err(e) = self.restore_persisted_state() {
+            error!(%e, "unable to restore persisted state");
+        }
+
         info!("managing existing clients");
 
         // We're not guaranteed that workspace indices are _always_ continuous from 0..n
@@ -490,6 +863,10 @@ where
                 ..
             } = self.x.get_window_attributes(id)?;
 
+            if map_state == MapState::Viewable {
+                update_struts(id, &mut self.state, &self.x);
+            }
+
             // ignore override_redirect == true, already managed clients and clients
             // that are not either currently visible or iconic
             if override_redirect
@@ -500,6 +877,10 @@ where
                 continue;
             }
 
+            if track_unmanaged(id, &mut self.state, &self.x)? {
+                continue;
+            }
+
             info!(%id, "attempting to manage existing client");
             let workspace_id = match self.x.get_prop(id, Atom::NetWmDesktop.as_ref()) {
                 Ok(Some(Prop::Cardinal(ids))) => ids[0] as usize,
@@ -513,4 +894,63 @@ where
         info!("triggering refresh");
         self.x.refresh(&mut self.state)
     }
+
+    // Restore a ClientSet that was written out using State::persist_client_set, retaining
+    // any clients that still exist. Restored clients are left in place when we go on to
+    // manage the remaining existing clients.
+    #[cfg(feature = "serde")]
+    fn restore_persisted_state(&mut self) -> Result<()> {
+        let path = match env::var(PERSISTED_STATE_ENV_VAR) {
+            Ok(path) => path,
+            Err(_) => return Ok(()),
+        };
+        env::remove_var(PERSISTED_STATE_ENV_VAR);
+
+        info!(%path, "restoring persisted state");
+        let raw = fs::read_to_string(&path)?;
+        fs::remove_file(&path)?;
+        let PersistedState {
+            client_set,
+            layouts,
+        } = serde_json::from_str(&raw)?;
+
+        let mut clients = Vec::new();
+        for id in self.x.existing_clients()? {
+            if !self.x.get_window_attributes(id)?.override_redirect
+                && unmanaged_window_type(id, &self.x)?.is_none()
+            {
+                clients.push(id);
+            }
+        }
+
+        self.state
+            .client_set
+            .restore(client_set, &layouts, &clients)?;
+
+        let restored: Vec<Xid> = self.state.client_set.clients().copied().collect();
+        for id in restored {
+            let props = ClientProperties::for_client(id, &self.x)?;
+            self.state.client_props.insert(id, props);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pure::geometry::Rect, x::mock::MockXConn};
+    use simple_test_case::test_case;
+
+    #[test_case(Duration::ZERO, MIN_TIMER_INTERVAL; "zero")]
+    #[test_case(Duration::from_micros(10), MIN_TIMER_INTERVAL; "sub millisecond")]
+    #[test_case(Duration::from_secs(5), Duration::from_secs(5); "seconds")]
+    #[test]
+    fn add_timer_clamps_short_intervals(interval: Duration, expected: Duration) {
+        let mut wm = MockXConn::new(vec![Rect::new(0, 0, 1000, 800)]).into_wm();
+        wm.add_timer(interval, |_: &mut State<MockXConn>, _: &MockXConn| Ok(()));
+
+        assert_eq!(wm.timers[0].interval, expected);
+    }
 }
@@ -33,6 +33,7 @@ pub const EWMH_SUPPORTED_ATOMS: &[Atom] = &[
     Atom::NetActiveWindow,
     Atom::NetWmDesktop,
     Atom::NetWmStrut,
+    Atom::NetWmStrutPartial,
     Atom::NetWmState,
     Atom::NetWmName,
     // TODO: read up on how this works and implement
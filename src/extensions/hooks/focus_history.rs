@@ -0,0 +1,250 @@
+//! A most-recently-used history of focused clients.
+//!
+//! Once [add_focus_history] has been called, the [FocusHistory] state extension tracks the
+//! order in which clients have been focused across all workspaces. This can then be used to
+//! jump back to the previously focused client using [focus_previous] or to cycle through
+//! clients in alt-tab style using [cycle_focus_history].
+use crate::{
+    builtin::actions::key_handler,
+    core::{bindings::KeyEventHandler, State, WindowManager},
+    x::{XConn, XConnExt},
+    Result, Xid,
+};
+use tracing::debug;
+
+/// The clients that have been focused, most recently focused first.
+///
+/// While cycling through the history using [cycle_focus_history] the order is frozen so that
+/// repeated presses move further back through the history. The client that is focused when
+/// the modifiers of the binding are released is then moved to the front.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct FocusHistory {
+    clients: Vec<Xid>,
+    cycle_position: Option<usize>,
+}
+
+impl FocusHistory {
+    /// Iterate over the clients in the history, most recently focused first.
+    pub fn iter(&self) -> impl Iterator<Item = &Xid> {
+        self.clients.iter()
+    }
+
+    /// The client that was focused before the current one.
+    pub fn previous(&self) -> Option<Xid> {
+        self.clients.get(1).copied()
+    }
+
+    /// Whether or not we are currently part way through cycling the history.
+    pub fn is_cycling(&self) -> bool {
+        self.cycle_position.is_some()
+    }
+
+    fn record(&mut self, client: Xid) {
+        self.clients.retain(|&c| c != client);
+        self.clients.insert(0, client);
+    }
+
+    // The next client to focus when cycling, moving the cycle position along
+    fn step(&mut self, forward: bool) -> Option<Xid> {
+        let n = self.clients.len();
+        if n == 0 {
+            return None;
+        }
+
+        let pos = self.cycle_position.unwrap_or(0).min(n - 1);
+        let next = if forward {
+            (pos + 1) % n
+        } else {
+            (pos + n - 1) % n
+        };
+        self.cycle_position = Some(next);
+
+        self.clients.get(next).copied()
+    }
+
+    // Drop clients that are no longer managed, keeping the cycle position pointing at the
+    // same client if we are part way through cycling. If that client has been removed then
+    // the position is moved back so that the next forward step lands on the client after it.
+    fn retain(&mut self, f: impl Fn(&Xid) -> bool) {
+        if let Some(pos) = self.cycle_position {
+            let removed = self.clients.iter().take(pos).filter(|id| !f(id)).count();
+            let current_removed = self.clients.get(pos).map(|id| !f(id)).unwrap_or(false);
+            let pos = pos - removed;
+
+            self.cycle_position = Some(if current_removed {
+                pos.saturating_sub(1)
+            } else {
+                pos
+            });
+        }
+
+        self.clients.retain(f);
+    }
+
+    fn finish_cycle(&mut self, focused: Option<Xid>) {
+        self.cycle_position = None;
+        if let Some(id) = focused {
+            self.record(id);
+        }
+    }
+}
+
+/// Add tracking of the most recently focused clients to an existing [WindowManager].
+pub fn add_focus_history<X>(mut wm: WindowManager<X>) -> WindowManager<X>
+where
+    X: XConn + 'static,
+{
+    wm.state.add_extension(FocusHistory::default());
+    wm.state.config.compose_or_set_refresh_hook(refresh_hook);
+
+    wm
+}
+
+fn refresh_hook<X: XConn>(state: &mut State<X>, _: &X) -> Result<()> {
+    let s = state.extension::<FocusHistory>()?;
+    let mut history = s.borrow_mut();
+
+    history.retain(|id| state.client_set.contains(id));
+
+    if history.is_cycling() || !state.diff.focused_client_changed() {
+        return Ok(());
+    }
+
+    if let Some(id) = state.diff.focused_client() {
+        history.record(id);
+    }
+
+    Ok(())
+}
+
+/// Focus the client that was focused before the current one, switching workspaces if needed.
+///
+/// Running this repeatedly will toggle between the two most recently focused clients.
+///
+/// **NOTE**: You will need to make use of [add_focus_history] for this action to work.
+pub fn focus_previous<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
+    key_handler(|state, x: &X| {
+        let client = state.extension::<FocusHistory>()?.borrow().previous();
+
+        match client {
+            Some(id) => x.modify_and_refresh(state, |cs| cs.focus_client(&id)),
+            None => Ok(()),
+        }
+    })
+}
+
+/// Cycle through the focus history in alt-tab style, switching workspaces as needed.
+///
+/// Each press of the binding focuses the next (or previous if `forward` is false) client in
+/// the history. The history is only updated once the modifiers used for the binding have
+/// been released, so holding `Alt` and tapping `Tab` moves further back through the history
+/// while a single press and release toggles between the two most recent clients.
+///
+/// **NOTE**: You will need to make use of [add_focus_history] for this action to work.
+pub fn cycle_focus_history<X: XConn>(forward: bool) -> Box<dyn KeyEventHandler<X>> {
+    key_handler(move |state, x: &X| {
+        let client = state
+            .extension::<FocusHistory>()?
+            .borrow_mut()
+            .step(forward);
+
+        if let Some(id) = client {
+            debug!(%id, "cycling focus history");
+            x.modify_and_refresh(state, |cs| cs.focus_client(&id))?;
+        }
+
+        state.on_modifiers_released(
+            |state: &mut State<X>, _: &X| {
+                let focused = state.client_set.current_client().copied();
+                state
+                    .extension::<FocusHistory>()?
+                    .borrow_mut()
+                    .finish_cycle(focused);
+
+                Ok(())
+            },
+            x,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    fn history(ids: &[u32], cycle_position: Option<usize>) -> FocusHistory {
+        FocusHistory {
+            clients: ids.iter().map(|&id| Xid(id)).collect(),
+            cycle_position,
+        }
+    }
+
+    fn ids(h: &FocusHistory) -> Vec<u32> {
+        h.iter().map(|id| **id).collect()
+    }
+
+    #[test_case(4, &[4, 1, 2, 3]; "new client")]
+    #[test_case(1, &[1, 2, 3]; "current client")]
+    #[test_case(3, &[3, 1, 2]; "existing client")]
+    #[test]
+    fn record_moves_the_client_to_the_front(id: u32, expected: &[u32]) {
+        let mut h = history(&[1, 2, 3], None);
+        h.record(Xid(id));
+
+        assert_eq!(ids(&h), expected);
+    }
+
+    #[test_case(true, &[2, 3, 1, 2]; "forward")]
+    #[test_case(false, &[3, 2, 1, 3]; "backward")]
+    #[test]
+    fn step_wraps_around_the_history(forward: bool, expected: &[u32]) {
+        let mut h = history(&[1, 2, 3], None);
+        let stepped: Vec<u32> = (0..4).map(|_| *h.step(forward).unwrap()).collect();
+
+        assert_eq!(stepped, expected);
+        assert!(h.is_cycling());
+        assert_eq!(ids(&h), vec![1, 2, 3], "order is frozen while cycling");
+    }
+
+    #[test]
+    fn step_with_an_empty_history_is_none() {
+        let mut h = FocusHistory::default();
+
+        assert_eq!(h.step(true), None);
+        assert!(!h.is_cycling());
+    }
+
+    #[test_case(Some(3), &[3, 1, 2]; "focused client")]
+    #[test_case(None, &[1, 2, 3]; "no focused client")]
+    #[test]
+    fn finish_cycle_records_the_focused_client(focused: Option<u32>, expected: &[u32]) {
+        let mut h = history(&[1, 2, 3], Some(2));
+        h.finish_cycle(focused.map(Xid));
+
+        assert!(!h.is_cycling());
+        assert_eq!(ids(&h), expected);
+    }
+
+    #[test_case(&[1], Some(1); "client before the cycle position")]
+    #[test_case(&[4], Some(2); "client after the cycle position")]
+    #[test_case(&[3], Some(1); "client at the cycle position")]
+    #[test_case(&[1, 2], Some(0); "multiple clients")]
+    #[test]
+    fn retain_keeps_the_cycle_position(removed: &[u32], expected: Option<usize>) {
+        let mut h = history(&[1, 2, 3, 4], Some(2));
+        h.retain(|id| !removed.contains(id));
+
+        assert_eq!(h.cycle_position, expected);
+    }
+
+    #[test]
+    fn removing_clients_while_cycling_does_not_skip_clients() {
+        let mut h = history(&[1, 2, 3, 4], None);
+        assert_eq!(h.step(true), Some(Xid(2)));
+
+        h.retain(|&id| id != Xid(1));
+
+        assert_eq!(h.step(true), Some(Xid(3)));
+    }
+}
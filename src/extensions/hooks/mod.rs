@@ -1,11 +1,15 @@
 //! Hook implementations and helpers for adding to your Penrose window manager
 pub mod ewmh;
+pub mod focus_history;
 pub mod manage;
 pub mod named_scratchpads;
 pub mod startup;
+pub mod urgency;
 pub mod window_swallowing;
 
 pub use ewmh::add_ewmh_hooks;
+pub use focus_history::{add_focus_history, cycle_focus_history, focus_previous, FocusHistory};
 pub use named_scratchpads::{add_named_scratchpads, NamedScratchPad, ToggleNamedScratchPad};
 pub use startup::SpawnOnStartup;
+pub use urgency::{add_urgency_hook, focus_urgent, NoUrgencyHook, UrgencyHook, UrgentClients};
 pub use window_swallowing::WindowSwallowing;
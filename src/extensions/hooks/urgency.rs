@@ -0,0 +1,427 @@
+//! Tracking of clients that are requesting the user's attention.
+//!
+//! Clients are marked as urgent either by setting the urgency hint in their WM_HINTS
+//! property or via a _NET_WM_STATE client message adding the
+//! _NET_WM_STATE_DEMANDS_ATTENTION state. Urgency is cleared when the client gains
+//! focus or is removed from the window manager.
+//!
+//! The set of currently urgent clients is available via the [UrgentClients] state
+//! extension once [add_urgency_hook] has been called.
+use crate::{
+    builtin::actions::key_handler,
+    core::{bindings::KeyEventHandler, State, WindowManager},
+    x::{
+        atom::Atom,
+        event::{ClientMessage, PropertyEvent},
+        property::Prop,
+        XConn, XConnExt, XEvent,
+    },
+    Result, Xid,
+};
+use std::{cell::RefCell, fmt, rc::Rc};
+use tracing::{debug, error, warn};
+
+/// An action to run when a client becomes urgent, such as sending a notification.
+pub trait UrgencyHook<X>
+where
+    X: XConn,
+{
+    /// Run this hook
+    fn call(&mut self, client: Xid, state: &mut State<X>, x: &X) -> Result<()>;
+
+    /// Convert to a trait object
+    fn boxed(self) -> Box<dyn UrgencyHook<X>>
+    where
+        Self: Sized + 'static,
+    {
+        Box::new(self)
+    }
+}
+
+impl<X: XConn> fmt::Debug for Box<dyn UrgencyHook<X>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UrgencyHook").finish()
+    }
+}
+
+impl<F, X> UrgencyHook<X> for F
+where
+    F: FnMut(Xid, &mut State<X>, &X) -> Result<()>,
+    X: XConn,
+{
+    fn call(&mut self, client: Xid, state: &mut State<X>, x: &X) -> Result<()> {
+        (self)(client, state, x)
+    }
+}
+
+/// The set of clients that are currently marked as urgent, in the order that they
+/// became urgent.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct UrgentClients(Vec<Xid>);
+
+impl UrgentClients {
+    /// Whether or not the given client is currently urgent.
+    pub fn contains(&self, client: &Xid) -> bool {
+        self.0.contains(client)
+    }
+
+    /// Whether or not there are any urgent clients.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over the currently urgent clients, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &Xid> {
+        self.0.iter()
+    }
+
+    /// The client that most recently became urgent.
+    pub fn most_recent(&self) -> Option<Xid> {
+        self.0.last().copied()
+    }
+
+    // Returns true if the client was not previously urgent
+    fn mark(&mut self, client: Xid) -> bool {
+        if self.contains(&client) {
+            return false;
+        }
+        self.0.push(client);
+
+        true
+    }
+
+    // Returns true if the client was previously urgent
+    fn clear(&mut self, client: &Xid) -> bool {
+        let n = self.0.len();
+        self.0.retain(|c| c != client);
+
+        self.0.len() != n
+    }
+}
+
+/// Add tracking of urgent clients to an existing [WindowManager].
+///
+/// The provided [UrgencyHook] is run each time a client that was not previously
+/// urgent is marked as such. If you only want to track urgent clients (for display in a
+/// status bar for example) then you can pass [NoUrgencyHook].
+pub fn add_urgency_hook<X, H>(mut wm: WindowManager<X>, hook: H) -> WindowManager<X>
+where
+    X: XConn + 'static,
+    H: UrgencyHook<X> + 'static,
+{
+    // Clients can be marked as urgent when they are first managed as well as in response to
+    // events so the user hook is shared between both
+    let hook = Rc::new(RefCell::new(hook.boxed()));
+    let manage_hook_ref = hook.clone();
+
+    wm.state.add_extension(UrgentClients::default());
+    wm.state
+        .config
+        .compose_or_set_event_hook(move |e: &XEvent, s: &mut State<X>, x: &X| {
+            event_hook(e, &mut hook.borrow_mut(), s, x)
+        });
+    wm.state
+        .config
+        .compose_or_set_manage_hook(move |id: Xid, s: &mut State<X>, x: &X| {
+            manage_hook(id, &mut manage_hook_ref.borrow_mut(), s, x)
+        });
+    wm.state.config.compose_or_set_refresh_hook(refresh_hook);
+
+    wm
+}
+
+/// An [UrgencyHook] that does nothing.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NoUrgencyHook;
+
+impl<X: XConn> UrgencyHook<X> for NoUrgencyHook {
+    fn call(&mut self, _: Xid, _: &mut State<X>, _: &X) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Mark the given client as urgent, running the user [UrgencyHook] if it was not already
+/// urgent. The currently focused client is never marked as urgent.
+fn mark_urgent<X: XConn>(
+    client: Xid,
+    hook: &mut Box<dyn UrgencyHook<X>>,
+    state: &mut State<X>,
+    x: &X,
+) -> Result<()> {
+    if mark_urgent_without_refresh(client, hook, state, x)? {
+        x.refresh(state)?;
+    }
+
+    Ok(())
+}
+
+// Returns true if the client was newly marked as urgent
+fn mark_urgent_without_refresh<X: XConn>(
+    client: Xid,
+    hook: &mut Box<dyn UrgencyHook<X>>,
+    state: &mut State<X>,
+    x: &X,
+) -> Result<bool> {
+    if state.client_set.current_client() == Some(&client) || !state.client_set.contains(&client) {
+        return Ok(false);
+    }
+
+    let newly_urgent = state
+        .extension::<UrgentClients>()?
+        .borrow_mut()
+        .mark(client);
+
+    if newly_urgent {
+        debug!(%client, "client marked as urgent");
+        set_demands_attention(client, true, x)?;
+        if let Err(e) = hook.call(client, state, x) {
+            error!(%e, %client, "error running user urgency hook");
+        }
+    }
+
+    Ok(newly_urgent)
+}
+
+fn clear_urgent<X: XConn>(client: Xid, state: &mut State<X>, x: &X) -> Result<()> {
+    let cleared = state
+        .extension::<UrgentClients>()?
+        .borrow_mut()
+        .clear(&client);
+
+    if cleared {
+        debug!(%client, "clearing urgency for client");
+        set_demands_attention(client, false, x)?;
+        x.refresh(state)?;
+    }
+
+    Ok(())
+}
+
+fn event_hook<X: XConn>(
+    event: &XEvent,
+    hook: &mut Box<dyn UrgencyHook<X>>,
+    state: &mut State<X>,
+    x: &X,
+) -> Result<bool> {
+    match event {
+        // Client properties are updated before event hooks are run so we can check the
+        // cached value to see if the urgency hint is set.
+        XEvent::PropertyNotify(PropertyEvent { id, atom, .. })
+            if atom == Atom::WmHints.as_ref() =>
+        {
+            let urgent = state
+                .client_properties(id)
+                .map(|p| p.urgent)
+                .unwrap_or(false);
+
+            if urgent {
+                mark_urgent(*id, hook, state, x)?;
+            } else {
+                clear_urgent(*id, state, x)?;
+            }
+        }
+
+        XEvent::ClientMessage(ClientMessage {
+            id, dtype, data, ..
+        }) if dtype == Atom::NetWmState.as_ref() => {
+            let mut data32 = data.as_u32();
+            if data32.is_empty() {
+                warn!(?data, "malformed data in _NET_WM_STATE message");
+                return Ok(true);
+            }
+
+            let demands_attention = x.intern_atom(Atom::NetWmStateDemandsAttention.as_ref())?;
+            let raw_action = data32.remove(0);
+
+            if !data32.contains(&demands_attention) {
+                return Ok(true);
+            }
+
+            let currently_urgent = state.extension::<UrgentClients>()?.borrow().contains(id);
+
+            match raw_action {
+                0 => clear_urgent(*id, state, x)?,
+                1 => mark_urgent(*id, hook, state, x)?,
+                2 if currently_urgent => clear_urgent(*id, state, x)?,
+                2 => mark_urgent(*id, hook, state, x)?,
+                action => warn!(%action, "invalid _NET_WM_STATE action: expected 0, 1 or 2"),
+            }
+        }
+
+        _ => (),
+    }
+
+    Ok(true)
+}
+
+// The window manager refreshes once the client has been managed so there is no need for us
+// to trigger a refresh here.
+fn manage_hook<X: XConn>(
+    id: Xid,
+    hook: &mut Box<dyn UrgencyHook<X>>,
+    state: &mut State<X>,
+    x: &X,
+) -> Result<()> {
+    let urgent = state
+        .client_properties(&id)
+        .map(|p| p.urgent)
+        .unwrap_or(false);
+
+    if urgent {
+        debug!(%id, "newly managed client is urgent");
+        mark_urgent_without_refresh(id, hook, state, x)?;
+    }
+
+    Ok(())
+}
+
+// Urgency is cleared for the focused client and any clients that are no longer managed
+fn refresh_hook<X: XConn>(state: &mut State<X>, x: &X) -> Result<()> {
+    let focused = state.client_set.current_client().copied();
+    let s = state.extension::<UrgentClients>()?;
+    let mut urgent = s.borrow_mut();
+
+    urgent.0.retain(|id| state.client_set.contains(id));
+
+    if let Some(id) = focused {
+        if urgent.clear(&id) {
+            debug!(%id, "clearing urgency for focused client");
+            set_demands_attention(id, false, x)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Focus the client that most recently became urgent, switching workspaces if needed.
+///
+/// **NOTE**: You will need to make use of [add_urgency_hook] for this action to work.
+pub fn focus_urgent<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
+    key_handler(|state, x: &X| {
+        let client = state.extension::<UrgentClients>()?.borrow().most_recent();
+
+        match client {
+            Some(id) => x.modify_and_refresh(state, |cs| cs.focus_client(&id)),
+            None => Ok(()),
+        }
+    })
+}
+
+// Keep _NET_WM_STATE in sync with our view of urgency so that it is visible to external programs.
+// Clients may have set the property themselves as a list of atoms rather than cardinals so we
+// need to handle both in order to preserve any other states that are present.
+fn set_demands_attention<X: XConn>(id: Xid, demands_attention: bool, x: &X) -> Result<()> {
+    let net_wm_state = Atom::NetWmState.as_ref();
+    let attention = *x.intern_atom(Atom::NetWmStateDemandsAttention.as_ref())?;
+
+    let mut wstate = match x.get_prop(id, net_wm_state) {
+        Ok(Some(Prop::Cardinal(vals))) => vals,
+        Ok(Some(Prop::Atom(names))) => names
+            .iter()
+            .map(|name| x.intern_atom(name).map(|atom| *atom))
+            .collect::<Result<Vec<u32>>>()?,
+        _ => vec![],
+    };
+
+    wstate.retain(|&val| val != attention);
+    if demands_attention {
+        wstate.push(attention);
+    }
+
+    x.set_prop(id, net_wm_state, Prop::Cardinal(wstate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        pure::geometry::Rect,
+        x::{mock::MockXConn, property::ClientProperties},
+    };
+    use simple_test_case::test_case;
+
+    #[test]
+    fn mark_only_reports_newly_urgent_clients() {
+        let mut urgent = UrgentClients::default();
+
+        assert!(urgent.mark(Xid(1)));
+        assert!(urgent.mark(Xid(2)));
+        assert!(!urgent.mark(Xid(1)));
+
+        assert_eq!(
+            urgent.iter().copied().collect::<Vec<_>>(),
+            vec![Xid(1), Xid(2)]
+        );
+        assert_eq!(urgent.most_recent(), Some(Xid(2)));
+    }
+
+    #[test]
+    fn clear_only_reports_previously_urgent_clients() {
+        let mut urgent = UrgentClients(vec![Xid(1), Xid(2)]);
+
+        assert!(urgent.clear(&Xid(2)));
+        assert!(!urgent.clear(&Xid(2)));
+
+        assert!(urgent.contains(&Xid(1)));
+        assert_eq!(urgent.most_recent(), Some(Xid(1)));
+        assert!(urgent.clear(&Xid(1)));
+        assert!(urgent.is_empty());
+    }
+
+    #[test_case(
+        Prop::Atom(vec![Atom::NetWmStateFullscreen.as_ref().to_owned()]);
+        "atoms"
+    )]
+    #[test_case(Prop::Cardinal(vec![1]); "cardinals")]
+    #[test]
+    fn set_demands_attention_preserves_other_states(initial: Prop) {
+        let x = MockXConn::default();
+        let other = *x.intern_atom(Atom::NetWmStateFullscreen.as_ref()).unwrap();
+        let attention = *x
+            .intern_atom(Atom::NetWmStateDemandsAttention.as_ref())
+            .unwrap();
+        let x = x.with_prop(Xid(1), Atom::NetWmState.as_ref(), initial);
+
+        let get = |x: &MockXConn| x.get_prop(Xid(1), Atom::NetWmState.as_ref()).unwrap();
+
+        set_demands_attention(Xid(1), true, &x).unwrap();
+        assert_eq!(get(&x), Some(Prop::Cardinal(vec![other, attention])));
+
+        set_demands_attention(Xid(1), false, &x).unwrap();
+        assert_eq!(get(&x), Some(Prop::Cardinal(vec![other])));
+    }
+
+    #[test_case(Xid(1), vec![]; "focused client")]
+    #[test_case(Xid(2), vec![Xid(2)]; "unfocused client")]
+    #[test]
+    fn urgent_clients_run_the_urgency_hook_when_managed(id: Xid, expected: Vec<Xid>) {
+        let screens = vec![Rect::new(0, 0, 1000, 800)];
+        let x = MockXConn::new(screens.clone());
+        let called = Rc::new(RefCell::new(Vec::new()));
+        let c = called.clone();
+
+        let wm = MockXConn::new(screens).into_wm();
+        let mut wm = add_urgency_hook(wm, move |id: Xid, _: &mut State<_>, _: &_| {
+            c.borrow_mut().push(id);
+            Ok(())
+        });
+
+        let state = &mut wm.state;
+        state.client_set.insert(Xid(1));
+        state.client_set.insert_as_focus_for("2", Xid(2));
+        for c in [Xid(1), Xid(2)] {
+            let props = ClientProperties {
+                urgent: true,
+                ..Default::default()
+            };
+            state.client_props.insert(c, props);
+        }
+
+        let mut hook = state.config.manage_hook.take().unwrap();
+        hook.call(id, state, &x).unwrap();
+
+        assert_eq!(*called.borrow(), expected);
+        let urgent = state.extension::<UrgentClients>().unwrap();
+        assert_eq!(urgent.borrow().contains(&Xid(2)), !expected.is_empty());
+    }
+}
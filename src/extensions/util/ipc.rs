@@ -0,0 +1,821 @@
+//! A Unix domain socket for controlling a running window manager from external programs.
+//!
+//! Once [add_ipc_server] has been called, penrose will listen on a Unix socket for
+//! newline delimited JSON encoded [IpcCommand]s and reply to each one with a single line
+//! containing a JSON encoded [IpcResponse]. The `penrose-msg` binary that ships with this
+//! crate can be used to send commands from shell scripts:
+//!
+//! ```sh
+//! $ penrose-msg focus-tag 3
+//! $ penrose-msg move-client 5
+//! $ penrose-msg layout-message inc-main 1
+//! $ penrose-msg query-state
+//! ```
+//!
+//! The socket can also be written to directly using something like `socat`:
+//!
+//! ```sh
+//! $ echo '{"command":"focus_tag","tag":"3"}' | socat - UNIX-CONNECT:$XDG_RUNTIME_DIR/penrose-0.sock
+//! {"status":"success"}
+//! ```
+//!
+//! In addition to one-shot commands, clients can send [IpcCommand::Subscribe] in order to
+//! receive a stream of [IpcEvent]s (one JSON object per line) describing changes to the window
+//! manager state each time it is refreshed:
+//!
+//! ```sh
+//! $ penrose-msg subscribe
+//! {"event":"workspace_focused","tag":"2"}
+//! {"event":"client_focused","client":4194313}
+//! ```
+//!
+//! Connections are accepted and read from on background threads so a slow or misbehaving
+//! client can not block the window manager. Subscribers that fall more than
+//! [SUBSCRIBER_BUFFER_SIZE] events behind are disconnected. Commands themselves are run on the main thread
+//! in between handling events from the X server.
+use crate::{
+    builtin::layout::messages::{ExpandMain, IncMain, Mirror, Rotate, ShrinkMain},
+    core::{hooks::EventSource, ClientSet, State, WindowManager},
+    pure::Diff,
+    pure::{geometry::Rect, Workspace},
+    x::{XConn, XConnExt},
+    Error, Result, Xid,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    os::unix::{
+        io::{AsRawFd, RawFd},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+};
+use tracing::{debug, error, info, warn};
+
+/// The maximum number of events that can be queued for a subscriber before it is considered
+/// to have fallen behind and is disconnected.
+pub const SUBSCRIBER_BUFFER_SIZE: usize = 256;
+
+/// The environment variable that can be used to override the default socket path.
+pub const SOCKET_ENV_VAR: &str = "PENROSE_SOCKET";
+
+/// The path of the IPC socket for the current X display.
+///
+/// If [SOCKET_ENV_VAR] is set then its value is used, otherwise the socket is placed in
+/// `$XDG_RUNTIME_DIR` (falling back to the system temp directory) and named using the
+/// current `$DISPLAY` so that multiple running instances of penrose do not collide.
+pub fn default_socket_path() -> PathBuf {
+    if let Some(path) = env::var_os(SOCKET_ENV_VAR) {
+        return PathBuf::from(path);
+    }
+
+    let dir = env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir);
+    let display: String = env::var("DISPLAY")
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '.')
+        .collect();
+
+    dir.join(format!("penrose-{display}.sock"))
+}
+
+/// A command that can be sent to a running window manager over the IPC socket.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum IpcCommand {
+    /// Focus the workspace with the given tag
+    FocusTag {
+        /// The tag of the workspace to focus
+        tag: String,
+    },
+    /// Move a client to the workspace with the given tag
+    MoveClient {
+        /// The tag of the workspace to move the client to
+        tag: String,
+        /// The client to move: defaults to the currently focused client
+        #[serde(default)]
+        client: Option<Xid>,
+    },
+    /// Send a message to the layout of the focused workspace
+    SendLayoutMessage {
+        /// The message to send
+        message: LayoutMessage,
+    },
+    /// Return a summary of the current window manager state
+    QueryState,
+    /// Subscribe to a stream of [IpcEvent]s.
+    ///
+    /// After replying with [IpcResponse::Success] the connection will only be used for sending
+    /// events and any further commands sent over it will be ignored.
+    Subscribe,
+}
+
+/// The built in layout messages that can be sent over the IPC socket.
+///
+/// See [crate::builtin::layout::messages] for details of each message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayoutMessage {
+    /// Alter the number of clients in the main area
+    IncMain(i8),
+    /// Expand the size of the main area
+    ExpandMain,
+    /// Shrink the size of the main area
+    ShrinkMain,
+    /// Rotate the layout
+    Rotate,
+    /// Mirror the layout
+    Mirror,
+}
+
+impl LayoutMessage {
+    fn send_to(self, ws: &mut Workspace<Xid>) {
+        match self {
+            Self::IncMain(n) => ws.handle_message(IncMain(n)),
+            Self::ExpandMain => ws.handle_message(ExpandMain),
+            Self::ShrinkMain => ws.handle_message(ShrinkMain),
+            Self::Rotate => ws.handle_message(Rotate),
+            Self::Mirror => ws.handle_message(Mirror),
+        }
+    }
+}
+
+/// The reply sent back over the IPC socket for each [IpcCommand].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum IpcResponse {
+    /// The command was run successfully
+    Success,
+    /// The current window manager state in response to [IpcCommand::QueryState]
+    State(StateSummary),
+    /// The command could not be run
+    Error {
+        /// A description of what went wrong
+        message: String,
+    },
+}
+
+impl IpcResponse {
+    fn error(message: impl Into<String>) -> Self {
+        Self::Error {
+            message: message.into(),
+        }
+    }
+}
+
+/// A change to the window manager state that is published to subscribers after each refresh.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum IpcEvent {
+    /// A new client is now being managed
+    ClientManaged {
+        /// The client that is now being managed
+        client: Xid,
+        /// The tag of the workspace the client was added to
+        tag: String,
+    },
+    /// A client is no longer being managed
+    ClientUnmanaged {
+        /// The client that is no longer being managed
+        client: Xid,
+    },
+    /// The set of connected screens or their dimensions have changed
+    ScreensChanged {
+        /// The geometry of each screen, ordered by index
+        screens: Vec<Rect>,
+    },
+    /// A different screen now has focus
+    ScreenFocused {
+        /// The index of the newly focused screen
+        screen: usize,
+    },
+    /// A different workspace now has focus
+    WorkspaceFocused {
+        /// The tag of the newly focused workspace
+        tag: String,
+    },
+    /// The active layout for a visible workspace has changed
+    LayoutChanged {
+        /// The tag of the workspace whose layout changed
+        tag: String,
+        /// The name of the new layout
+        layout: String,
+    },
+    /// The focused client has changed
+    ClientFocused {
+        /// The newly focused client (if there is one)
+        client: Option<Xid>,
+    },
+}
+
+impl IpcEvent {
+    fn from_diff(diff: &Diff<Xid>, cs: &ClientSet) -> Vec<Self> {
+        let mut events: Vec<Self> = diff
+            .new_clients()
+            .map(|&client| Self::ClientManaged {
+                client,
+                tag: cs.tag_for_client(&client).unwrap_or_default().to_string(),
+            })
+            .collect();
+
+        events.extend(
+            diff.withdrawn_clients()
+                .map(|&client| Self::ClientUnmanaged { client }),
+        );
+
+        if diff.screens_changed() {
+            let mut screens: Vec<_> = diff.after.screens().map(|s| (s.screen, s.r)).collect();
+            screens.sort_by_key(|&(i, _)| i);
+            let screens = screens.into_iter().map(|(_, r)| r).collect();
+            events.push(Self::ScreensChanged { screens });
+        }
+
+        if let Some(screen) = diff.newly_focused_screen() {
+            events.push(Self::ScreenFocused { screen });
+        }
+
+        if let Some(tag) = diff.newly_focused_tag() {
+            events.push(Self::WorkspaceFocused {
+                tag: tag.to_string(),
+            });
+        }
+
+        events.extend(
+            diff.changed_layouts()
+                .map(|(tag, layout)| Self::LayoutChanged {
+                    tag: tag.to_string(),
+                    layout: layout.to_string(),
+                }),
+        );
+
+        if diff.focused_client_changed() {
+            events.push(Self::ClientFocused {
+                client: diff.focused_client(),
+            });
+        }
+
+        events
+    }
+}
+
+/// A summary of the current window manager state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateSummary {
+    /// The tag of the focused workspace
+    pub focused_tag: String,
+    /// The currently focused client (if there is one)
+    pub focused_client: Option<Xid>,
+    /// The index of the focused screen
+    pub focused_screen: usize,
+    /// Each connected screen, ordered by index
+    pub screens: Vec<ScreenSummary>,
+    /// Each workspace, ordered by id
+    pub workspaces: Vec<WorkspaceSummary>,
+}
+
+/// A summary of a single screen.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreenSummary {
+    /// The index of this screen
+    pub index: usize,
+    /// The tag of the workspace shown on this screen
+    pub tag: String,
+    /// The geometry of this screen
+    pub r: Rect,
+}
+
+/// A summary of a single workspace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceSummary {
+    /// The tag of this workspace
+    pub tag: String,
+    /// The name of the active layout
+    pub layout: String,
+    /// The clients on this workspace in stack order
+    pub clients: Vec<Xid>,
+    /// The focused client on this workspace (if there is one)
+    pub focused_client: Option<Xid>,
+    /// The index of the screen this workspace is visible on (if it is visible)
+    pub screen: Option<usize>,
+}
+
+impl StateSummary {
+    fn new(cs: &ClientSet) -> Self {
+        let mut screens: Vec<ScreenSummary> = cs
+            .screens()
+            .map(|s| ScreenSummary {
+                index: s.index(),
+                tag: s.workspace.tag().to_string(),
+                r: s.geometry(),
+            })
+            .collect();
+        screens.sort_by_key(|s| s.index);
+
+        let workspaces = cs
+            .ordered_workspaces()
+            .map(|w| WorkspaceSummary {
+                tag: w.tag().to_string(),
+                layout: w.layout_name(),
+                clients: w.clients().copied().collect(),
+                focused_client: w.focus().copied(),
+                screen: screens.iter().find(|s| s.tag == w.tag()).map(|s| s.index),
+            })
+            .collect();
+
+        Self {
+            focused_tag: cs.current_tag().to_string(),
+            focused_client: cs.current_client().copied(),
+            focused_screen: cs.current_screen().index(),
+            screens,
+            workspaces,
+        }
+    }
+}
+
+/// Send a single [IpcCommand] to the window manager listening on the given socket and
+/// wait for its response.
+pub fn send_command(path: impl AsRef<Path>, command: &IpcCommand) -> Result<IpcResponse> {
+    let (response, _) = send_command_on(UnixStream::connect(path)?, command)?;
+
+    Ok(response)
+}
+
+fn send_command_on(
+    mut stream: UnixStream,
+    command: &IpcCommand,
+) -> Result<(IpcResponse, BufReader<UnixStream>)> {
+    let mut line = serde_json::to_string(command)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut reader = BufReader::new(stream);
+    let mut reply = String::new();
+    reader.read_line(&mut reply)?;
+
+    Ok((serde_json::from_str(&reply)?, reader))
+}
+
+/// Subscribe to [IpcEvent]s from the window manager listening on the given socket.
+///
+/// The returned iterator blocks waiting for each new event and ends when the window
+/// manager closes the connection.
+pub fn subscribe(path: impl AsRef<Path>) -> Result<impl Iterator<Item = Result<IpcEvent>>> {
+    match send_command_on(UnixStream::connect(path)?, &IpcCommand::Subscribe)? {
+        (IpcResponse::Success, reader) => {
+            Ok(reader.lines().map(|line| Ok(serde_json::from_str(&line?)?)))
+        }
+        (IpcResponse::Error { message }, _) => Err(Error::Custom(message)),
+        (response, _) => Err(Error::Custom(format!(
+            "unexpected response to subscribe: {response:?}"
+        ))),
+    }
+}
+
+/// Start listening for [IpcCommand]s on the given socket path.
+///
+/// This also adds a refresh hook for publishing [IpcEvent]s to any subscribed clients.
+/// Any existing file at `path` is removed before binding the socket. If you do not have
+/// a specific location in mind you can use [default_socket_path].
+pub fn add_ipc_server<X>(
+    mut wm: WindowManager<X>,
+    path: impl AsRef<Path>,
+) -> Result<WindowManager<X>>
+where
+    X: XConn + 'static,
+{
+    let path = path.as_ref();
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+    let (wake_tx, wake_rx) = UnixStream::pair()?;
+    wake_rx.set_nonblocking(true)?;
+    let (tx, rx) = mpsc::channel();
+    let subscribers = Subscribers::default();
+    let conn_subscribers = subscribers.clone();
+
+    info!(?path, "listening for IPC commands");
+    thread::spawn(move || accept_connections(listener, tx, wake_tx, conn_subscribers));
+
+    wm.add_event_source(IpcServer { rx, wake_rx });
+    wm.state
+        .config
+        .compose_or_set_refresh_hook(move |state: &mut State<X>, _: &X| {
+            publish_events(&subscribers, state);
+            Ok(())
+        });
+
+    Ok(wm)
+}
+
+// Senders for each connection that has subscribed to events
+type Subscribers = Arc<Mutex<Vec<SyncSender<IpcEvent>>>>;
+
+fn publish_events<X: XConn>(subscribers: &Subscribers, state: &State<X>) {
+    let mut subscribers = match subscribers.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    if subscribers.is_empty() {
+        return;
+    }
+
+    let events = IpcEvent::from_diff(&state.diff, &state.client_set);
+    if events.is_empty() {
+        return;
+    }
+
+    // Drop any subscribers whose connection has since been closed or who are not keeping up
+    // with the events being sent to them. Dropping the sender closes their connection.
+    subscribers.retain(|tx| {
+        events.iter().all(|e| match tx.try_send(e.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                warn!("IPC subscriber has fallen behind: disconnecting");
+                false
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        })
+    });
+}
+
+// A command received from a client along with a channel for sending the reply
+#[derive(Debug)]
+struct Request {
+    command: IpcCommand,
+    reply: Sender<IpcResponse>,
+}
+
+fn accept_connections(
+    listener: UnixListener,
+    tx: Sender<Request>,
+    wake_tx: UnixStream,
+    subscribers: Subscribers,
+) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!(%e, "unable to accept IPC connection");
+                continue;
+            }
+        };
+
+        let (tx, wake_tx) = match wake_tx.try_clone() {
+            Ok(wake_tx) => (tx.clone(), wake_tx),
+            Err(e) => {
+                error!(%e, "unable to clone IPC wake socket");
+                continue;
+            }
+        };
+
+        let subscribers = subscribers.clone();
+
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, tx, wake_tx, subscribers) {
+                debug!(%e, "IPC connection closed with error");
+            }
+        });
+    }
+}
+
+fn handle_connection(
+    stream: UnixStream,
+    tx: Sender<Request>,
+    mut wake_tx: UnixStream,
+    subscribers: Subscribers,
+) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str(&line) {
+            Ok(IpcCommand::Subscribe) => return stream_events(writer, subscribers),
+
+            Ok(command) => {
+                let (reply, reply_rx) = mpsc::channel();
+                if tx.send(Request { command, reply }).is_err() {
+                    return Ok(()); // the window manager is no longer running
+                }
+                wake_tx.write_all(&[0])?;
+
+                reply_rx
+                    .recv()
+                    .unwrap_or_else(|_| IpcResponse::error("no response from window manager"))
+            }
+
+            Err(e) => IpcResponse::error(format!("invalid command: {e}")),
+        };
+
+        write_line(&mut writer, &response)?;
+    }
+
+    Ok(())
+}
+
+fn stream_events(mut writer: UnixStream, subscribers: Subscribers) -> io::Result<()> {
+    let (tx, rx) = mpsc::sync_channel(SUBSCRIBER_BUFFER_SIZE);
+    match subscribers.lock() {
+        Ok(mut guard) => guard.push(tx),
+        Err(poisoned) => poisoned.into_inner().push(tx),
+    }
+
+    write_line(&mut writer, &IpcResponse::Success)?;
+    for event in rx {
+        write_line(&mut writer, &event)?;
+    }
+
+    Ok(())
+}
+
+fn write_line(writer: &mut UnixStream, value: &impl Serialize) -> io::Result<()> {
+    let mut s = serde_json::to_string(value)?;
+    s.push('\n');
+
+    writer.write_all(s.as_bytes())
+}
+
+// Runs on the main thread whenever a connection thread has forwarded a request
+#[derive(Debug)]
+struct IpcServer {
+    rx: Receiver<Request>,
+    wake_rx: UnixStream,
+}
+
+impl<X: XConn> EventSource<X> for IpcServer {
+    fn fd(&self) -> RawFd {
+        self.wake_rx.as_raw_fd()
+    }
+
+    fn on_ready(&mut self, state: &mut State<X>, x: &X) -> Result<()> {
+        let mut buf = [0; 64];
+        loop {
+            match self.wake_rx.read(&mut buf) {
+                Ok(0) => break,
+                Ok(_) => continue,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        while let Ok(Request { command, reply }) = self.rx.try_recv() {
+            debug!(?command, "running IPC command");
+            let response = run_command(command, state, x).unwrap_or_else(|e| {
+                error!(%e, "error running IPC command");
+                IpcResponse::error(e.to_string())
+            });
+
+            // The client may have disconnected while waiting for us
+            let _ = reply.send(response);
+        }
+
+        Ok(())
+    }
+}
+
+fn run_command<X: XConn>(command: IpcCommand, state: &mut State<X>, x: &X) -> Result<IpcResponse> {
+    match command {
+        IpcCommand::FocusTag { tag } => {
+            if !state.client_set.contains_tag(&tag) {
+                return Ok(IpcResponse::error(format!("unknown tag: {tag}")));
+            }
+            x.modify_and_refresh(state, |cs| cs.focus_tag(&tag))?;
+        }
+
+        IpcCommand::MoveClient { tag, client } => {
+            if !state.client_set.contains_tag(&tag) {
+                return Ok(IpcResponse::error(format!("unknown tag: {tag}")));
+            }
+
+            let client = match client.or_else(|| state.client_set.current_client().copied()) {
+                Some(id) if state.client_set.contains(&id) => id,
+                Some(id) => return Ok(IpcResponse::error(format!("unknown client: {id}"))),
+                None => return Ok(IpcResponse::error("no client is currently focused")),
+            };
+            x.modify_and_refresh(state, |cs| cs.move_client_to_tag(&client, &tag))?;
+        }
+
+        IpcCommand::SendLayoutMessage { message } => {
+            x.modify_and_refresh(state, |cs| message.send_to(cs.current_workspace_mut()))?;
+        }
+
+        IpcCommand::QueryState => {
+            return Ok(IpcResponse::State(StateSummary::new(&state.client_set)));
+        }
+
+        // Subscriptions are handled by the connection thread directly
+        IpcCommand::Subscribe => {
+            return Ok(IpcResponse::error("unable to subscribe"));
+        }
+    }
+
+    Ok(IpcResponse::Success)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::x::mock::MockXConn;
+    use serde::de::DeserializeOwned;
+    use simple_test_case::test_case;
+    use std::fmt::Debug;
+
+    fn screens() -> Vec<Rect> {
+        vec![Rect::new(0, 0, 1000, 800), Rect::new(1000, 0, 1000, 800)]
+    }
+
+    fn state_with_clients(n: u32) -> State<MockXConn> {
+        let mut state = MockXConn::new(screens()).into_wm().state;
+        for i in 1..=n {
+            state.client_set.insert(Xid(i));
+        }
+
+        state
+    }
+
+    fn round_trip<T>(value: T)
+    where
+        T: Serialize + DeserializeOwned + PartialEq + Debug,
+    {
+        let s = serde_json::to_string(&value).unwrap();
+        let parsed: T = serde_json::from_str(&s).unwrap();
+
+        assert_eq!(parsed, value);
+    }
+
+    #[test_case(IpcCommand::FocusTag { tag: "3".into() }; "focus tag")]
+    #[test_case(IpcCommand::MoveClient { tag: "3".into(), client: None }; "move focused client")]
+    #[test_case(IpcCommand::MoveClient { tag: "3".into(), client: Some(Xid(5)) }; "move client")]
+    #[test_case(IpcCommand::SendLayoutMessage { message: LayoutMessage::IncMain(-1) }; "inc main")]
+    #[test_case(IpcCommand::SendLayoutMessage { message: LayoutMessage::Rotate }; "rotate")]
+    #[test_case(IpcCommand::QueryState; "query state")]
+    #[test_case(IpcCommand::Subscribe; "subscribe")]
+    #[test]
+    fn commands_round_trip(command: IpcCommand) {
+        round_trip(command);
+    }
+
+    #[test_case(IpcResponse::Success; "success")]
+    #[test_case(IpcResponse::error("boom"); "error")]
+    #[test_case(IpcResponse::State(StateSummary::new(&state_with_clients(2).client_set)); "state")]
+    #[test]
+    fn responses_round_trip(response: IpcResponse) {
+        round_trip(response);
+    }
+
+    #[test_case(IpcEvent::ClientManaged { client: Xid(1), tag: "1".into() }; "client managed")]
+    #[test_case(IpcEvent::ClientUnmanaged { client: Xid(1) }; "client unmanaged")]
+    #[test_case(IpcEvent::ScreensChanged { screens: screens() }; "screens changed")]
+    #[test_case(IpcEvent::ScreenFocused { screen: 1 }; "screen focused")]
+    #[test_case(IpcEvent::WorkspaceFocused { tag: "2".into() }; "workspace focused")]
+    #[test_case(IpcEvent::LayoutChanged { tag: "2".into(), layout: "Side".into() }; "layout changed")]
+    #[test_case(IpcEvent::ClientFocused { client: None }; "client focused")]
+    #[test]
+    fn events_round_trip(event: IpcEvent) {
+        round_trip(event);
+    }
+
+    #[test]
+    fn commands_use_the_documented_wire_format() {
+        let command: IpcCommand =
+            serde_json::from_str(r#"{"command":"focus_tag","tag":"3"}"#).unwrap();
+
+        assert_eq!(command, IpcCommand::FocusTag { tag: "3".into() });
+        assert_eq!(
+            serde_json::to_string(&IpcResponse::Success).unwrap(),
+            r#"{"status":"success"}"#
+        );
+    }
+
+    #[test]
+    fn state_summary_describes_screens_and_workspaces() {
+        let state = state_with_clients(2);
+        let summary = StateSummary::new(&state.client_set);
+
+        assert_eq!(summary.focused_tag, "1");
+        assert_eq!(summary.focused_client, Some(Xid(2)));
+        assert_eq!(summary.focused_screen, 0);
+
+        let summary_screens: Vec<(usize, &str, Rect)> = summary
+            .screens
+            .iter()
+            .map(|s| (s.index, s.tag.as_str(), s.r))
+            .collect();
+        assert_eq!(
+            summary_screens,
+            vec![(0, "1", screens()[0]), (1, "2", screens()[1])]
+        );
+
+        let ws = &summary.workspaces[0];
+        assert_eq!(ws.tag, "1");
+        assert_eq!(ws.clients, vec![Xid(2), Xid(1)]);
+        assert_eq!(ws.focused_client, Some(Xid(2)));
+        assert_eq!(ws.screen, Some(0));
+
+        let hidden: Vec<_> = summary
+            .workspaces
+            .iter()
+            .filter(|w| w.screen.is_none())
+            .collect();
+        assert_eq!(hidden.len(), summary.workspaces.len() - 2);
+        assert!(hidden
+            .iter()
+            .all(|w| w.clients.is_empty() && w.focused_client.is_none()));
+    }
+
+    #[test_case(IpcCommand::FocusTag { tag: "3".into() }, IpcResponse::Success, "3"; "focus tag")]
+    #[test_case(IpcCommand::FocusTag { tag: "unknown".into() }, IpcResponse::error("unknown tag: unknown"), "1"; "focus unknown tag")]
+    #[test_case(IpcCommand::MoveClient { tag: "3".into(), client: None }, IpcResponse::Success, "1"; "move focused client")]
+    #[test_case(IpcCommand::MoveClient { tag: "unknown".into(), client: None }, IpcResponse::error("unknown tag: unknown"), "1"; "move to unknown tag")]
+    #[test_case(IpcCommand::MoveClient { tag: "3".into(), client: Some(Xid(42)) }, IpcResponse::error("unknown client: 42"), "1"; "move unknown client")]
+    #[test_case(IpcCommand::SendLayoutMessage { message: LayoutMessage::Rotate }, IpcResponse::Success, "1"; "layout message")]
+    #[test_case(IpcCommand::Subscribe, IpcResponse::error("unable to subscribe"), "1"; "subscribe")]
+    #[test]
+    fn run_command_dispatches_commands(command: IpcCommand, expected: IpcResponse, tag: &str) {
+        let x = MockXConn::new(screens());
+        let mut state = state_with_clients(1);
+
+        let response = run_command(command, &mut state, &x).unwrap();
+
+        assert_eq!(response, expected);
+        assert_eq!(state.client_set.current_tag(), tag);
+    }
+
+    #[test]
+    fn run_command_moves_the_requested_client() {
+        let x = MockXConn::new(screens());
+        let mut state = state_with_clients(2);
+        let command = IpcCommand::MoveClient {
+            tag: "3".into(),
+            client: Some(Xid(1)),
+        };
+
+        let response = run_command(command, &mut state, &x).unwrap();
+
+        assert_eq!(response, IpcResponse::Success);
+        assert_eq!(state.client_set.tag_for_client(&Xid(1)), Some("3"));
+        assert_eq!(state.client_set.tag_for_client(&Xid(2)), Some("1"));
+    }
+
+    #[test]
+    fn run_command_returns_the_state_for_query_state() {
+        let x = MockXConn::new(screens());
+        let mut state = state_with_clients(2);
+        let expected = StateSummary::new(&state.client_set);
+
+        let response = run_command(IpcCommand::QueryState, &mut state, &x).unwrap();
+
+        assert_eq!(response, IpcResponse::State(expected));
+    }
+
+    #[test]
+    fn move_client_without_a_focused_client_is_an_error() {
+        let x = MockXConn::new(screens());
+        let mut state = state_with_clients(0);
+        let command = IpcCommand::MoveClient {
+            tag: "3".into(),
+            client: None,
+        };
+
+        let response = run_command(command, &mut state, &x).unwrap();
+
+        assert_eq!(
+            response,
+            IpcResponse::error("no client is currently focused")
+        );
+    }
+
+    #[test]
+    fn subscribers_that_fall_behind_are_dropped() {
+        let x = MockXConn::new(screens());
+        let mut state = state_with_clients(0);
+        let (slow_tx, _slow_rx) = mpsc::sync_channel(1);
+        let (closed_tx, closed_rx) = mpsc::sync_channel(SUBSCRIBER_BUFFER_SIZE);
+        let (tx, rx) = mpsc::sync_channel(SUBSCRIBER_BUFFER_SIZE);
+        drop(closed_rx);
+        let subscribers: Subscribers = Arc::new(Mutex::new(vec![slow_tx, closed_tx, tx]));
+
+        x.modify_and_refresh(&mut state, |cs| cs.insert(Xid(1)))
+            .unwrap();
+        publish_events(&subscribers, &state);
+
+        assert_eq!(subscribers.lock().unwrap().len(), 1);
+        let events: Vec<IpcEvent> = rx.try_iter().collect();
+        assert_eq!(events, IpcEvent::from_diff(&state.diff, &state.client_set));
+        assert!(events.len() > 1);
+    }
+}
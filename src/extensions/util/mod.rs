@@ -7,6 +7,8 @@ use crate::{
 
 pub mod debug;
 pub mod dmenu;
+#[cfg(feature = "serde")]
+pub mod ipc;
 
 /// Detect the current monitor set up and arrange the monitors if needed using [xrandr][1].
 ///
@@ -142,6 +142,13 @@ pub enum Error {
         prop: String,
     },
 
+    /// The output of `xmodmap -pke` was not in the expected format
+    #[error("unexpected output from xmodmap: '{line}'")]
+    InvalidXmodmapOutput {
+        /// The line that could not be parsed
+        line: String,
+    },
+
     /// Duplicate tags were provided for one or more workspaces
     #[error("The following tags have been used multiple times for different workspaces: {tags:?}")]
     NonUniqueTags {
@@ -157,10 +164,31 @@ pub enum Error {
     #[error(transparent)]
     ParseInt(#[from] std::num::ParseIntError),
 
+    /// Unable to serialize or deserialize persisted window manager state
+    #[cfg(feature = "serde")]
+    #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+
     /// There was a problem initialising randr
     #[error("Error initialising randr: {0}")]
     Randr(String),
 
+    /// The keyboard could not be actively grabbed (it is likely already grabbed by another
+    /// client)
+    #[error("Unable to grab the keyboard: {reason}")]
+    UnableToGrabKeyboard {
+        /// The status returned by the X server
+        reason: String,
+    },
+
+    /// The pointer could not be actively grabbed (it is likely already grabbed by another
+    /// client)
+    #[error("Unable to grab the pointer: {reason}")]
+    UnableToGrabPointer {
+        /// The status returned by the X server
+        reason: String,
+    },
+
     /// An operation was requested on a client window that is unknown
     #[error("The given client is not in this State")]
     UnknownClient,
@@ -9,6 +9,8 @@ where
 {
     pub screen: usize,
     pub tag: String,
+    pub layout: String,
+    pub r: Rect,
     pub clients: Vec<C>,
 }
 
@@ -20,6 +22,8 @@ where
         Self {
             screen: s.index,
             tag: s.workspace.tag.clone(),
+            layout: s.workspace.layout_name(),
+            r: s.r,
             clients: s.workspace.clients().copied().collect(),
         }
     }
@@ -46,6 +50,11 @@ where
         self.positions.iter().map(|(c, _)| c)
     }
 
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub(crate) fn screens(&self) -> impl Iterator<Item = &ScreenState<C>> {
+        once(&self.focused).chain(self.visible.iter())
+    }
+
     pub(crate) fn all_clients(&self) -> impl Iterator<Item = &C> {
         self.focused
             .clients
@@ -102,6 +111,38 @@ where
         }
     }
 
+    // The following are only used when publishing IPC events which requires the serde feature
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub fn newly_focused_tag(&self) -> Option<&str> {
+        if self.before.focused.tag != self.after.focused.tag {
+            Some(&self.after.focused.tag)
+        } else {
+            None
+        }
+    }
+
+    /// The (tag, layout name) of each workspace that was visible both before and after
+    /// and whose active layout has changed.
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub fn changed_layouts(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.after.screens().filter_map(|s| {
+            self.before
+                .screens()
+                .find(|prev| prev.tag == s.tag && prev.layout != s.layout)
+                .map(|_| (s.tag.as_ref(), s.layout.as_ref()))
+        })
+    }
+
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub fn screens_changed(&self) -> bool {
+        let mut before: Vec<_> = self.before.screens().map(|s| (s.screen, s.r)).collect();
+        let mut after: Vec<_> = self.after.screens().map(|s| (s.screen, s.r)).collect();
+        before.sort_by_key(|&(i, _)| i);
+        after.sort_by_key(|&(i, _)| i);
+
+        before != after
+    }
+
     pub fn new_clients(&self) -> impl Iterator<Item = &C> {
         let before: HashSet<_> = self.before.all_clients().collect();
 
@@ -154,6 +195,8 @@ where
             || self.new_clients().count() > 0
             || self.withdrawn_clients().count() > 0
             || self.previous_visible_tags() != self.current_visible_tags()
+            || self.changed_layouts().count() > 0
+            || self.screens_changed()
             || self.before.positions != self.after.positions
             || self.after.killed_clients.len() > 0)
     }
@@ -163,10 +206,13 @@ where
 mod tests {
     use super::*;
     use crate::{
+        builtin::layout::{MainAndStack, Monocle},
+        core::layout::Layout,
         pure::stack_set::tests::{test_stack_set, test_stack_set_with_stacks},
         stack, Xid,
     };
     use simple_test_case::test_case;
+    use std::collections::HashMap;
 
     #[test]
     fn diff_of_unchanged_stackset_is_empty() {
@@ -204,15 +250,63 @@ mod tests {
             2,
         );
 
-        let before = s.position_and_snapshot();
+        let before = s.position_and_snapshot(&HashMap::new());
         s.drag_workspace_forward();
-        let after = s.position_and_snapshot();
+        let after = s.position_and_snapshot(&HashMap::new());
 
         let diff = Diff::new(before, after);
 
         assert_eq!(diff.newly_focused_screen(), Some(1));
         assert_eq!(diff.focused_client(), Some(Xid(3)));
     }
+
+    #[test_case("1", None; "current tag")]
+    #[test_case("2", Some("2"); "visible tag")]
+    #[test_case("3", Some("3"); "hidden tag")]
+    #[test]
+    fn newly_focused_tag_works(tag: &str, expected: Option<&str>) {
+        let mut s = test_stack_set_with_stacks::<Xid>(vec![None, None, None], 2);
+        let before = s.position_and_snapshot(&HashMap::new());
+        s.focus_tag(tag);
+        let after = s.position_and_snapshot(&HashMap::new());
+
+        let diff = Diff::new(before, after);
+
+        assert_eq!(diff.newly_focused_tag(), expected);
+    }
+
+    #[test]
+    fn changing_layout_is_in_diff() {
+        let mut s = test_stack_set_with_stacks::<Xid>(vec![None, None, None], 2);
+        s.screens.focus.workspace.layouts = stack!(
+            Box::new(MainAndStack::default()) as Box<dyn Layout>,
+            Monocle::boxed()
+        );
+
+        let before = s.position_and_snapshot(&HashMap::new());
+        s.next_layout();
+        let after = s.position_and_snapshot(&HashMap::new());
+
+        let diff = Diff::new(before, after);
+        let changed: Vec<_> = diff.changed_layouts().collect();
+
+        assert_eq!(changed, vec![("1", "Mono")]);
+    }
+
+    #[test_case(vec![Rect::new(0, 0, 1000, 2000), Rect::new(1000, 2000, 1000, 2000)], false; "unchanged")]
+    #[test_case(vec![Rect::new(0, 0, 1000, 2000)], true; "screen removed")]
+    #[test_case(vec![Rect::new(0, 0, 500, 500), Rect::new(1000, 2000, 1000, 2000)], true; "screen resized")]
+    #[test]
+    fn screens_changed_works(rects: Vec<Rect>, expected: bool) {
+        let mut s = test_stack_set_with_stacks::<Xid>(vec![None, None, None], 2);
+        let before = s.position_and_snapshot(&HashMap::new());
+        s.update_screens(rects).unwrap();
+        let after = s.position_and_snapshot(&HashMap::new());
+
+        let diff = Diff::new(before, after);
+
+        assert_eq!(diff.screens_changed(), expected);
+    }
 }
 
 #[cfg(test)]
@@ -220,10 +314,11 @@ mod quickcheck_tests {
     use super::*;
     use crate::{pure::StackSet, Xid};
     use quickcheck_macros::quickcheck;
+    use std::collections::HashMap;
 
     #[quickcheck]
     fn diff_of_unchanged_stackset_is_empty(mut s: StackSet<Xid>) -> bool {
-        let ss = s.position_and_snapshot();
+        let ss = s.position_and_snapshot(&HashMap::new());
         let diff = Diff::new(ss.clone(), ss);
 
         diff.is_empty()
@@ -231,12 +326,12 @@ mod quickcheck_tests {
 
     #[quickcheck]
     fn adding_a_client_is_new_in_diff(mut s: StackSet<Xid>) -> bool {
-        let ss = s.position_and_snapshot();
+        let ss = s.position_and_snapshot(&HashMap::new());
         let new = s.minimal_unknown_client();
 
         s.insert(new);
 
-        let diff = Diff::new(ss, s.position_and_snapshot());
+        let diff = Diff::new(ss, s.position_and_snapshot(&HashMap::new()));
         let res = diff.new_clients().any(|&c| c == new);
 
         res
@@ -257,11 +352,11 @@ mod quickcheck_tests {
             None => vec![],
         };
 
-        let ss = s.position_and_snapshot();
+        let ss = s.position_and_snapshot(&HashMap::new());
 
         s.focus_tag(&tag);
 
-        let diff = Diff::new(ss, s.position_and_snapshot());
+        let diff = Diff::new(ss, s.position_and_snapshot(&HashMap::new()));
         let hidden: HashSet<_> = diff.hidden_clients().collect();
 
         let focused_clients_now_hidden = clients_on_active.iter().all(|c| hidden.contains(c));
@@ -277,10 +372,10 @@ mod quickcheck_tests {
             None => return true, // nothing to remove
         };
 
-        let ss = s.position_and_snapshot();
+        let ss = s.position_and_snapshot(&HashMap::new());
         s.remove_focused();
 
-        let diff = Diff::new(ss, s.position_and_snapshot());
+        let diff = Diff::new(ss, s.position_and_snapshot(&HashMap::new()));
         let res = diff.withdrawn_clients().any(|&c| c == focus)
             && diff.hidden_clients().any(|&c| c == focus);
 
@@ -296,10 +391,10 @@ mod quickcheck_tests {
             None => return true, // nothing to remove
         };
 
-        let ss = s.position_and_snapshot();
+        let ss = s.position_and_snapshot(&HashMap::new());
         s.kill_focused();
 
-        let diff = Diff::new(ss, s.position_and_snapshot());
+        let diff = Diff::new(ss, s.position_and_snapshot(&HashMap::new()));
         let res = diff.withdrawn_clients().any(|&c| c == focus)
             && diff.hidden_clients().any(|&c| c == focus)
             && diff.killed_clients().any(|&c| c == focus);
@@ -314,11 +409,11 @@ mod quickcheck_tests {
 
         match (client, tag) {
             (Some(client), Some(tag)) => {
-                let ss = s.position_and_snapshot();
+                let ss = s.position_and_snapshot(&HashMap::new());
 
                 s.move_client_to_tag(&client, &tag);
 
-                let diff = Diff::new(ss, s.position_and_snapshot());
+                let diff = Diff::new(ss, s.position_and_snapshot(&HashMap::new()));
                 let res = diff.hidden_clients().any(|&c| c == client);
 
                 res
@@ -331,6 +331,180 @@ impl Rect {
             ))
         }
     }
+
+    /// Move this [Rect] so that its edges line up with the edges of any of the `targets` that
+    /// are within `threshold` pixels. Horizontal and vertical edges are snapped independently
+    /// with the closest target edge being used in each direction.
+    pub fn snap_position(&self, targets: &[Rect], threshold: u32) -> Self {
+        let (xs, ys) = target_edges(targets);
+        let dx = snap_offset(&[self.x, self.x + self.w], &xs, threshold);
+        let dy = snap_offset(&[self.y, self.y + self.h], &ys, threshold);
+
+        Self {
+            x: max(0, self.x as i64 + dx) as u32,
+            y: max(0, self.y as i64 + dy) as u32,
+            ..*self
+        }
+    }
+
+    /// Resize this [Rect] so that its right and bottom edges line up with the edges of any of
+    /// the `targets` that are within `threshold` pixels, maintaining the current x and y
+    /// coordinates.
+    pub fn snap_size(&self, targets: &[Rect], threshold: u32) -> Self {
+        let (xs, ys) = target_edges(targets);
+        let dw = snap_offset(&[self.x + self.w], &xs, threshold);
+        let dh = snap_offset(&[self.y + self.h], &ys, threshold);
+
+        Self {
+            w: max(1, self.w as i64 + dw) as u32,
+            h: max(1, self.h as i64 + dh) as u32,
+            ..*self
+        }
+    }
+}
+
+/// Space reserved along each edge of a [Rect], such as the space taken up by a dock or
+/// status bar.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Reserved {
+    /// Pixels reserved along the left edge
+    pub left: u32,
+    /// Pixels reserved along the right edge
+    pub right: u32,
+    /// Pixels reserved along the top edge
+    pub top: u32,
+    /// Pixels reserved along the bottom edge
+    pub bottom: u32,
+}
+
+impl Reserved {
+    /// Combine with another [Reserved], keeping the larger reservation for each edge.
+    pub fn max(self, other: Self) -> Self {
+        Self {
+            left: max(self.left, other.left),
+            right: max(self.right, other.right),
+            top: max(self.top, other.top),
+            bottom: max(self.bottom, other.bottom),
+        }
+    }
+
+    /// Shrink the given [Rect] to remove the space reserved along each of its edges.
+    pub fn applied_to(&self, r: &Rect) -> Rect {
+        Rect {
+            x: r.x + self.left,
+            y: r.y + self.top,
+            w: r.w.saturating_sub(self.left + self.right),
+            h: r.h.saturating_sub(self.top + self.bottom),
+        }
+    }
+}
+
+/// Space along the edges of the root window that has been reserved by a client such as a
+/// dock or status bar.
+///
+/// See the EWMH [spec][1] for `_NET_WM_STRUT_PARTIAL` for details of how struts are
+/// specified.
+///
+/// [1]: https://specifications.freedesktop.org/wm-spec/wm-spec-latest.html#idm45381391268672
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Strut {
+    pub(crate) left: u32,
+    pub(crate) right: u32,
+    pub(crate) top: u32,
+    pub(crate) bottom: u32,
+    pub(crate) left_y: (u32, u32),
+    pub(crate) right_y: (u32, u32),
+    pub(crate) top_x: (u32, u32),
+    pub(crate) bottom_x: (u32, u32),
+}
+
+impl Strut {
+    /// Try to construct a [Strut] from the raw values of either a `_NET_WM_STRUT_PARTIAL`
+    /// (12 values) or a `_NET_WM_STRUT` (4 values) property.
+    ///
+    /// A `_NET_WM_STRUT` reserves space along the full length of each edge.
+    pub fn try_from_cardinals(raw: &[u32]) -> Option<Self> {
+        let full = (0, u32::MAX);
+        let (ly, ry, tx, bx) = match raw.len() {
+            12 => (
+                (raw[4], raw[5]),
+                (raw[6], raw[7]),
+                (raw[8], raw[9]),
+                (raw[10], raw[11]),
+            ),
+            4 => (full, full, full, full),
+            _ => return None,
+        };
+
+        Some(Self {
+            left: raw[0],
+            right: raw[1],
+            top: raw[2],
+            bottom: raw[3],
+            left_y: ly,
+            right_y: ry,
+            top_x: tx,
+            bottom_x: bx,
+        })
+    }
+
+    /// The space reserved along each edge of `screen` by this strut when the root window
+    /// has the geometry given by `root`.
+    pub fn reserved_on(&self, screen: &Rect, root: &Rect) -> Reserved {
+        // Start and end coordinates are inclusive
+        let overlaps =
+            |(start, end): (u32, u32), pos: u32, len: u32| start < pos + len && end >= pos;
+        let (right_edge, bottom_edge) = (screen.x + screen.w, screen.y + screen.h);
+
+        let mut reserved = Reserved::default();
+
+        if overlaps(self.left_y, screen.y, screen.h) {
+            reserved.left = (root.x + self.left).saturating_sub(screen.x);
+        }
+        if overlaps(self.right_y, screen.y, screen.h) {
+            let start = (root.x + root.w).saturating_sub(self.right);
+            reserved.right = right_edge.saturating_sub(start);
+        }
+        if overlaps(self.top_x, screen.x, screen.w) {
+            reserved.top = (root.y + self.top).saturating_sub(screen.y);
+        }
+        if overlaps(self.bottom_x, screen.x, screen.w) {
+            let start = (root.y + root.h).saturating_sub(self.bottom);
+            reserved.bottom = bottom_edge.saturating_sub(start);
+        }
+
+        Reserved {
+            left: reserved.left.min(screen.w),
+            right: reserved.right.min(screen.w),
+            top: reserved.top.min(screen.h),
+            bottom: reserved.bottom.min(screen.h),
+        }
+    }
+}
+
+// The vertical and horizontal edges of each target as x and y coordinates respectively
+fn target_edges(targets: &[Rect]) -> (Vec<u32>, Vec<u32>) {
+    targets
+        .iter()
+        .map(|r| ([r.x, r.x + r.w], [r.y, r.y + r.h]))
+        .fold((vec![], vec![]), |(mut xs, mut ys), (x, y)| {
+            xs.extend(x);
+            ys.extend(y);
+            (xs, ys)
+        })
+}
+
+// The smallest offset that would align one of the given edges with a target edge, or 0 if
+// there are no target edges within the threshold.
+fn snap_offset(edges: &[u32], targets: &[u32], threshold: u32) -> i64 {
+    edges
+        .iter()
+        .flat_map(|&e| targets.iter().map(move |&t| t as i64 - e as i64))
+        .filter(|d| d.unsigned_abs() <= threshold as u64)
+        .min_by_key(|d| d.unsigned_abs())
+        .unwrap_or(0)
 }
 
 #[cfg(test)]
@@ -521,4 +695,84 @@ mod tests {
 
         assert_eq!(res, rr(0.005, 0.0, 0.8, 0.8));
     }
+
+    #[test_case(Rect::new(13, 20, 30, 30), Rect::new(10, 20, 30, 30); "left edge to left edge")]
+    #[test_case(Rect::new(62, 20, 30, 30), Rect::new(60, 20, 30, 30); "left edge to right edge")]
+    #[test_case(Rect::new(80, 97, 30, 30), Rect::new(80, 100, 30, 30); "bottom edge to bottom edge")]
+    #[test_case(Rect::new(80, 30, 30, 20), Rect::new(80, 30, 30, 20); "nothing in range")]
+    #[test_case(Rect::new(14, 63, 30, 30), Rect::new(10, 60, 30, 30); "both axes")]
+    #[test]
+    fn snap_position(r: Rect, expected: Rect) {
+        let targets = [Rect::new(0, 0, 200, 130), Rect::new(10, 10, 50, 50)];
+
+        assert_eq!(r.snap_position(&targets, 5), expected);
+    }
+
+    #[test_case(Rect::new(20, 20, 37, 20), Rect::new(20, 20, 40, 20); "right edge")]
+    #[test_case(Rect::new(20, 20, 20, 43), Rect::new(20, 20, 20, 40); "bottom edge")]
+    #[test_case(Rect::new(20, 20, 10, 10), Rect::new(20, 20, 10, 10); "nothing in range")]
+    #[test]
+    fn snap_size(r: Rect, expected: Rect) {
+        let targets = [Rect::new(10, 10, 50, 50)];
+
+        assert_eq!(r.snap_size(&targets, 5), expected);
+    }
+
+    fn reserved(left: u32, right: u32, top: u32, bottom: u32) -> Reserved {
+        Reserved {
+            left,
+            right,
+            top,
+            bottom,
+        }
+    }
+
+    // Two side by side screens where the right hand screen is shorter than the left
+    const ROOT: Rect = Rect {
+        x: 0,
+        y: 0,
+        w: 3000,
+        h: 1200,
+    };
+    const LEFT: Rect = Rect {
+        x: 0,
+        y: 0,
+        w: 1800,
+        h: 1200,
+    };
+    const RIGHT: Rect = Rect {
+        x: 1800,
+        y: 0,
+        w: 1200,
+        h: 1000,
+    };
+
+    #[test_case(&[0, 0, 20, 0], LEFT, reserved(0, 0, 20, 0); "full top on left")]
+    #[test_case(&[0, 0, 20, 0], RIGHT, reserved(0, 0, 20, 0); "full top on right")]
+    #[test_case(&[30, 0, 0, 0], LEFT, reserved(30, 0, 0, 0); "full left on left")]
+    #[test_case(&[30, 0, 0, 0], RIGHT, reserved(0, 0, 0, 0); "full left on right")]
+    #[test_case(&[0, 40, 0, 0], RIGHT, reserved(0, 40, 0, 0); "full right on right")]
+    #[test_case(&[0, 0, 0, 25], LEFT, reserved(0, 0, 0, 25); "full bottom on left")]
+    #[test_case(&[0, 0, 0, 25], RIGHT, reserved(0, 0, 0, 0); "full bottom on short right")]
+    #[test_case(&[0, 0, 0, 225, 0, 0, 0, 0, 0, 0, 1800, 2999], RIGHT, reserved(0, 0, 0, 25); "partial bottom on short right")]
+    #[test_case(&[0, 0, 0, 225, 0, 0, 0, 0, 0, 0, 1800, 2999], LEFT, reserved(0, 0, 0, 0); "partial bottom on left")]
+    #[test_case(&[0, 0, 20, 0, 0, 0, 0, 0, 0, 1799, 0, 0], RIGHT, reserved(0, 0, 0, 0); "partial top on left only")]
+    #[test]
+    fn strut_reserved_on(raw: &[u32], screen: Rect, expected: Reserved) {
+        let strut = Strut::try_from_cardinals(raw).unwrap();
+
+        assert_eq!(strut.reserved_on(&screen, &ROOT), expected);
+    }
+
+    #[test]
+    fn strut_requires_4_or_12_values() {
+        assert!(Strut::try_from_cardinals(&[1, 2, 3]).is_none());
+    }
+
+    #[test]
+    fn reserved_applied_to() {
+        let res = reserved(10, 20, 30, 40).applied_to(&Rect::new(100, 100, 200, 200));
+
+        assert_eq!(res, Rect::new(110, 130, 170, 130));
+    }
 }
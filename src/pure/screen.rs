@@ -1,14 +1,22 @@
-use crate::pure::{geometry::Rect, Workspace};
+use crate::pure::{
+    geometry::{Rect, Reserved},
+    Workspace,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// A wrapper around a single [Workspace] that includes the physical screen
 /// size as a [Rect].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Default, Debug, Clone)]
 pub struct Screen<C> {
     pub(crate) index: usize,
     /// The [Workspace] current visible on this screen
     pub workspace: Workspace<C>,
     pub(crate) r: Rect,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) reserved: Reserved,
 }
 
 impl<C: fmt::Display> fmt::Display for Screen<C> {
@@ -34,4 +42,15 @@ impl<C> Screen<C> {
     pub fn geometry(&self) -> Rect {
         self.r
     }
+
+    /// The space reserved along the edges of this [Screen] by docks and status bars.
+    pub fn reserved(&self) -> Reserved {
+        self.reserved
+    }
+
+    /// The area of this [Screen] that is available for laying out clients once any reserved
+    /// space has been removed.
+    pub fn usable_geometry(&self) -> Rect {
+        self.reserved.applied_to(&self.r)
+    }
 }
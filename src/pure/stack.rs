@@ -1,4 +1,6 @@
 use crate::pop_where;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::{
     collections::linked_list::{self, LinkedList},
     fmt,
@@ -26,6 +28,7 @@ macro_rules! stack {
 }
 
 /// A position within a [Stack].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Position {
     /// The current focus point
@@ -55,6 +58,7 @@ impl Default for Position {
 /// This is a [zipper](https://en.wikipedia.org/wiki/Zipper_(data_structure))
 /// over a [LinkedList]. Many of the methods that mutate the structure of the Stack
 /// return back a mutable reference so that they are able to be chained.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Stack<T> {
     pub(crate) up: LinkedList<T>,
@@ -1,22 +1,26 @@
 use crate::{
-    core::layout::{Layout, LayoutStack},
+    core::layout::{ClientInfo, Layout, LayoutStack},
     pop_where,
     pure::{
         diff::{ScreenState, Snapshot},
-        geometry::{Rect, RelativeRect, RelativeTo},
+        geometry::{Rect, RelativeRect, RelativeTo, Reserved, Strut},
         workspace::check_workspace_invariants,
         Position, Screen, Stack, Workspace,
     },
     stack, Error, Result, Xid,
 };
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::{
     cmp::Ordering,
     collections::{HashMap, LinkedList},
     hash::Hash,
     mem::{swap, take},
 };
+use tracing::warn;
 
 /// The side-effect free internal state representation of the window manager.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Default, Debug, Clone)]
 pub struct StackSet<C>
 where
@@ -27,6 +31,7 @@ where
     pub(crate) floating: HashMap<C, RelativeRect>, // Floating windows
     pub(crate) previous_tag: String,      // The last tag to be focused before the current one
     pub(crate) invisible_tags: Vec<String>, // Tags that should never be focused
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub(crate) killed_clients: Vec<C>, // clients that have been removed and need processing on the X side
 }
 
@@ -82,6 +87,7 @@ where
                     workspace,
                     index,
                     r,
+                    reserved: Reserved::default(),
                 },
             ));
 
@@ -271,16 +277,26 @@ where
     }
 
     pub(crate) fn float_unchecked<R: RelativeTo>(&mut self, client: C, r: R) {
-        let r = r.relative_to(&self.screens.focus.r);
+        let r = r.relative_to(&self.screen_rect_for_client(&client));
         self.floating.insert(client, r);
     }
 
     /// Clear the floating status of a client, returning its previous preferred
     /// screen position if the client was known, otherwise `None`.
     pub fn sink(&mut self, client: &C) -> Option<Rect> {
-        self.floating
-            .remove(client)
-            .map(|rr| rr.applied_to(&self.screens.focus.r))
+        let r = self.screen_rect_for_client(client);
+
+        self.floating.remove(client).map(|rr| rr.applied_to(&r))
+    }
+
+    // Floating positions are stored relative to the screen showing the client so that they
+    // are unaffected by which screen currently has focus. Clients that are not on a visible
+    // workspace are treated as being on the focused screen.
+    fn screen_rect_for_client(&self, client: &C) -> Rect {
+        self.screens()
+            .find(|s| s.workspace.contains(client))
+            .unwrap_or(&self.screens.focus)
+            .r
     }
 
     /// Check whether a given tag currently has any floating windows present.
@@ -368,6 +384,27 @@ where
         self.move_client_to_tag(client, self.screens.focus.workspace.tag.clone());
     }
 
+    /// Swap the positions of two clients, which may be on different [Workspace]s.
+    ///
+    /// The focused position of each workspace is unchanged so if either client was focused
+    /// then the other client will now be focused in its place. If either client is unknown
+    /// then this is a no-op.
+    pub fn swap_clients(&mut self, a: &C, b: &C) {
+        if a == b || !self.contains(a) || !self.contains(b) {
+            return;
+        }
+
+        for s in self.workspaces_mut().flat_map(|w| w.stack.iter_mut()) {
+            for c in s.iter_mut() {
+                if c == a {
+                    *c = b.clone();
+                } else if c == b {
+                    *c = a.clone();
+                }
+            }
+        }
+    }
+
     /// Insert a client as the current focus for the given tag.
     ///
     /// NOTE: This will silently fail if the tag is not in the StackSet which
@@ -699,13 +736,16 @@ impl StackSet<Xid> {
     /// are placed above stacked clients, clients per workspace are stacked in the order they are returned
     /// from the layout.
     /// NOTE: we require Xid as the client type here as we need that when running layouts
-    pub(crate) fn visible_client_positions(&mut self) -> Vec<(Xid, Rect)> {
+    ///
+    /// If a layout is unable to make use of the provided [ClientInfo] then we fall back to running
+    /// it without access to the client properties.
+    pub(crate) fn visible_client_positions(&mut self, info: &dyn ClientInfo) -> Vec<(Xid, Rect)> {
         let mut float_positions: Vec<(Xid, Rect)> = self
             .on_screen_workspace_clients()
             .flat_map(|c| {
                 self.floating
                     .get(c)
-                    .map(|rr| (*c, rr.applied_to(&self.screens.focus.r)))
+                    .map(|rr| (*c, rr.applied_to(&self.screen_rect_for_client(c))))
             })
             .collect();
 
@@ -714,15 +754,19 @@ impl StackSet<Xid> {
         let mut positions: Vec<(Xid, Rect)> = Vec::new();
 
         for s in self.screens.iter_mut() {
-            let r = s.geometry();
+            let r = s.usable_geometry();
             let tag = &s.workspace.tag;
             let true_stack = s.workspace.stack.as_ref();
             let tiling =
                 true_stack.and_then(|st| st.from_filtered(|c| self.floating.get(c).is_none()));
 
-            // TODO: if this supports using X state for determining layout position in future then this
-            //       will be fallible and needs to fall back to a default layout.
-            let (_, stack_positions) = s.workspace.layouts.layout_workspace(tag, &tiling, r);
+            let layouts = &mut s.workspace.layouts;
+            let (_, stack_positions) = layouts
+                .layout_workspace_with_info(tag, &tiling, r, info)
+                .unwrap_or_else(|e| {
+                    warn!(%e, %tag, "unable to run layout with client info: falling back");
+                    layouts.layout_workspace(tag, &tiling, r)
+                });
 
             positions.extend(stack_positions.into_iter().rev());
         }
@@ -732,8 +776,27 @@ impl StackSet<Xid> {
         positions
     }
 
-    pub(crate) fn position_and_snapshot(&mut self) -> Snapshot<Xid> {
-        let positions = self.visible_client_positions();
+    /// Update the space reserved on each screen based on the given struts. The root window
+    /// is assumed to be the smallest [Rect] containing all screens.
+    pub(crate) fn set_struts<'a, I>(&mut self, struts: I)
+    where
+        I: IntoIterator<Item = &'a Strut>,
+    {
+        let struts: Vec<&Strut> = struts.into_iter().collect();
+        let (w, h) = self.screens.iter().fold((0, 0), |(w, h), s| {
+            (w.max(s.r.x + s.r.w), h.max(s.r.y + s.r.h))
+        });
+        let root = Rect::new(0, 0, w, h);
+
+        for s in self.screens.iter_mut() {
+            s.reserved = struts.iter().fold(Reserved::default(), |acc, strut| {
+                acc.max(strut.reserved_on(&s.r, &root))
+            });
+        }
+    }
+
+    pub(crate) fn position_and_snapshot(&mut self, info: &dyn ClientInfo) -> Snapshot<Xid> {
+        let positions = self.visible_client_positions(info);
         self.snapshot(positions)
     }
 
@@ -760,6 +823,7 @@ impl StackSet<Xid> {
                             workspace: w,
                             index: n_old + n,
                             r: Rect::default(),
+                            reserved: Reserved::default(),
                         },
                     );
                 }
@@ -783,6 +847,67 @@ impl StackSet<Xid> {
         Ok(())
     }
 
+    /// Replace this StackSet with one that was previously persisted, keeping the current
+    /// screen dimensions and the [LayoutStack] for each workspace.
+    ///
+    /// Any clients that are not contained in `clients` are dropped from the restored state
+    /// and the active layout for each workspace is set from `layout_names` (a map of
+    /// workspace tag to layout name). Workspaces that are present in this StackSet but not in
+    /// the restored one are added as hidden workspaces.
+    #[cfg(feature = "serde")]
+    pub(crate) fn restore(
+        &mut self,
+        mut restored: Self,
+        layout_names: &HashMap<String, String>,
+        clients: &[Xid],
+    ) -> Result<()> {
+        let stale: Vec<Xid> = restored
+            .clients()
+            .filter(|c| !clients.contains(c))
+            .copied()
+            .collect();
+
+        for c in stale.iter() {
+            restored.remove_client(c);
+        }
+        restored.floating.retain(|c, _| clients.contains(c));
+
+        let rects: Vec<Rect> = self.screens.iter().map(|s| s.r).collect();
+        let mut current: Vec<(usize, String)> =
+            self.workspaces().map(|w| (w.id, w.tag.clone())).collect();
+        current.sort();
+
+        let mut layouts: HashMap<String, LayoutStack> = self
+            .workspaces_mut()
+            .map(|w| (w.tag.clone(), take(&mut w.layouts)))
+            .collect();
+
+        for w in restored.workspaces_mut() {
+            if let Some(ls) = layouts.remove(&w.tag) {
+                w.layouts = ls;
+            }
+
+            if let Some(name) = layout_names.get(&w.tag) {
+                w.layouts.focus_layout_named(name);
+            }
+        }
+
+        for (_, tag) in current {
+            if let Some(ls) = layouts.remove(&tag) {
+                if self.invisible_tags.contains(&tag) {
+                    restored.add_invisible_workspace(tag)?;
+                } else {
+                    restored.add_workspace(tag, ls)?;
+                }
+            }
+        }
+
+        restored.update_screens(rects)?;
+        *self = restored;
+
+        Ok(())
+    }
+
     // This is a little fiddly...
     // Rather than hard erroring if we end up with new screens being detected that
     // push us over the number of available workspaces, we pad the workspace set
@@ -909,6 +1034,7 @@ defer_to_current_stack!(
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use crate::builtin::layout::Monocle;
     use simple_test_case::test_case;
 
     fn _test_stack_set<C>(n_tags: usize, n_screens: usize) -> StackSet<C>
@@ -1218,11 +1344,26 @@ pub mod tests {
         assert_eq!(s.workspace(tag).unwrap().focus(), Some(&client));
     }
 
+    #[test_case(1, 3, vec![Some(stack!([0], 3, [2, 1])), Some(stack!(4))]; "same workspace")]
+    #[test_case(2, 4, vec![Some(stack!([0], 1, [4, 3])), Some(stack!(2))]; "different workspaces")]
+    #[test_case(1, 9, vec![Some(stack!([0], 1, [2, 3])), Some(stack!(4))]; "unknown client")]
+    #[test_case(1, 1, vec![Some(stack!([0], 1, [2, 3])), Some(stack!(4))]; "same client")]
+    #[test]
+    fn swap_clients(a: u8, b: u8, expected: Vec<Option<Stack<u8>>>) {
+        let mut s =
+            test_stack_set_with_stacks(vec![Some(stack!([0], 1, [2, 3])), Some(stack!(4))], 1);
+
+        s.swap_clients(&a, &b);
+        let stacks: Vec<_> = s.workspaces().map(|w| w.stack.clone()).collect();
+
+        assert_eq!(stacks, expected);
+    }
+
     mod visible_client_positions {
         use super::*;
 
         fn stack_order(s: &mut StackSet<Xid>) -> Vec<u32> {
-            let positions = s.visible_client_positions();
+            let positions = s.visible_client_positions(&HashMap::new());
             positions.iter().map(|&(id, _)| *id).collect()
         }
 
@@ -1237,11 +1378,26 @@ pub mod tests {
             let r = Rect::new(50, 50, 50, 50);
             s.float_unchecked(Xid(1), r);
 
-            let positions = s.visible_client_positions();
+            let positions = s.visible_client_positions(&HashMap::new());
 
             assert!(positions.contains(&(Xid(1), r)), "{positions:?}")
         }
 
+        #[test]
+        fn floating_positions_are_relative_to_the_client_screen() {
+            let mut s = test_xid_stack_set(5, 2);
+            s.insert(Xid(1));
+
+            let r = Rect::new(50, 50, 50, 50);
+            s.float_unchecked(Xid(1), r);
+            s.focus_screen(1);
+
+            let positions = s.visible_client_positions(&HashMap::new());
+
+            assert_eq!(positions, vec![(Xid(1), r)]);
+            assert_eq!(s.sink(&Xid(1)), Some(r));
+        }
+
         #[test]
         fn floating_windows_are_returned_last() {
             let mut s = test_xid_stack_set(5, 2);
@@ -1358,6 +1514,114 @@ pub mod tests {
 
         assert!(matches!(res, Err(Error::NoScreens)));
     }
+
+    #[test]
+    fn set_struts_shrinks_the_space_used_for_layouts() {
+        let screens = vec![Rect::new(0, 0, 100, 50), Rect::new(100, 0, 100, 50)];
+        let mut ss: StackSet<Xid> =
+            StackSet::try_new(stack!(Monocle::boxed()), ["1", "2"], screens).unwrap();
+        ss.insert(Xid(1));
+
+        // A 10px top strut on the first screen only
+        let strut = Strut::try_from_cardinals(&[0, 0, 10, 0, 0, 0, 0, 0, 0, 99, 0, 0]).unwrap();
+        ss.set_struts([&strut]);
+
+        let reserved: Vec<u32> = ss.screens().map(|s| s.reserved().top).collect();
+        assert_eq!(reserved, vec![10, 0]);
+        assert_eq!(
+            ss.visible_client_positions(&HashMap::new()),
+            vec![(Xid(1), Rect::new(0, 10, 100, 40))]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    mod restore {
+        use super::*;
+        use crate::builtin::layout::{MainAndStack, Monocle};
+
+        fn layouts() -> LayoutStack {
+            stack!(Box::new(MainAndStack::default()), Monocle::boxed())
+        }
+
+        fn persisted_and_fresh() -> (StackSet<Xid>, StackSet<Xid>) {
+            let mut persisted: StackSet<Xid> =
+                StackSet::try_new(layouts(), ["1", "2", "3"], vec![Rect::new(0, 0, 100, 50)])
+                    .unwrap();
+
+            persisted.insert(Xid(1));
+            persisted.insert(Xid(2));
+            persisted.move_client_to_tag(&Xid(2), "2");
+            persisted.insert(Xid(3));
+            persisted.float_unchecked(Xid(3), Rect::new(10, 10, 20, 20));
+            persisted.focus_tag("2");
+
+            let json = serde_json::to_string(&persisted).unwrap();
+            let persisted = serde_json::from_str(&json).unwrap();
+
+            let fresh = StackSet::try_new(
+                layouts(),
+                ["1", "2", "3", "4"],
+                vec![Rect::new(0, 0, 200, 100)],
+            )
+            .unwrap();
+
+            (persisted, fresh)
+        }
+
+        #[test]
+        fn restored_state_matches_persisted() {
+            let (persisted, mut ss) = persisted_and_fresh();
+            let clients = [Xid(1), Xid(2), Xid(3)];
+
+            ss.restore(persisted, &HashMap::new(), &clients).unwrap();
+
+            assert_eq!(ss.current_tag(), "2");
+            assert_eq!(ss.current_client(), Some(&Xid(2)));
+            assert_eq!(ss.tag_for_client(&Xid(1)), Some("1"));
+            assert_eq!(ss.tag_for_client(&Xid(3)), Some("1"));
+            assert!(ss.floating.contains_key(&Xid(3)));
+        }
+
+        #[test]
+        fn current_screen_details_are_kept() {
+            let (persisted, mut ss) = persisted_and_fresh();
+
+            ss.restore(persisted, &HashMap::new(), &[]).unwrap();
+
+            assert_eq!(ss.screens.focus.r, Rect::new(0, 0, 200, 100));
+        }
+
+        #[test]
+        fn missing_clients_are_dropped() {
+            let (persisted, mut ss) = persisted_and_fresh();
+
+            ss.restore(persisted, &HashMap::new(), &[Xid(1)]).unwrap();
+
+            let clients: Vec<Xid> = ss.clients().copied().collect();
+            assert_eq!(clients, vec![Xid(1)]);
+            assert!(ss.floating.is_empty());
+        }
+
+        #[test]
+        fn new_workspaces_are_added() {
+            let (persisted, mut ss) = persisted_and_fresh();
+
+            ss.restore(persisted, &HashMap::new(), &[]).unwrap();
+
+            assert_eq!(ss.ordered_tags(), vec!["1", "2", "3", "4"]);
+        }
+
+        #[test]
+        fn active_layouts_are_restored_by_name() {
+            let (persisted, mut ss) = persisted_and_fresh();
+            let layout_names = [("2".to_owned(), "Mono".to_owned())].into_iter().collect();
+
+            ss.restore(persisted, &layout_names, &[]).unwrap();
+
+            assert_eq!(ss.workspace("1").unwrap().layout_name(), "Side");
+            assert_eq!(ss.workspace("2").unwrap().layout_name(), "Mono");
+        }
+    }
 }
 
 #[cfg(test)]
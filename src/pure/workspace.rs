@@ -3,15 +3,22 @@ use crate::{
     pure::Stack,
     Error, Result,
 };
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// A wrapper around a [Stack] of windows belonging to a single "workspace" or virtual
 /// desktop. When this workspace is active on a given screen, the windows contained in
 /// its stack will be positioned using the active layout of its [LayoutStack].
+///
+/// When serialized, the [LayoutStack] is skipped as [Layout][crate::core::layout::Layout]s
+/// are trait objects: deserialized workspaces will use the default LayoutStack.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone)]
 pub struct Workspace<T> {
     pub(crate) id: usize,
     pub(crate) tag: String,
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub(crate) layouts: LayoutStack,
     pub(crate) stack: Option<Stack<T>>,
 }
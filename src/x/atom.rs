@@ -101,6 +101,9 @@ pub enum Atom {
     /// _NET_WM_STRUT
     #[strum(serialize = "_NET_WM_STRUT")]
     NetWmStrut,
+    /// _NET_WM_STRUT_PARTIAL
+    #[strum(serialize = "_NET_WM_STRUT_PARTIAL")]
+    NetWmStrutPartial,
     /// _NET_WM_STATE_DEMANDS_ATTENTION
     #[strum(serialize = "_NET_WM_STATE_DEMANDS_ATTENTION")]
     NetWmStateDemandsAttention,
@@ -33,6 +33,8 @@ pub enum XEvent {
     Destroy(Xid),
     /// A grabbed key combination has been entered by the user
     KeyPress(KeyCode),
+    /// A grabbed key has been released by the user (or any key while the keyboard is grabbed)
+    KeyRelease(KeyCode),
     /// The mouse pointer has left the current client window
     Leave(PointerChange),
     /// Keybindings have changed
@@ -64,6 +66,7 @@ impl std::fmt::Display for XEvent {
             FocusIn(_) => write!(f, "FocusIn"),
             Destroy(_) => write!(f, "Destroy"),
             KeyPress(_) => write!(f, "KeyPress"),
+            KeyRelease(_) => write!(f, "KeyRelease"),
             Leave(_) => write!(f, "Leave"),
             MappingNotify => write!(f, "MappingNotify"),
             MapRequest(_) => write!(f, "MapRequest"),
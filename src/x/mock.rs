@@ -0,0 +1,246 @@
+//! A mock [XConn] implementation for testing logic that needs to talk to the X server.
+use crate::{
+    core::{
+        bindings::{KeyBindings, KeyCode, MouseState},
+        Config, WindowManager,
+    },
+    pure::geometry::{Point, Rect},
+    x::{
+        event::ClientMessage,
+        property::{MapState, WindowClass, WmState},
+        ClientAttr, ClientConfig, Prop, WindowAttributes, XConn, XEvent,
+    },
+    Error, Result, Xid,
+};
+use std::{cell::RefCell, collections::HashMap, os::unix::io::RawFd};
+
+/// The requests made to the X server that tests are able to make assertions about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Call {
+    Map(Xid),
+    Unmap(Xid),
+    SetProp(Xid, String, Prop),
+    SetClientAttributes(Xid, Vec<ClientAttr>),
+    SetClientConfig(Xid, Vec<ClientConfig>),
+    SendConfigureNotify(Xid, Rect, u32),
+}
+
+/// An in memory [XConn] that records the requests it is sent.
+///
+/// Properties are stored when set so that they can be read back, and any query that has not
+/// been given a value returns an empty result.
+#[derive(Debug, Default)]
+pub(crate) struct MockXConn {
+    pub(crate) screens: Vec<Rect>,
+    pub(crate) geometry: HashMap<Xid, Rect>,
+    pub(crate) attributes: HashMap<Xid, WindowAttributes>,
+    pub(crate) props: RefCell<HashMap<(Xid, String), Prop>>,
+    pub(crate) calls: RefCell<Vec<Call>>,
+    atoms: RefCell<Vec<String>>,
+}
+
+impl MockXConn {
+    pub(crate) fn new(screens: Vec<Rect>) -> Self {
+        Self {
+            screens,
+            ..Default::default()
+        }
+    }
+
+    /// Set a property on a client window without recording it as a call.
+    pub(crate) fn with_prop(self, id: Xid, name: &str, val: Prop) -> Self {
+        self.props.borrow_mut().insert((id, name.to_owned()), val);
+
+        self
+    }
+
+    /// Take the calls that have been recorded so far.
+    pub(crate) fn take_calls(&self) -> Vec<Call> {
+        std::mem::take(&mut *self.calls.borrow_mut())
+    }
+
+    /// Wrap this connection in a [WindowManager] using the default config.
+    pub(crate) fn into_wm(self) -> WindowManager<Self> {
+        WindowManager::new(
+            Config::default(),
+            KeyBindings::default(),
+            HashMap::new(),
+            self,
+        )
+        .expect("mock window manager to be valid")
+    }
+
+    fn record(&self, call: Call) {
+        self.calls.borrow_mut().push(call);
+    }
+}
+
+impl XConn for MockXConn {
+    fn root(&self) -> Xid {
+        Xid(0)
+    }
+
+    fn screen_details(&self) -> Result<Vec<Rect>> {
+        Ok(self.screens.clone())
+    }
+
+    fn cursor_position(&self) -> Result<Point> {
+        Ok(Point::default())
+    }
+
+    fn grab(&self, _: &[KeyCode], _: &[MouseState]) -> Result<()> {
+        Ok(())
+    }
+
+    fn keyboard_mapping(&self) -> Result<HashMap<u8, Vec<u32>>> {
+        Ok(HashMap::new())
+    }
+
+    fn grab_keyboard(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn ungrab_keyboard(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn grab_pointer(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn ungrab_pointer(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn next_event(&self) -> Result<XEvent> {
+        Err(Error::Custom("no events in MockXConn".to_owned()))
+    }
+
+    fn poll_for_event(&self) -> Result<Option<XEvent>> {
+        Ok(None)
+    }
+
+    fn connection_fd(&self) -> RawFd {
+        -1
+    }
+
+    fn flush(&self) {}
+
+    fn intern_atom(&self, atom: &str) -> Result<Xid> {
+        let mut atoms = self.atoms.borrow_mut();
+        let ix = match atoms.iter().position(|a| a == atom) {
+            Some(ix) => ix,
+            None => {
+                atoms.push(atom.to_owned());
+                atoms.len() - 1
+            }
+        };
+
+        // Offset so that interned atoms do not collide with the root window id
+        Ok(Xid(ix as u32 + 1))
+    }
+
+    fn atom_name(&self, xid: Xid) -> Result<String> {
+        self.atoms
+            .borrow()
+            .get((*xid as usize).wrapping_sub(1))
+            .cloned()
+            .ok_or_else(|| Error::Custom(format!("unknown atom: {xid}")))
+    }
+
+    fn client_geometry(&self, client: Xid) -> Result<Rect> {
+        Ok(self.geometry.get(&client).copied().unwrap_or_default())
+    }
+
+    fn existing_clients(&self) -> Result<Vec<Xid>> {
+        Ok(vec![])
+    }
+
+    fn map(&self, client: Xid) -> Result<()> {
+        self.record(Call::Map(client));
+        Ok(())
+    }
+
+    fn unmap(&self, client: Xid) -> Result<()> {
+        self.record(Call::Unmap(client));
+        Ok(())
+    }
+
+    fn kill(&self, _: Xid) -> Result<()> {
+        Ok(())
+    }
+
+    fn focus(&self, _: Xid) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_prop(&self, client: Xid, prop_name: &str) -> Result<Option<Prop>> {
+        let key = (client, prop_name.to_owned());
+
+        Ok(self.props.borrow().get(&key).cloned())
+    }
+
+    fn list_props(&self, client: Xid) -> Result<Vec<String>> {
+        let props = self.props.borrow();
+
+        Ok(props
+            .keys()
+            .filter(|(id, _)| *id == client)
+            .map(|(_, name)| name.clone())
+            .collect())
+    }
+
+    fn get_wm_state(&self, _: Xid) -> Result<Option<WmState>> {
+        Ok(None)
+    }
+
+    fn get_window_attributes(&self, client: Xid) -> Result<WindowAttributes> {
+        let default = WindowAttributes::new(false, MapState::Viewable, WindowClass::InputOutput);
+
+        Ok(self.attributes.get(&client).cloned().unwrap_or(default))
+    }
+
+    fn set_wm_state(&self, _: Xid, _: WmState) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_prop(&self, client: Xid, name: &str, val: Prop) -> Result<()> {
+        self.props
+            .borrow_mut()
+            .insert((client, name.to_owned()), val.clone());
+        self.record(Call::SetProp(client, name.to_owned(), val));
+
+        Ok(())
+    }
+
+    fn delete_prop(&self, client: Xid, prop_name: &str) -> Result<()> {
+        self.props
+            .borrow_mut()
+            .remove(&(client, prop_name.to_owned()));
+
+        Ok(())
+    }
+
+    fn set_client_attributes(&self, client: Xid, attrs: &[ClientAttr]) -> Result<()> {
+        self.record(Call::SetClientAttributes(client, attrs.to_vec()));
+        Ok(())
+    }
+
+    fn set_client_config(&self, client: Xid, data: &[ClientConfig]) -> Result<()> {
+        self.record(Call::SetClientConfig(client, data.to_vec()));
+        Ok(())
+    }
+
+    fn send_client_message(&self, _: ClientMessage) -> Result<()> {
+        Ok(())
+    }
+
+    fn send_configure_notify(&self, client: Xid, r: Rect, border: u32) -> Result<()> {
+        self.record(Call::SendConfigureNotify(client, r, border));
+        Ok(())
+    }
+
+    fn warp_pointer(&self, _: Xid, _: i16, _: i16) -> Result<()> {
+        Ok(())
+    }
+}
@@ -5,13 +5,20 @@ use crate::{
         bindings::{KeyCode, MouseState},
         ClientSet, Config, State,
     },
-    pure::geometry::{Point, Rect},
-    x::{atom::AUTO_FLOAT_WINDOW_TYPES, event::ClientMessage, property::WmState},
+    pure::geometry::{Point, Rect, RelativeRect, Strut},
+    x::{
+        atom::AUTO_FLOAT_WINDOW_TYPES,
+        event::ClientMessage,
+        property::{ClientProperties, WmState},
+    },
     Color, Result, Xid,
 };
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    os::unix::io::RawFd,
+};
 use tracing::{error, trace};
 
 pub mod atom;
@@ -19,6 +26,9 @@ pub mod event;
 pub mod property;
 pub mod query;
 
+#[cfg(test)]
+pub(crate) mod mock;
+
 pub use atom::Atom;
 pub use event::XEvent;
 pub use property::{Prop, WindowAttributes};
@@ -67,6 +77,12 @@ pub enum ClientAttr {
     ClientUnmapMask,
     /// Set the pre-defined root event mask
     RootEventMask,
+    /// Set the pre-defined event mask for receiving mouse button events on windows owned by the
+    /// window manager itself (such as a status bar)
+    ButtonEventMask,
+    /// Set the pre-defined event mask for receiving property change events on windows that are
+    /// not managed by the window manager (such as docks)
+    PropertyEventMask,
 }
 
 /// A handle on a running X11 connection that we can use for issuing X requests.
@@ -86,9 +102,34 @@ pub trait XConn {
 
     /// Grab the specified key and mouse states, intercepting them for processing within
     /// the window manager itself.
+    ///
+    /// Any existing grabs are replaced.
     fn grab(&self, key_codes: &[KeyCode], mouse_states: &[MouseState]) -> Result<()>;
+    /// Fetch the current keyboard mapping from the X server.
+    ///
+    /// The returned map contains the raw keysym values bound to each key code, in the order
+    /// that they are defined by the X server (unmodified, shifted, ...).
+    fn keyboard_mapping(&self) -> Result<HashMap<u8, Vec<u32>>>;
+    /// Actively grab the keyboard so that all key presses are reported to the window manager
+    /// rather than only those that have been registered via [XConn::grab].
+    fn grab_keyboard(&self) -> Result<()>;
+    /// Release an active keyboard grab made using [XConn::grab_keyboard].
+    fn ungrab_keyboard(&self) -> Result<()>;
+    /// Actively grab the pointer so that all motion and button release events are reported to
+    /// the window manager until [XConn::ungrab_pointer] is called.
+    fn grab_pointer(&self) -> Result<()>;
+    /// Release an active pointer grab made using [XConn::grab_pointer].
+    fn ungrab_pointer(&self) -> Result<()>;
     /// Block and wait for the next event from the X server so it can be processed.
     fn next_event(&self) -> Result<XEvent>;
+    /// Check for a pending event from the X server without blocking, returning `None` if there
+    /// are currently no events waiting to be processed.
+    fn poll_for_event(&self) -> Result<Option<XEvent>>;
+    /// The file descriptor of the underlying connection to the X server.
+    ///
+    /// This is used to wait on X events alongside any additional event sources that have been
+    /// registered with the window manager.
+    fn connection_fd(&self) -> RawFd;
     /// Flush any pending events to the X server.
     fn flush(&self);
 
@@ -132,6 +173,9 @@ pub trait XConn {
     fn set_client_config(&self, client: Xid, data: &[ClientConfig]) -> Result<()>;
     /// Send a [ClientMessage] to a given client.
     fn send_client_message(&self, msg: ClientMessage) -> Result<()>;
+    /// Send a synthetic ConfigureNotify event to a given client informing it of its current
+    /// position and border width, as required by ICCCM when a configure request is not honoured.
+    fn send_configure_notify(&self, client: Xid, r: Rect, border: u32) -> Result<()>;
 
     /// Reposition the mouse cursor to the given (x, y) coordinates within the specified window.
     /// This method should not be called directly: use `warp_pointer_to_window` or `warp_pointer_to_screen`
@@ -161,6 +205,7 @@ pub trait XConnExt: XConn + Sized {
     /// current X state.
     fn unmanage(&self, client: Xid, state: &mut State<Self>) -> Result<()> {
         trace!(?client, "removing client");
+        state.client_props.remove(&client);
         self.modify_and_refresh(state, |cs| {
             cs.remove_client(&client);
         })
@@ -216,13 +261,14 @@ pub trait XConnExt: XConn + Sized {
     {
         f(&mut state.client_set); // NOTE: mutating the existing state
 
-        let ss = state.client_set.position_and_snapshot();
+        let ss = state.client_set.position_and_snapshot(&state.client_props);
         state.diff.update(ss);
 
         notify_killed(self, state)?;
         set_window_props(self, state)?;
         notify_hidden_workspaces(state);
         self.position_clients(state.config.border_width, &state.diff.after.positions)?;
+        raise_docks(self, state)?;
         set_window_visibility(self, state)?;
         set_focus(self, state)?;
         handle_pointer_change(self, state)?;
@@ -451,6 +497,8 @@ pub(crate) fn manage_without_refresh<X: XConn>(
 ) -> Result<()> {
     let should_float = x.client_should_float(id, &state.config.floating_classes)?;
     let r = x.client_geometry(id)?;
+    let props = ClientProperties::for_client(id, x)?;
+    state.client_props.insert(id, props);
 
     match tag {
         Some(tag) => state.client_set.insert_as_focus_for(tag, id),
@@ -473,6 +521,111 @@ pub(crate) fn manage_without_refresh<X: XConn>(
     Ok(())
 }
 
+// Docks and desktop windows are mapped as requested but are never added to the StackSet.
+// Desktops are kept below all other windows and docks above them. Returns true if the window
+// is one of these types and is now being tracked.
+pub(crate) fn track_unmanaged<X: XConn>(id: Xid, state: &mut State<X>, x: &X) -> Result<bool> {
+    match unmanaged_window_type(id, x)? {
+        Some(Atom::NetWindowTypeDesktop) => {
+            trace!(?id, "mapping desktop window");
+            state.desktops.insert(id);
+            x.map(id)?;
+            x.set_client_config(id, &[ClientConfig::StackBottom])?;
+        }
+
+        Some(_) => {
+            trace!(?id, "mapping dock window");
+            state.docks.insert(id);
+            // Property change events are needed to track changes to struts
+            x.set_client_attributes(id, &[ClientAttr::PropertyEventMask])?;
+            if !state.docks_hidden {
+                x.map(id)?;
+                x.set_client_config(id, &[ClientConfig::StackTop])?;
+            }
+        }
+
+        None => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+// Whether the given window is a desktop or dock window that should not be managed
+pub(crate) fn unmanaged_window_type<X: XConn>(id: Xid, x: &X) -> Result<Option<Atom>> {
+    let types = match x.get_prop(id, Atom::NetWmWindowType.as_ref())? {
+        Some(Prop::Atom(types)) => types,
+        _ => return Ok(None),
+    };
+
+    let ty = [Atom::NetWindowTypeDesktop, Atom::NetWindowTypeDock]
+        .into_iter()
+        .find(|a| types.iter().any(|t| t == a.as_ref()));
+
+    Ok(ty)
+}
+
+// Stop tracking a dock or desktop window that has been unmapped or destroyed
+pub(crate) fn untrack_unmanaged<X: XConn>(id: Xid, state: &mut State<X>) {
+    state.docks.remove(&id);
+    state.desktops.remove(&id);
+}
+
+// Update the space reserved on each screen from the struts we are tracking, ignoring any
+// set by docks while they are hidden.
+pub(crate) fn apply_struts<X: XConn>(state: &mut State<X>) {
+    let State {
+        client_set,
+        struts,
+        docks,
+        docks_hidden,
+        ..
+    } = state;
+
+    client_set.set_struts(
+        struts
+            .iter()
+            .filter(|(id, _)| !(*docks_hidden && docks.contains(id)))
+            .map(|(_, s)| s),
+    );
+}
+
+// Read the struts set by the given window (if any) and update the space reserved on each
+// screen, preferring _NET_WM_STRUT_PARTIAL over _NET_WM_STRUT. Returns true if the tracked
+// struts have changed.
+pub(crate) fn update_struts<X: XConn>(id: Xid, state: &mut State<X>, x: &X) -> bool {
+    let strut = [Atom::NetWmStrutPartial, Atom::NetWmStrut]
+        .iter()
+        .find_map(|a| match x.get_prop(id, a.as_ref()) {
+            Ok(Some(Prop::Cardinal(raw))) => Strut::try_from_cardinals(&raw),
+            _ => None,
+        });
+
+    let changed = match strut {
+        Some(s) => state.struts.insert(id, s) != Some(s),
+        None => state.struts.remove(&id).is_some(),
+    };
+
+    if changed {
+        trace!(?id, ?strut, "struts updated");
+        apply_struts(state);
+    }
+
+    changed
+}
+
+// Stop tracking the struts for a window that has been unmapped or destroyed. Returns true
+// if the window had set any struts.
+pub(crate) fn remove_struts<X: XConn>(id: Xid, state: &mut State<X>) -> bool {
+    let removed = state.struts.remove(&id).is_some();
+
+    if removed {
+        trace!(?id, "struts removed");
+        apply_struts(state);
+    }
+
+    removed
+}
+
 fn notify_killed<X: XConn>(x: &X, state: &mut State<X>) -> Result<()> {
     for &c in state.diff.killed_clients() {
         x.kill(c)?;
@@ -554,6 +707,30 @@ fn handle_pointer_change<X: XConn>(x: &X, state: &mut State<X>) -> Result<()> {
     Ok(())
 }
 
+// Restacking clients can place them above docks so we need to raise them again. Fullscreen
+// clients are then raised above the docks so that they are not covered.
+fn raise_docks<X: XConn>(x: &X, state: &mut State<X>) -> Result<()> {
+    if state.docks_hidden {
+        return Ok(());
+    }
+
+    for &id in state.docks.iter() {
+        x.set_client_config(id, &[ClientConfig::StackTop])?;
+    }
+
+    let cs = &state.client_set;
+    let fullscreen = cs
+        .screens()
+        .flat_map(|s| s.workspace.focus())
+        .filter(|&id| cs.floating.get(id) == Some(&RelativeRect::fullscreen()));
+
+    for &id in fullscreen {
+        x.set_client_config(id, &[ClientConfig::StackTop])?;
+    }
+
+    Ok(())
+}
+
 fn set_window_visibility<X: XConn>(x: &X, state: &mut State<X>) -> Result<()> {
     for &c in state.diff.visible_clients() {
         trace!(?c, "revealing client");
@@ -580,3 +757,185 @@ fn set_focus<X: XConn>(x: &X, state: &mut State<X>) -> Result<()> {
         x.focus(state.root)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::x::mock::{Call, MockXConn};
+    use simple_test_case::test_case;
+
+    const DOCK: Xid = Xid(10);
+
+    fn screens() -> Vec<Rect> {
+        vec![Rect::new(0, 0, 1000, 800)]
+    }
+
+    fn with_window_type(x: MockXConn, id: Xid, ty: Atom) -> MockXConn {
+        x.with_prop(
+            id,
+            Atom::NetWmWindowType.as_ref(),
+            Prop::Atom(vec![ty.as_ref().to_owned()]),
+        )
+    }
+
+    // A state with a dock that reserves 10px along the top of the screen
+    fn state_with_dock() -> State<MockXConn> {
+        let mut state = MockXConn::new(screens()).into_wm().state;
+        state.docks.insert(DOCK);
+        let strut = Strut::try_from_cardinals(&[0, 0, 10, 0]).unwrap();
+        state.struts.insert(DOCK, strut);
+        apply_struts(&mut state);
+
+        state
+    }
+
+    fn reserved_top(state: &State<MockXConn>) -> u32 {
+        state.client_set.screens().next().unwrap().reserved().top
+    }
+
+    #[test_case(
+        Some(Atom::NetWindowTypeDock),
+        vec![
+            Call::SetClientAttributes(DOCK, vec![ClientAttr::PropertyEventMask]),
+            Call::Map(DOCK),
+            Call::SetClientConfig(DOCK, vec![ClientConfig::StackTop]),
+        ];
+        "dock"
+    )]
+    #[test_case(
+        Some(Atom::NetWindowTypeDesktop),
+        vec![
+            Call::Map(DOCK),
+            Call::SetClientConfig(DOCK, vec![ClientConfig::StackBottom]),
+        ];
+        "desktop"
+    )]
+    #[test_case(Some(Atom::NetWindowTypeDialog), vec![]; "dialog")]
+    #[test_case(None, vec![]; "no window type")]
+    #[test]
+    fn track_unmanaged_maps_docks_and_desktops(ty: Option<Atom>, expected: Vec<Call>) {
+        let mut x = MockXConn::new(screens());
+        if let Some(ty) = ty {
+            x = with_window_type(x, DOCK, ty);
+        }
+        let mut state = MockXConn::new(screens()).into_wm().state;
+
+        let tracked = track_unmanaged(DOCK, &mut state, &x).unwrap();
+
+        assert_eq!(tracked, !expected.is_empty());
+        assert_eq!(x.take_calls(), expected);
+        assert_eq!(
+            state.docks.contains(&DOCK),
+            ty == Some(Atom::NetWindowTypeDock)
+        );
+        assert_eq!(
+            state.desktops.contains(&DOCK),
+            ty == Some(Atom::NetWindowTypeDesktop)
+        );
+        assert!(!state.client_set.contains(&DOCK));
+    }
+
+    #[test]
+    fn track_unmanaged_does_not_map_docks_while_they_are_hidden() {
+        let x = with_window_type(MockXConn::new(screens()), DOCK, Atom::NetWindowTypeDock);
+        let mut state = MockXConn::new(screens()).into_wm().state;
+        state.docks_hidden = true;
+
+        assert!(track_unmanaged(DOCK, &mut state, &x).unwrap());
+        assert!(state.docks.contains(&DOCK));
+        assert_eq!(
+            x.take_calls(),
+            vec![Call::SetClientAttributes(
+                DOCK,
+                vec![ClientAttr::PropertyEventMask]
+            )]
+        );
+    }
+
+    #[test_case(false, 10; "visible docks")]
+    #[test_case(true, 0; "hidden docks")]
+    #[test]
+    fn apply_struts_ignores_hidden_docks(docks_hidden: bool, expected: u32) {
+        let mut state = state_with_dock();
+        state.docks_hidden = docks_hidden;
+
+        apply_struts(&mut state);
+
+        assert_eq!(reserved_top(&state), expected);
+    }
+
+    #[test]
+    fn apply_struts_keeps_struts_from_other_windows_while_docks_are_hidden() {
+        let mut state = state_with_dock();
+        let strut = Strut::try_from_cardinals(&[0, 0, 5, 0]).unwrap();
+        state.struts.insert(Xid(20), strut);
+        state.docks_hidden = true;
+
+        apply_struts(&mut state);
+
+        assert_eq!(reserved_top(&state), 5);
+    }
+
+    #[test]
+    fn set_docks_visible_maps_and_unmaps_docks() {
+        let x = MockXConn::new(screens());
+        let mut state = state_with_dock();
+
+        state.set_docks_visible(false, &x).unwrap();
+
+        assert!(!state.docks_visible());
+        assert_eq!(reserved_top(&state), 0);
+        assert_eq!(state.pending_unmap.get(&DOCK), Some(&1));
+        assert!(x.take_calls().contains(&Call::Unmap(DOCK)));
+
+        state.set_docks_visible(true, &x).unwrap();
+
+        assert!(state.docks_visible());
+        assert_eq!(reserved_top(&state), 10);
+        let calls = x.take_calls();
+        assert_eq!(
+            &calls[0..2],
+            &[
+                Call::Map(DOCK),
+                Call::SetClientConfig(DOCK, vec![ClientConfig::StackTop])
+            ]
+        );
+    }
+
+    #[test]
+    fn set_docks_visible_is_a_no_op_if_visibility_is_unchanged() {
+        let x = MockXConn::new(screens());
+        let mut state = state_with_dock();
+
+        state.set_docks_visible(true, &x).unwrap();
+
+        assert_eq!(x.take_calls(), vec![]);
+    }
+
+    #[test_case(false, vec![DOCK]; "tiled")]
+    #[test_case(true, vec![DOCK, Xid(1)]; "fullscreen")]
+    #[test]
+    fn raise_docks_keeps_fullscreen_clients_above_docks(fullscreen: bool, expected: Vec<Xid>) {
+        let x = MockXConn::new(screens());
+        let mut state = state_with_dock();
+        state.client_set.insert(Xid(1));
+        if fullscreen {
+            state
+                .client_set
+                .float_unchecked(Xid(1), RelativeRect::fullscreen());
+        }
+
+        raise_docks(&x, &mut state).unwrap();
+
+        let raised: Vec<Xid> = x
+            .take_calls()
+            .into_iter()
+            .filter_map(|c| match c {
+                Call::SetClientConfig(id, conf) if conf == vec![ClientConfig::StackTop] => Some(id),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(raised, expected);
+    }
+}
@@ -1,6 +1,7 @@
 //! Data types for working with X window properties
 use crate::{
     pure::geometry::{Point, Rect},
+    x::{Atom, XConn, XConnExt},
     Error, Result, Xid,
 };
 use bitflags::bitflags;
@@ -164,6 +165,11 @@ impl WmHints {
         }
     }
 
+    /// Whether or not the client has set the urgency hint.
+    pub fn is_urgent(&self) -> bool {
+        self.flags.contains(WmHintsFlags::URGENCY_HINT)
+    }
+
     /// Try to construct a [WmHints] instance from raw bytes.
     ///
     /// This method expects a slice of 9 u32s corresponding to the C struct layout shown below.
@@ -340,6 +346,91 @@ impl WmNormalHints {
     }
 }
 
+/// A cache of the client properties that penrose tracks for each managed client.
+///
+/// These are kept up to date in response to `PropertyNotify` events so that hooks and
+/// status bar widgets are able to make use of them without needing to query the X
+/// server directly. See [State::client_properties][crate::core::State::client_properties].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Default, Debug, PartialEq, Eq, Hash, Clone)]
+pub struct ClientProperties {
+    /// The title of the client following ICCCM / EWMH standards
+    pub title: String,
+    /// The application name of the client (the first string in its WM_CLASS)
+    pub app_name: String,
+    /// The class name of the client (the second string in its WM_CLASS)
+    pub class_name: String,
+    /// Whether or not the client has set the urgency hint in its WM_HINTS
+    pub urgent: bool,
+    /// The WM_NORMAL_HINTS set by the client if there are any
+    pub normal_hints: Option<WmNormalHints>,
+    /// The window that this client is transient for if there is one
+    pub transient_for: Option<Xid>,
+}
+
+impl ClientProperties {
+    /// Fetch the current value of all tracked properties for the given client.
+    pub fn for_client<X: XConn>(id: Xid, x: &X) -> Result<Self> {
+        let mut props = Self::default();
+        for atom in [
+            Atom::WmName,
+            Atom::WmClass,
+            Atom::WmHints,
+            Atom::WmNormalHints,
+            Atom::WmTransientFor,
+        ] {
+            props.update(id, atom, x)?;
+        }
+
+        Ok(props)
+    }
+
+    /// Update the cached value for the given property by querying the X server.
+    ///
+    /// Returns `true` if the cached value changed as a result.
+    pub fn update<X: XConn>(&mut self, id: Xid, atom: Atom, x: &X) -> Result<bool> {
+        let before = self.clone();
+
+        match atom {
+            Atom::WmName | Atom::NetWmName => self.title = x.window_title(id)?,
+
+            Atom::WmClass => {
+                let mut strs = match x.get_prop(id, atom.as_ref())? {
+                    Some(Prop::UTF8String(strs)) => strs.into_iter(),
+                    _ => Vec::new().into_iter(),
+                };
+                self.app_name = strs.next().unwrap_or_default();
+                self.class_name = strs.next().unwrap_or_default();
+            }
+
+            Atom::WmHints => {
+                self.urgent = matches!(
+                    x.get_prop(id, atom.as_ref())?,
+                    Some(Prop::WmHints(hints)) if hints.is_urgent()
+                )
+            }
+
+            Atom::WmNormalHints => {
+                self.normal_hints = match x.get_prop(id, atom.as_ref())? {
+                    Some(Prop::WmNormalHints(hints)) => Some(hints),
+                    _ => None,
+                }
+            }
+
+            Atom::WmTransientFor => {
+                self.transient_for = match x.get_prop(id, atom.as_ref())? {
+                    Some(Prop::Window(ids)) => ids.first().copied(),
+                    _ => None,
+                }
+            }
+
+            _ => (),
+        }
+
+        Ok(*self != before)
+    }
+}
+
 /// Window Attributes honoured by penose.
 ///
 /// Only a small subset of window attributes are checked and honoured by penrose. This list may be
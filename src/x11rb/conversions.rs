@@ -17,7 +17,9 @@ use tracing::warn;
 use x11rb::{
     connection::Connection,
     protocol::{
-        xproto::{ClientMessageEvent, KeyButMask, ModMask},
+        xproto::{
+            ClientMessageEvent, ConnectionExt as _, KeyButMask, KeyPressEvent, Mapping, ModMask,
+        },
         Event,
     },
 };
@@ -29,30 +31,36 @@ pub(crate) fn convert_event<C: Connection>(conn: &Conn<C>, event: Event) -> Resu
         Event::RandrScreenChangeNotify(_) => Ok(Some(XEvent::ScreenChange)),
 
         Event::ButtonPress(event) => Ok(to_mouse_state(event.detail, event.state).map(|state| {
+            let (id, wx, wy) =
+                clicked_window(conn, event.event, event.child, event.event_x, event.event_y);
             XEvent::MouseEvent(MouseEvent::new(
-                Xid(event.event),
+                id,
                 event.root_x,
                 event.root_y,
-                event.event_x,
-                event.event_y,
+                wx,
+                wy,
                 state,
                 MouseEventKind::Press,
             ))
         })),
 
         Event::ButtonRelease(event) => Ok(to_mouse_state(event.detail, event.state).map(|state| {
+            let (id, wx, wy) =
+                clicked_window(conn, event.event, event.child, event.event_x, event.event_y);
             XEvent::MouseEvent(MouseEvent::new(
-                Xid(event.event),
+                id,
                 event.root_x,
                 event.root_y,
-                event.event_x,
-                event.event_y,
+                wx,
+                wy,
                 state,
                 MouseEventKind::Release,
             ))
         })),
 
         // FIXME: The 5 is due to https://github.com/sminez/penrose/issues/113
+        // Motion events are only used while dragging so we avoid the round trip to the X server
+        // that would be needed to find the child window under the pointer.
         Event::MotionNotify(event) => Ok(to_mouse_state(5, event.state).map(|state| {
             XEvent::MouseEvent(MouseEvent::new(
                 Xid(event.event),
@@ -65,16 +73,9 @@ pub(crate) fn convert_event<C: Connection>(conn: &Conn<C>, event: Event) -> Resu
             ))
         })),
 
-        Event::KeyPress(event) => {
-            let code = KeyCode {
-                mask: event.state.into(),
-                code: event.detail,
-            };
-            let numlock = ModMask::M2;
-            Ok(Some(XEvent::KeyPress(
-                code.ignoring_modifier(numlock.into()),
-            )))
-        }
+        Event::KeyPress(event) => Ok(Some(XEvent::KeyPress(to_key_code(event)))),
+
+        Event::KeyRelease(event) => Ok(Some(XEvent::KeyRelease(to_key_code(event)))),
 
         Event::MapRequest(event) => Ok(Some(XEvent::MapRequest(Xid(event.window)))),
 
@@ -137,6 +138,11 @@ pub(crate) fn convert_event<C: Connection>(conn: &Conn<C>, event: Event) -> Resu
             is_root: event.window == *conn.root(),
         }))),
 
+        // Changes to the pointer mapping don't affect our bindings
+        Event::MappingNotify(event) if event.request != Mapping::POINTER => {
+            Ok(Some(XEvent::MappingNotify))
+        }
+
         Event::Error(err) => Err(Error::X11rbX11Error(err)),
 
         // NOTE: Ignoring other event types
@@ -144,6 +150,43 @@ pub(crate) fn convert_event<C: Connection>(conn: &Conn<C>, event: Event) -> Resu
     }
 }
 
+// Button bindings are grabbed on the root window so the window that was actually clicked
+// is the child window under the pointer (if there is one). Event coordinates are converted
+// to be relative to that window, which requires a round trip to the X server so this is only
+// done for button presses and releases.
+fn clicked_window<C: Connection>(
+    conn: &Conn<C>,
+    window: u32,
+    child: u32,
+    event_x: i16,
+    event_y: i16,
+) -> (Xid, i16, i16) {
+    if child == x11rb::NONE {
+        return (Xid(window), event_x, event_y);
+    }
+
+    match conn
+        .connection()
+        .translate_coordinates(window, child, event_x, event_y)
+        .map_err(Error::from)
+        .and_then(|cookie| Ok(cookie.reply()?))
+    {
+        Ok(reply) => (Xid(child), reply.dst_x, reply.dst_y),
+        Err(_) => (Xid(window), event_x, event_y),
+    }
+}
+
+// KeyRelease events share the same structure as KeyPress events
+fn to_key_code(event: KeyPressEvent) -> KeyCode {
+    let code = KeyCode {
+        mask: event.state.into(),
+        code: event.detail,
+    };
+    let numlock = ModMask::M2;
+
+    code.ignoring_modifier(numlock.into())
+}
+
 fn to_mouse_state(detail: u8, state: KeyButMask) -> Option<MouseState> {
     fn is_held(key: &ModifierKey, mask: u16) -> bool {
         mask & u16::from(*key) > 0
@@ -28,18 +28,24 @@ use crate::{
     },
     Error, Result, Xid,
 };
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::HashMap,
+    os::unix::io::{AsRawFd, RawFd},
+    str::FromStr,
+};
 use strum::IntoEnumIterator;
-use tracing::error;
+use tracing::{error, warn};
 use x11rb::{
     connection::Connection,
     protocol::{
         randr::{self, ConnectionExt as _, NotifyMask},
+        xkb::{self, ConnectionExt as _},
         xproto::{
-            AtomEnum, ChangeWindowAttributesAux, ClientMessageData, ClientMessageEvent,
-            ColormapAlloc, ConfigureWindowAux, ConnectionExt as _, CreateWindowAux, EventMask,
-            GrabMode, InputFocus, MapState, ModMask, PropMode, StackMode, WindowClass,
-            CLIENT_MESSAGE_EVENT,
+            AtomEnum, ButtonIndex, ChangeWindowAttributesAux, ClientMessageData,
+            ClientMessageEvent, ColormapAlloc, ConfigureNotifyEvent, ConfigureWindowAux,
+            ConnectionExt as _, CreateWindowAux, EventMask, Grab, GrabMode, GrabStatus, InputFocus,
+            MapState, ModMask, PropMode, StackMode, WindowClass, CLIENT_MESSAGE_EVENT,
+            CONFIGURE_NOTIFY_EVENT,
         },
     },
     rust_connection::RustConnection,
@@ -94,6 +100,7 @@ pub struct Conn<C: Connection> {
     conn: C,
     root: u32,
     atoms: Atoms,
+    fd: RawFd,
 }
 
 /// A pure rust based connection to the X server using a [RustConnection].
@@ -104,8 +111,9 @@ impl Conn<RustConnection> {
     /// [x11rb::rust_connection::RustConnection].
     pub fn new() -> Result<Self> {
         let (conn, _) = RustConnection::connect(None).map_err(Error::from)?;
+        let fd = conn.stream().as_raw_fd();
 
-        Self::new_for_connection(conn)
+        Self::new_for_connection(conn, fd)
     }
 }
 
@@ -117,8 +125,9 @@ impl Conn<XCBConnection> {
     /// [x11rb::xcb_ffi::XCBConnection].
     pub fn new() -> Result<Self> {
         let (conn, _) = XCBConnection::connect(None).map_err(Error::from)?;
+        let fd = conn.as_raw_fd();
 
-        Self::new_for_connection(conn)
+        Self::new_for_connection(conn, fd)
     }
 }
 
@@ -126,7 +135,7 @@ impl<C> Conn<C>
 where
     C: Connection,
 {
-    fn new_for_connection(conn: C) -> Result<Self> {
+    fn new_for_connection(conn: C, fd: RawFd) -> Result<Self> {
         let root = conn.setup().roots[0].root;
         conn.prefetch_extension_information(randr::X11_EXTENSION_NAME)?;
         let atoms = Atoms::new(&conn)?;
@@ -150,13 +159,54 @@ where
         let mask = NotifyMask::OUTPUT_CHANGE | NotifyMask::CRTC_CHANGE | NotifyMask::SCREEN_CHANGE;
         conn.randr_select_input(root, mask)?;
 
-        let xconn = Self { conn, root, atoms };
+        let xconn = Self {
+            conn,
+            root,
+            atoms,
+            fd,
+        };
 
         xconn.set_client_attributes(Xid(root), &[ClientAttr::RootEventMask])?;
+        xconn.enable_detectable_auto_repeat();
 
         Ok(xconn)
     }
 
+    // By default, holding down a key generates a stream of synthetic KeyRelease / KeyPress
+    // pairs. With detectable auto-repeat enabled we only see repeated KeyPress events and a
+    // single KeyRelease when the key is actually released. This is best effort: if XKB is not
+    // available then repeated key presses will be reported as distinct presses.
+    fn enable_detectable_auto_repeat(&self) {
+        let flag = xkb::PerClientFlag::DETECTABLE_AUTO_REPEAT;
+        let res = self
+            .conn
+            .xkb_use_extension(1, 0)
+            .map_err(Error::from)
+            .and_then(|cookie| Ok(cookie.reply()?))
+            .and_then(|reply| {
+                if !reply.supported {
+                    return Err(Error::Custom("XKB extension not supported".to_string()));
+                }
+
+                self.conn
+                    .xkb_per_client_flags(
+                        xkb::ID::USE_CORE_KBD.into(),
+                        flag,
+                        flag,
+                        0u32.into(),
+                        0u32.into(),
+                        0u32.into(),
+                    )?
+                    .reply()?;
+
+                Ok(())
+            });
+
+        if let Err(e) = res {
+            warn!(%e, "unable to enable detectable auto-repeat");
+        }
+    }
+
     /// Get a handle to the underlying connection.
     pub fn connection(&self) -> &C {
         &self.conn
@@ -278,6 +328,11 @@ where
         let mode = GrabMode::ASYNC;
         let mask = EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE | EventMask::BUTTON_MOTION;
 
+        // Drop any existing grabs so that stale bindings are removed when re-grabbing
+        self.conn.ungrab_key(Grab::ANY, self.root, ModMask::ANY)?;
+        self.conn
+            .ungrab_button(ButtonIndex::ANY, self.root, ModMask::ANY)?;
+
         for m in modifiers.iter() {
             for k in key_codes.iter() {
                 self.conn.grab_key(
@@ -313,6 +368,91 @@ where
         Ok(())
     }
 
+    fn grab_keyboard(&self) -> Result<()> {
+        let status = self
+            .conn
+            .grab_keyboard(
+                false,
+                self.root,
+                CURRENT_TIME,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+            )?
+            .reply()?
+            .status;
+
+        if status != GrabStatus::SUCCESS {
+            return Err(Error::UnableToGrabKeyboard {
+                reason: format!("{status:?}"),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn ungrab_keyboard(&self) -> Result<()> {
+        self.conn.ungrab_keyboard(CURRENT_TIME)?;
+
+        Ok(())
+    }
+
+    fn grab_pointer(&self) -> Result<()> {
+        let mask = EventMask::BUTTON_RELEASE | EventMask::POINTER_MOTION;
+        let status = self
+            .conn
+            .grab_pointer(
+                false,
+                self.root,
+                mask,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+                x11rb::NONE,
+                x11rb::NONE,
+                CURRENT_TIME,
+            )?
+            .reply()?
+            .status;
+
+        if status != GrabStatus::SUCCESS {
+            return Err(Error::UnableToGrabPointer {
+                reason: format!("{status:?}"),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn ungrab_pointer(&self) -> Result<()> {
+        self.conn.ungrab_pointer(CURRENT_TIME)?;
+
+        Ok(())
+    }
+
+    fn keyboard_mapping(&self) -> Result<HashMap<u8, Vec<u32>>> {
+        let setup = self.conn.setup();
+        let (min, max) = (setup.min_keycode, setup.max_keycode);
+        let reply = self
+            .conn
+            .get_keyboard_mapping(min, max - min + 1)?
+            .reply()?;
+
+        let per_code = reply.keysyms_per_keycode as usize;
+        if per_code == 0 {
+            return Ok(HashMap::new());
+        }
+
+        let m = (min..=max)
+            .zip(reply.keysyms.chunks(per_code))
+            .map(|(code, syms)| {
+                // NoSymbol is 0
+                let syms = syms.iter().copied().filter(|&s| s != 0).collect();
+                (code, syms)
+            })
+            .collect();
+
+        Ok(m)
+    }
+
     fn next_event(&self) -> Result<XEvent> {
         loop {
             let event = self.conn.wait_for_event()?;
@@ -322,6 +462,20 @@ where
         }
     }
 
+    fn poll_for_event(&self) -> Result<Option<XEvent>> {
+        while let Some(event) = self.conn.poll_for_event()? {
+            if let Some(event) = convert_event(self, event)? {
+                return Ok(Some(event));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn connection_fd(&self) -> RawFd {
+        self.fd
+    }
+
     fn flush(&self) {
         self.conn.flush().unwrap_or(());
     }
@@ -624,8 +778,14 @@ where
         let root_event_mask = EventMask::PROPERTY_CHANGE
             | EventMask::SUBSTRUCTURE_REDIRECT
             | EventMask::SUBSTRUCTURE_NOTIFY
+            | EventMask::BUTTON_PRESS
+            | EventMask::BUTTON_RELEASE
             | EventMask::BUTTON_MOTION;
 
+        let button_event_mask = EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE;
+
+        let property_event_mask = EventMask::PROPERTY_CHANGE;
+
         let mut aux = ChangeWindowAttributesAux::new();
         for conf in attrs.iter() {
             match conf {
@@ -633,6 +793,8 @@ where
                 ClientAttr::ClientEventMask => aux = aux.event_mask(client_event_mask),
                 ClientAttr::ClientUnmapMask => aux = aux.event_mask(client_unmap_mask),
                 ClientAttr::RootEventMask => aux = aux.event_mask(root_event_mask),
+                ClientAttr::ButtonEventMask => aux = aux.event_mask(button_event_mask),
+                ClientAttr::PropertyEventMask => aux = aux.event_mask(property_event_mask),
             }
         }
         self.conn.change_window_attributes(*id, &aux)?;
@@ -685,6 +847,27 @@ where
         Ok(())
     }
 
+    fn send_configure_notify(&self, id: Xid, r: Rect, border: u32) -> Result<()> {
+        let event = ConfigureNotifyEvent {
+            response_type: CONFIGURE_NOTIFY_EVENT,
+            sequence: 0,
+            event: *id,
+            window: *id,
+            above_sibling: x11rb::NONE,
+            x: r.x as i16,
+            y: r.y as i16,
+            width: r.w as u16,
+            height: r.h as u16,
+            border_width: border as u16,
+            override_redirect: false,
+        };
+
+        self.conn
+            .send_event(false, *id, EventMask::STRUCTURE_NOTIFY, event)?;
+
+        Ok(())
+    }
+
     fn warp_pointer(&self, id: Xid, x: i16, y: i16) -> Result<()> {
         self.conn.warp_pointer(x11rb::NONE, *id, 0, 0, 0, 0, x, y)?;
 